@@ -5,8 +5,54 @@ fn commit_hash() -> String {
         .unwrap();
     String::from_utf8(output.stdout).unwrap()
 }
+/// Every command registered in `src/cmd/mod.rs`. Declaring them makes
+/// them subject to the capabilities, so a window can only call the ones its
+/// capability allows with `allow-<command>`.
+const COMMANDS: &[&str] = &[
+    "show_main_window",
+    "get_effective_config",
+    "show_log_viewer",
+    "get_logs",
+    "export_logs",
+    "install_module",
+    "install_module_from",
+    "uninstall_module",
+    "list_modules",
+    "set_module_version",
+    "rollback_module",
+    "resolve_module",
+    "show_palette",
+    "hide_palette",
+    "run_module",
+    "list_jobs",
+    "get_job",
+    "list_queues",
+    "retry_job",
+    "delete_job",
+    "list_settings",
+    "get_setting",
+    "set_setting",
+    "list_crash_reports",
+    "redact_crash_report",
+    "send_crash_report",
+    "delete_crash_report",
+    "is_ok_sharing_usage_data",
+    "check_for_updates",
+    "get_update_offer",
+    "respond_to_update",
+    "open_window",
+    "close_window",
+    "list_windows",
+    "export_workspace",
+    "import_workspace",
+];
+
 fn main() {
     let hash = commit_hash();
     println!("cargo:rustc-env=COMMIT_HASH={}", hash);
-    tauri_build::build();
+    tauri_build::try_build(
+        tauri_build::Attributes::new()
+            .app_manifest(tauri_build::AppManifest::new().commands(COMMANDS)),
+    )
+    .expect("failed to run tauri-build");
 }
@@ -0,0 +1,175 @@
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use clap::Subcommand;
+use tauri::{AppHandle, Manager};
+
+use crate::cmd::CommandError;
+use crate::functions;
+use crate::instance::InstanceLock;
+use crate::modules::{ModuleSource, ModuleStore};
+use crate::runtime;
+use crate::telemetry::Telemetry;
+use crate::updater;
+use crate::workspace::{self, WorkspaceError, WorkspacePaths};
+
+/// Commands that run headless and exit instead of starting the app.
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Export the local workspace to an archive
+    ExportWorkspace {
+        /// Destination archive, e.g. workspace.tar.gz
+        path: PathBuf,
+    },
+    /// Replace the local workspace with the contents of an archive
+    ImportWorkspace {
+        /// Archive created by `export-workspace`
+        path: PathBuf,
+        /// Overwrite an existing workspace without asking
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Install a module from an archive, a directory or a local git repository
+    InstallModule {
+        /// A .tar.gz, .tgz or .zip file, a module directory, or a git
+        /// repository path or file:// URL
+        source: String,
+        /// Branch, tag or commit to install when the source is a git repository
+        #[arg(long = "ref")]
+        reference: Option<String>,
+    },
+    /// Send a test event to the configured Sentry DSN, e.g. a local mock
+    /// endpoint
+    SendTestEvent,
+    /// Install the latest signed functions bundle from the functions
+    /// endpoint, used from the next start
+    UpdateFunctions,
+    /// Install an app update from a downloaded manifest (latest.json) and
+    /// bundle, for machines without access to the update server
+    InstallUpdate {
+        /// The update manifest
+        manifest: PathBuf,
+        /// The update bundle, by default the file named in the manifest next
+        /// to it
+        #[arg(long)]
+        bundle: Option<PathBuf>,
+    },
+}
+
+pub fn run(app_handle: AppHandle, command: Command) {
+    tauri::async_runtime::spawn(async move {
+        let code = match execute(&app_handle, command).await {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                1
+            }
+        };
+        app_handle.exit(code);
+    });
+}
+
+async fn execute(app_handle: &AppHandle, command: Command) -> Result<(), CommandError> {
+    let paths = WorkspacePaths::resolve(app_handle)?;
+    match command {
+        Command::ExportWorkspace { path } => {
+            let app_version = app_handle.package_info().version.to_string();
+            let summary = tauri::async_runtime::spawn_blocking(move || {
+                workspace::export(&paths, &app_version, &path)
+            })
+            .await??;
+            println!(
+                "exported {} files ({} bytes) to {}",
+                summary.files,
+                summary.bytes,
+                summary.path.display()
+            );
+        }
+        Command::ImportWorkspace { path, yes } => {
+            let _lock = lock_workspace(app_handle).await?;
+            let import = |overwrite| {
+                let (paths, path) = (paths.clone(), path.clone());
+                tauri::async_runtime::spawn_blocking(move || {
+                    workspace::import(&paths, &path, overwrite)
+                })
+            };
+            let summary = match import(yes).await? {
+                Err(WorkspaceError::WouldOverwrite) if confirm("Replace the existing workspace?")? => {
+                    import(true).await??
+                }
+                result => result?,
+            };
+            println!(
+                "imported {} files from a Craftgen {} workspace",
+                summary.files, summary.app_version
+            );
+            if let Some(backup) = summary.backup {
+                println!("the previous workspace was moved to {}", backup.display());
+            }
+        }
+        Command::InstallModule { source, reference } => {
+            let source = ModuleSource::detect(&source, reference);
+            let store = ModuleStore::new(paths.modules);
+            let module =
+                tauri::async_runtime::spawn_blocking(move || store.install_from(&source))
+                    .await??;
+            println!(
+                "installed {}@{} to {}",
+                module.manifest.name,
+                module.manifest.version,
+                module.path.display()
+            );
+        }
+        Command::SendTestEvent => {
+            let id = app_handle.state::<Telemetry>().send_test_event().await?;
+            println!("sent test event {}", id);
+        }
+        Command::UpdateFunctions => match functions::update(app_handle, false).await? {
+            Some(version) => println!(
+                "installed functions {}, they are used from the next start",
+                version
+            ),
+            None => println!("functions are up to date"),
+        },
+        Command::InstallUpdate { manifest, bundle } => {
+            let version =
+                updater::install_from_file(app_handle, &manifest, bundle.as_deref()).await?;
+            println!("installed Craftgen {}, it is used from the next start", version);
+        }
+    }
+    Ok(())
+}
+
+/// Refuses to go on while the app or its runtime uses the workspace, and
+/// holds the app's lock until the returned one is dropped.
+async fn lock_workspace(app_handle: &AppHandle) -> Result<InstanceLock, CommandError> {
+    let in_use = || {
+        CommandError::Runtime(
+            "Craftgen is running, quit it first or import the workspace from the app".to_string(),
+        )
+    };
+    let lock = InstanceLock::try_acquire(&app_handle.path().app_data_dir()?)?.ok_or_else(in_use)?;
+    if runtime::is_answering(app_handle).await {
+        return Err(in_use());
+    }
+    Ok(lock)
+}
+
+/// Release builds use the windows subsystem and have no console, so a
+/// command started from a terminal would print nothing and `confirm` could
+/// not read an answer. Fails without harm when there is no parent console.
+#[cfg(windows)]
+pub fn attach_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+    // SAFETY: takes no pointers, only changes the console of this process.
+    unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+fn confirm(question: &str) -> io::Result<bool> {
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
@@ -0,0 +1,58 @@
+use serde::Serialize;
+
+use crate::workspace::WorkspaceError;
+
+/// Error returned by every command.
+///
+/// Serialized as `{ kind, message }` so the frontend can branch on `kind` and
+/// show `message` to the user.
+#[derive(Debug, thiserror::Error, Serialize, specta::Type)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum CommandError {
+    /// The request was malformed or referred to something invalid.
+    #[error("{0}")]
+    InvalidInput(String),
+    /// The operation would destroy data and has to be retried with explicit
+    /// confirmation.
+    #[error("{0}")]
+    ConfirmationRequired(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    Io(String),
+    /// Tauri, a window or the edge runtime failed.
+    #[error("{0}")]
+    Runtime(String),
+}
+
+impl From<std::io::Error> for CommandError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::NotFound => CommandError::NotFound(e.to_string()),
+            _ => CommandError::Io(e.to_string()),
+        }
+    }
+}
+
+impl From<tauri::Error> for CommandError {
+    fn from(e: tauri::Error) -> Self {
+        CommandError::Runtime(e.to_string())
+    }
+}
+
+impl From<WorkspaceError> for CommandError {
+    fn from(e: WorkspaceError) -> Self {
+        match e {
+            WorkspaceError::Io(e) => e.into(),
+            WorkspaceError::Tauri(e) => e.into(),
+            WorkspaceError::WouldOverwrite => CommandError::ConfirmationRequired(e.to_string()),
+            WorkspaceError::Manifest(_)
+            | WorkspaceError::MissingManifest
+            | WorkspaceError::UnsupportedVersion(_)
+            | WorkspaceError::UnsafePath(_)
+            | WorkspaceError::Checksum(_)
+            | WorkspaceError::MissingFile(_)
+            | WorkspaceError::UnlistedFile(_) => CommandError::InvalidInput(e.to_string()),
+        }
+    }
+}
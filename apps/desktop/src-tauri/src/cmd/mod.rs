@@ -0,0 +1,201 @@
+mod error;
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+
+pub use error::CommandError;
+
+pub const MAIN_WINDOW: &str = "main";
+
+/// Every command and event exposed to the frontend. `src/bindings.ts` is
+/// generated from these lists, so anything missing here is neither callable
+/// nor typed.
+pub fn builder() -> tauri_specta::Builder {
+    tauri_specta::Builder::<tauri::Wry>::new().commands(tauri_specta::collect_commands![
+        show_main_window,
+        crate::config::get_effective_config,
+        crate::logs::show_log_viewer,
+        crate::logs::get_logs,
+        crate::logs::export_logs,
+        crate::modules::install_module,
+        crate::modules::install_module_from,
+        crate::modules::uninstall_module,
+        crate::modules::list_modules,
+        crate::modules::set_module_version,
+        crate::modules::rollback_module,
+        crate::modules::resolve_module,
+        crate::palette::show_palette,
+        crate::palette::hide_palette,
+        crate::palette::run_module,
+        crate::queue::list_jobs,
+        crate::queue::get_job,
+        crate::queue::list_queues,
+        crate::queue::retry_job,
+        crate::queue::delete_job,
+        crate::settings::list_settings,
+        crate::settings::get_setting,
+        crate::settings::set_setting,
+        crate::telemetry::list_crash_reports,
+        crate::telemetry::redact_crash_report,
+        crate::telemetry::send_crash_report,
+        crate::telemetry::delete_crash_report,
+        crate::tray::is_ok_sharing_usage_data,
+        crate::updater::check_for_updates,
+        crate::updater::get_update_offer,
+        crate::updater::respond_to_update,
+        crate::windows::open_window,
+        crate::windows::close_window,
+        crate::windows::list_windows,
+        crate::workspace::export_workspace,
+        crate::workspace::import_workspace,
+    ])
+    .events(tauri_specta::collect_events![
+        Navigate,
+        crate::logs::LogsAppended,
+        crate::settings::SettingChanged,
+        crate::updater::UpdateFailed,
+        crate::updater::UpdateOffer,
+        crate::updater::UpdateProgress
+    ])
+}
+
+#[cfg(any(debug_assertions, test))]
+const BINDINGS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../src/bindings.ts");
+
+/// Regenerates the TypeScript bindings in `apps/desktop/src/bindings.ts`.
+/// Runs on every debug build so `pnpm tauri dev` keeps the frontend in sync.
+#[cfg(debug_assertions)]
+pub fn export_bindings(builder: &tauri_specta::Builder) {
+    write_bindings(builder, std::path::Path::new(BINDINGS));
+}
+
+#[cfg(any(debug_assertions, test))]
+fn write_bindings(builder: &tauri_specta::Builder, path: &std::path::Path) {
+    use specta_typescript::{BigIntExportBehavior, Typescript};
+
+    builder
+        .export(
+            Typescript::default()
+                .header("/* eslint-disable */")
+                .bigint(BigIntExportBehavior::Number),
+            path,
+        )
+        .expect("failed to export typescript bindings");
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn show_main_window(app_handle: AppHandle) -> Result<(), CommandError> {
+    open_main_window(&app_handle)
+}
+
+/// Asks the main window to show a frontend route. Secondary windows, see
+/// [`crate::windows`], don't get it.
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type, tauri_specta::Event)]
+pub struct Navigate {
+    pub path: String,
+}
+
+/// Shows the main window on the frontend route `path`, whether or not it was
+/// already open.
+pub fn open_main_window_on(app_handle: &AppHandle, path: &str) -> Result<(), CommandError> {
+    if open_main_window_at(app_handle, path)? {
+        Navigate {
+            path: path.to_string(),
+        }
+        .emit_to(app_handle, MAIN_WINDOW)?;
+    }
+    Ok(())
+}
+
+pub fn open_main_window(app_handle: &AppHandle) -> Result<(), CommandError> {
+    open_main_window_at(app_handle, "/").map(|_| ())
+}
+
+/// Shows the main window, creating it on the frontend route `path` if it
+/// isn't open yet. Returns whether the window already existed, in which case
+/// it is left on its current route.
+pub fn open_main_window_at(app_handle: &AppHandle, path: &str) -> Result<bool, CommandError> {
+    #[cfg(target_os = "macos")]
+    {
+        crate::dock::set_dock_visible(true);
+    }
+
+    if let Some(window) = app_handle.get_webview_window(MAIN_WINDOW) {
+        window.show()?;
+        window.set_focus()?;
+        Ok(true)
+    } else {
+        let url = tauri::WebviewUrl::App(PathBuf::from(path));
+        // Same defaults as the window in tauri.conf.json, until the user
+        // resizes it.
+        let window = tauri::WebviewWindowBuilder::new(app_handle, MAIN_WINDOW, url)
+            .title("Craftgen")
+            .inner_size(1200.0, 800.0)
+            .min_inner_size(600.0, 400.0)
+            .visible(false)
+            .build()?;
+        track_main_window(&window);
+        Ok(false)
+    }
+}
+
+/// Keeps the main window's size and position, and hides it instead of
+/// closing it while the app keeps running in the background. Otherwise the
+/// hidden palette goes with it, so that the app exits.
+pub fn track_main_window(window: &tauri::WebviewWindow) {
+    crate::window_state::track(window);
+    let handle = window.clone();
+    window.on_window_event(move |event| {
+        let keep_running = || crate::runtime::keep_running_in_background(handle.app_handle());
+        let api = match event {
+            tauri::WindowEvent::CloseRequested { api, .. } if keep_running() => api,
+            tauri::WindowEvent::Destroyed if !keep_running() => {
+                crate::palette::close_palette(handle.app_handle());
+                return;
+            }
+            _ => return,
+        };
+        api.prevent_close();
+        if let Err(e) = handle.hide() {
+            log::error!("Failed to hide main window: {}", e);
+        }
+        #[cfg(target_os = "macos")]
+        {
+            let others_visible = handle
+                .app_handle()
+                .webview_windows()
+                .values()
+                .any(|window| {
+                    window.label() != MAIN_WINDOW && window.is_visible().unwrap_or(false)
+                });
+            if !others_visible {
+                crate::dock::set_dock_visible(false);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Release builds don't regenerate the bindings, so a command changed
+    /// without a debug build would leave the frontend calling the old one.
+    #[test]
+    fn bindings_are_up_to_date() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("bindings.ts");
+        write_bindings(&builder(), &path);
+
+        let expected = std::fs::read_to_string(path).unwrap();
+        let committed = std::fs::read_to_string(BINDINGS).unwrap();
+        assert!(
+            committed == expected,
+            "src/bindings.ts is out of date, a debug build (e.g. `pnpm tauri dev`) regenerates it"
+        );
+    }
+}
@@ -0,0 +1,865 @@
+//! Layered configuration for the desktop shell.
+//!
+//! Every value is resolved with the precedence
+//! `defaults < craftgen.toml < environment variables < CLI flags`, and the
+//! layer that won is remembered so it can be reported back to the user. An
+//! invalid value is ignored with its error kept for that key, the layers below
+//! it still apply.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use sentry::types::Dsn;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tauri::Url;
+
+use crate::updater::Channel;
+use crate::Args;
+
+pub const CONFIG_FILE_NAME: &str = "craftgen.toml";
+
+const DEFAULT_RUNTIME_PORT: u16 = 24321;
+const DEFAULT_SENTRY_DSN: &str = "https://9d430bec9a3518bcb0c34c7f8b9fe1d8@o4507501119799296.ingest.us.sentry.io/4507501176029184";
+const DEFAULT_UPDATE_ENDPOINT: &str =
+    "https://github.com/craftgen/craftgen/releases/latest/download/latest.json";
+// Pre-releases are published under rolling `beta` and `nightly` tags, GitHub's
+// `latest` only ever points at a stable release.
+const DEFAULT_BETA_UPDATE_ENDPOINT: &str =
+    "https://github.com/craftgen/craftgen/releases/download/beta/latest.json";
+const DEFAULT_NIGHTLY_UPDATE_ENDPOINT: &str =
+    "https://github.com/craftgen/craftgen/releases/download/nightly/latest.json";
+const DEFAULT_UPDATE_CHECK_INTERVAL_HOURS: u64 = 6;
+const DEFAULT_FUNCTIONS_ENDPOINT: &str =
+    "https://github.com/craftgen/craftgen/releases/latest/download/functions.json";
+
+const ENV_CONFIG_PATH: &str = "CRAFTGEN_CONFIG";
+const ENV_RUNTIME_PORT: &str = "CRAFTGEN_RUNTIME_PORT";
+const ENV_RUNTIME_ARGS: &str = "CRAFTGEN_RUNTIME_ARGS";
+const ENV_SENTRY_DSN: &str = "CRAFTGEN_SENTRY_DSN";
+const ENV_UPDATE_ENDPOINT: &str = "CRAFTGEN_UPDATE_ENDPOINT";
+const ENV_BETA_UPDATE_ENDPOINT: &str = "CRAFTGEN_BETA_UPDATE_ENDPOINT";
+const ENV_NIGHTLY_UPDATE_ENDPOINT: &str = "CRAFTGEN_NIGHTLY_UPDATE_ENDPOINT";
+const ENV_UPDATE_CHECK_INTERVAL: &str = "CRAFTGEN_UPDATE_CHECK_INTERVAL_HOURS";
+const ENV_FUNCTIONS_ENDPOINT: &str = "CRAFTGEN_FUNCTIONS_ENDPOINT";
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub runtime: RuntimeConfig,
+    pub telemetry: TelemetryConfig,
+    pub updater: UpdaterConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct RuntimeConfig {
+    /// Port the edge-runtime listens on.
+    pub port: u16,
+    /// Extra arguments appended to `edge-runtime start`.
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    /// `None` disables crash reporting entirely.
+    pub sentry_dsn: Option<Dsn>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UpdaterConfig {
+    /// Manifest of the stable channel.
+    pub endpoint: Url,
+    pub beta_endpoint: Url,
+    pub nightly_endpoint: Url,
+    /// How often to check for updates in the background, `None` disables
+    /// background checks.
+    pub check_interval: Option<Duration>,
+    /// Manifest of signed functions bundles, checked with the app updates.
+    pub functions_endpoint: Url,
+}
+
+impl UpdaterConfig {
+    pub fn endpoint(&self, channel: Channel) -> &Url {
+        match channel {
+            Channel::Stable => &self.endpoint,
+            Channel::Beta => &self.beta_endpoint,
+            Channel::Nightly => &self.nightly_endpoint,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            runtime: RuntimeConfig {
+                port: DEFAULT_RUNTIME_PORT,
+                args: Vec::new(),
+            },
+            telemetry: TelemetryConfig {
+                sentry_dsn: Some(DEFAULT_SENTRY_DSN.parse().expect("invalid default DSN")),
+            },
+            updater: UpdaterConfig {
+                endpoint: DEFAULT_UPDATE_ENDPOINT
+                    .parse()
+                    .expect("invalid default update endpoint"),
+                beta_endpoint: DEFAULT_BETA_UPDATE_ENDPOINT
+                    .parse()
+                    .expect("invalid default beta update endpoint"),
+                nightly_endpoint: DEFAULT_NIGHTLY_UPDATE_ENDPOINT
+                    .parse()
+                    .expect("invalid default nightly update endpoint"),
+                check_interval: check_interval(DEFAULT_UPDATE_CHECK_INTERVAL_HOURS),
+                functions_endpoint: DEFAULT_FUNCTIONS_ENDPOINT
+                    .parse()
+                    .expect("invalid default functions endpoint"),
+            },
+        }
+    }
+}
+
+/// Ordered by precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, specta::Type)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    Default,
+    File,
+    Env,
+    Cli,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConfigSource::Default => "defaults",
+            ConfigSource::File => CONFIG_FILE_NAME,
+            ConfigSource::Env => "environment",
+            ConfigSource::Cli => "command line",
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed to read {}: {error}", path.display())]
+    Read {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    #[error("failed to parse {}: {error}", path.display())]
+    Parse {
+        path: PathBuf,
+        error: toml::de::Error,
+    },
+    #[error("invalid value for `{key}` (from {origin}): {message}")]
+    Invalid {
+        key: &'static str,
+        origin: ConfigSource,
+        message: String,
+    },
+    #[error("unknown key `{0}` in {CONFIG_FILE_NAME}")]
+    Unknown(String),
+}
+
+/// The effective configuration together with where each value came from.
+#[derive(Debug, Clone)]
+pub struct LoadedConfig {
+    pub config: Config,
+    pub path: PathBuf,
+    pub file_found: bool,
+    sources: BTreeMap<&'static str, ConfigSource>,
+    /// Set when `craftgen.toml` couldn't be read or parsed, none of its values
+    /// were used then.
+    pub error: Option<String>,
+    /// Why a layer's value for a key was ignored, by key and layer.
+    errors: BTreeMap<String, BTreeMap<ConfigSource, String>>,
+}
+
+impl LoadedConfig {
+    fn defaults(path: PathBuf) -> Self {
+        Self {
+            config: Config::default(),
+            path,
+            file_found: false,
+            sources: BTreeMap::new(),
+            error: None,
+            errors: BTreeMap::new(),
+        }
+    }
+
+    pub fn source(&self, key: &str) -> ConfigSource {
+        self.sources
+            .get(key)
+            .copied()
+            .unwrap_or(ConfigSource::Default)
+    }
+
+    /// Everything that was ignored, to be logged and shown once the app is up.
+    pub fn problems(&self) -> Vec<String> {
+        self.error
+            .iter()
+            .chain(self.errors.values().flat_map(BTreeMap::values))
+            .cloned()
+            .collect()
+    }
+
+    /// Applies `value` from `origin`, or records why it is invalid and keeps
+    /// the value of the layers below.
+    fn set<T>(
+        &mut self,
+        key: &'static str,
+        origin: ConfigSource,
+        value: Result<T, ConfigError>,
+        apply: impl FnOnce(&mut Config, T),
+    ) {
+        match value {
+            Ok(value) => {
+                apply(&mut self.config, value);
+                self.sources.insert(key, origin);
+            }
+            Err(e) => self.ignore(key.to_string(), origin, e),
+        }
+    }
+
+    fn ignore(&mut self, key: String, origin: ConfigSource, error: ConfigError) {
+        self.errors
+            .entry(key)
+            .or_default()
+            .insert(origin, error.to_string());
+    }
+
+    /// Why the value for `key` from the layer that won, or from a layer
+    /// above it, was ignored. Errors of the layers it overrode don't matter
+    /// for the value in use.
+    fn error(&self, key: &str) -> Option<String> {
+        self.errors
+            .get(key)?
+            .range(self.source(key)..)
+            .next_back()
+            .map(|(_, error)| error.clone())
+    }
+
+    pub fn effective(&self) -> EffectiveConfig {
+        let config = &self.config;
+        let entry = |key: &str, value: serde_json::Value| ConfigEntry {
+            key: key.to_string(),
+            value,
+            source: self.source(key),
+            error: self.error(key),
+        };
+
+        EffectiveConfig {
+            path: self.path.clone(),
+            file_found: self.file_found,
+            error: self.error.clone(),
+            entries: vec![
+                entry("runtime.port", config.runtime.port.into()),
+                entry("runtime.args", config.runtime.args.clone().into()),
+                entry(
+                    "telemetry.sentry_dsn",
+                    config
+                        .telemetry
+                        .sentry_dsn
+                        .as_ref()
+                        .map(|dsn| dsn.to_string())
+                        .into(),
+                ),
+                entry(
+                    "updater.endpoint",
+                    config.updater.endpoint.to_string().into(),
+                ),
+                entry(
+                    "updater.beta_endpoint",
+                    config.updater.beta_endpoint.to_string().into(),
+                ),
+                entry(
+                    "updater.nightly_endpoint",
+                    config.updater.nightly_endpoint.to_string().into(),
+                ),
+                entry(
+                    "updater.check_interval_hours",
+                    config
+                        .updater
+                        .check_interval
+                        .map_or(0, |interval| interval.as_secs() / 3600)
+                        .into(),
+                ),
+                entry(
+                    "updater.functions_endpoint",
+                    config.updater.functions_endpoint.to_string().into(),
+                ),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveConfig {
+    pub path: PathBuf,
+    pub file_found: bool,
+    pub error: Option<String>,
+    pub entries: Vec<ConfigEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigEntry {
+    pub key: String,
+    pub value: serde_json::Value,
+    pub source: ConfigSource,
+    /// Why a value for this key was ignored.
+    pub error: Option<String>,
+}
+
+/// Resolves the configuration. Never fails, invalid values are left out and
+/// reported by [`LoadedConfig::problems`] so the app can still start and tell
+/// the user once logging is up.
+pub fn load_or_default(args: &Args, config_dir: &Path) -> LoadedConfig {
+    let path = config_path(args, config_dir);
+    load(args, path, |name| std::env::var(name).ok())
+}
+
+fn config_path(args: &Args, config_dir: &Path) -> PathBuf {
+    args.config
+        .clone()
+        .or_else(|| std::env::var_os(ENV_CONFIG_PATH).map(PathBuf::from))
+        .unwrap_or_else(|| config_dir.join(CONFIG_FILE_NAME))
+}
+
+fn load(args: &Args, path: PathBuf, env: impl Fn(&str) -> Option<String>) -> LoadedConfig {
+    let mut loaded = LoadedConfig::defaults(path);
+
+    match read_file(&loaded.path) {
+        Ok(Some(file)) => {
+            loaded.file_found = true;
+            apply_file(&mut loaded, file);
+        }
+        Ok(None) => {}
+        Err(e) => {
+            loaded.file_found = matches!(e, ConfigError::Parse { .. });
+            loaded.error = Some(e.to_string());
+        }
+    }
+    apply_env(&mut loaded, &env);
+    apply_args(&mut loaded, args);
+
+    loaded
+}
+
+/// `None` if there is no file.
+fn read_file(path: &Path) -> Result<Option<toml::Table>, ConfigError> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => toml::from_str(&contents)
+            .map(Some)
+            .map_err(|error| ConfigError::Parse {
+                path: path.to_path_buf(),
+                error,
+            }),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(ConfigError::Read {
+            path: path.to_path_buf(),
+            error,
+        }),
+    }
+}
+
+/// Applies every key of `craftgen.toml` on its own, so a mistyped or unknown
+/// key only loses that key.
+fn apply_file(loaded: &mut LoadedConfig, file: toml::Table) {
+    let origin = ConfigSource::File;
+    let values = file.into_iter().flat_map(|(section, value)| match value {
+        toml::Value::Table(table) => table
+            .into_iter()
+            .map(|(name, value)| (format!("{section}.{name}"), value))
+            .collect(),
+        value => vec![(section, value)],
+    });
+    for (key, value) in values {
+        match key.as_str() {
+            "runtime.port" => {
+                let port =
+                    file_value("runtime.port", value).and_then(|port| validate_port(port, origin));
+                loaded.set("runtime.port", origin, port, |c, port| {
+                    c.runtime.port = port
+                });
+            }
+            "runtime.args" => {
+                let args = file_value("runtime.args", value);
+                loaded.set("runtime.args", origin, args, |c, args| {
+                    c.runtime.args = args
+                });
+            }
+            "telemetry.sentry_dsn" => {
+                let dsn = file_value::<String>("telemetry.sentry_dsn", value)
+                    .and_then(|dsn| parse_dsn(&dsn, origin));
+                loaded.set("telemetry.sentry_dsn", origin, dsn, |c, dsn| {
+                    c.telemetry.sentry_dsn = dsn
+                });
+            }
+            "updater.check_interval_hours" => {
+                let hours = file_value("updater.check_interval_hours", value);
+                loaded.set("updater.check_interval_hours", origin, hours, |c, hours| {
+                    c.updater.check_interval = check_interval(hours)
+                });
+            }
+            "updater.endpoint" => {
+                set_file_endpoint(loaded, "updater.endpoint", |u| &mut u.endpoint, value)
+            }
+            "updater.beta_endpoint" => set_file_endpoint(
+                loaded,
+                "updater.beta_endpoint",
+                |u| &mut u.beta_endpoint,
+                value,
+            ),
+            "updater.nightly_endpoint" => set_file_endpoint(
+                loaded,
+                "updater.nightly_endpoint",
+                |u| &mut u.nightly_endpoint,
+                value,
+            ),
+            "updater.functions_endpoint" => set_file_endpoint(
+                loaded,
+                "updater.functions_endpoint",
+                |u| &mut u.functions_endpoint,
+                value,
+            ),
+            _ => loaded.ignore(key.clone(), origin, ConfigError::Unknown(key)),
+        }
+    }
+}
+
+/// `value` from `craftgen.toml` as the type of `key`.
+fn file_value<T: DeserializeOwned>(
+    key: &'static str,
+    value: toml::Value,
+) -> Result<T, ConfigError> {
+    value
+        .try_into()
+        .map_err(|e: toml::de::Error| ConfigError::Invalid {
+            key,
+            origin: ConfigSource::File,
+            message: e.message().to_string(),
+        })
+}
+
+fn set_file_endpoint(
+    loaded: &mut LoadedConfig,
+    key: &'static str,
+    field: fn(&mut UpdaterConfig) -> &mut Url,
+    value: toml::Value,
+) {
+    match file_value::<String>(key, value) {
+        Ok(endpoint) => set_endpoint(loaded, key, field, &endpoint, ConfigSource::File),
+        Err(e) => loaded.ignore(key.to_string(), ConfigSource::File, e),
+    }
+}
+
+fn apply_env(loaded: &mut LoadedConfig, env: &impl Fn(&str) -> Option<String>) {
+    let origin = ConfigSource::Env;
+    // Empty variables count as unset.
+    let var = |name: &str| env(name).filter(|v| !v.trim().is_empty());
+    if let Some(port) = var(ENV_RUNTIME_PORT) {
+        let port = u16::from_str(&port)
+            .map_err(|e| ConfigError::Invalid {
+                key: "runtime.port",
+                origin,
+                message: format!("{ENV_RUNTIME_PORT}={port:?}: {e}"),
+            })
+            .and_then(|port| validate_port(port, origin));
+        loaded.set("runtime.port", origin, port, |c, port| {
+            c.runtime.port = port
+        });
+    }
+    if let Some(args) = var(ENV_RUNTIME_ARGS) {
+        let args = args.split_whitespace().map(String::from).collect();
+        loaded.set("runtime.args", origin, Ok(args), |c, args| {
+            c.runtime.args = args
+        });
+    }
+    // An empty value is meaningful here: it disables crash reporting.
+    if let Some(dsn) = env(ENV_SENTRY_DSN) {
+        loaded.set(
+            "telemetry.sentry_dsn",
+            origin,
+            parse_dsn(&dsn, origin),
+            |c, dsn| c.telemetry.sentry_dsn = dsn,
+        );
+    }
+    if let Some(endpoint) = var(ENV_UPDATE_ENDPOINT) {
+        set_endpoint(
+            loaded,
+            "updater.endpoint",
+            |u| &mut u.endpoint,
+            &endpoint,
+            origin,
+        );
+    }
+    if let Some(endpoint) = var(ENV_BETA_UPDATE_ENDPOINT) {
+        set_endpoint(
+            loaded,
+            "updater.beta_endpoint",
+            |u| &mut u.beta_endpoint,
+            &endpoint,
+            origin,
+        );
+    }
+    if let Some(endpoint) = var(ENV_NIGHTLY_UPDATE_ENDPOINT) {
+        set_endpoint(
+            loaded,
+            "updater.nightly_endpoint",
+            |u| &mut u.nightly_endpoint,
+            &endpoint,
+            origin,
+        );
+    }
+    if let Some(hours) = var(ENV_UPDATE_CHECK_INTERVAL) {
+        let hours = u64::from_str(&hours).map_err(|e| ConfigError::Invalid {
+            key: "updater.check_interval_hours",
+            origin,
+            message: format!("{ENV_UPDATE_CHECK_INTERVAL}={hours:?}: {e}"),
+        });
+        loaded.set("updater.check_interval_hours", origin, hours, |c, hours| {
+            c.updater.check_interval = check_interval(hours)
+        });
+    }
+    if let Some(endpoint) = var(ENV_FUNCTIONS_ENDPOINT) {
+        set_endpoint(
+            loaded,
+            "updater.functions_endpoint",
+            |u| &mut u.functions_endpoint,
+            &endpoint,
+            origin,
+        );
+    }
+}
+
+fn apply_args(loaded: &mut LoadedConfig, args: &Args) {
+    let origin = ConfigSource::Cli;
+    if let Some(port) = args.port {
+        loaded.set(
+            "runtime.port",
+            origin,
+            validate_port(port, origin),
+            |c, port| c.runtime.port = port,
+        );
+    }
+    if !args.runtime_arg.is_empty() {
+        loaded.set(
+            "runtime.args",
+            origin,
+            Ok(args.runtime_arg.clone()),
+            |c, args| c.runtime.args = args,
+        );
+    }
+    if let Some(dsn) = &args.sentry_dsn {
+        loaded.set(
+            "telemetry.sentry_dsn",
+            origin,
+            parse_dsn(dsn, origin),
+            |c, dsn| c.telemetry.sentry_dsn = dsn,
+        );
+    }
+    if let Some(endpoint) = &args.update_endpoint {
+        set_endpoint(
+            loaded,
+            "updater.endpoint",
+            |u| &mut u.endpoint,
+            endpoint,
+            origin,
+        );
+    }
+    if let Some(endpoint) = &args.functions_endpoint {
+        set_endpoint(
+            loaded,
+            "updater.functions_endpoint",
+            |u| &mut u.functions_endpoint,
+            endpoint,
+            origin,
+        );
+    }
+}
+
+fn set_endpoint(
+    loaded: &mut LoadedConfig,
+    key: &'static str,
+    field: fn(&mut UpdaterConfig) -> &mut Url,
+    endpoint: &str,
+    origin: ConfigSource,
+) {
+    let url = parse_endpoint(key, endpoint, origin);
+    loaded.set(key, origin, url, |c, url| *field(&mut c.updater) = url);
+}
+
+fn validate_port(port: u16, origin: ConfigSource) -> Result<u16, ConfigError> {
+    if port == 0 {
+        return Err(ConfigError::Invalid {
+            key: "runtime.port",
+            origin,
+            message: "port must be between 1 and 65535".into(),
+        });
+    }
+    Ok(port)
+}
+
+/// Zero disables background update checks.
+fn check_interval(hours: u64) -> Option<Duration> {
+    (hours > 0).then(|| Duration::from_secs(hours * 3600))
+}
+
+/// An empty DSN disables crash reporting.
+fn parse_dsn(dsn: &str, origin: ConfigSource) -> Result<Option<Dsn>, ConfigError> {
+    let dsn = dsn.trim();
+    if dsn.is_empty() {
+        return Ok(None);
+    }
+    Dsn::from_str(dsn)
+        .map(Some)
+        .map_err(|e| ConfigError::Invalid {
+            key: "telemetry.sentry_dsn",
+            origin,
+            message: format!("{dsn:?} is not a valid Sentry DSN: {e}"),
+        })
+}
+
+fn parse_endpoint(
+    key: &'static str,
+    endpoint: &str,
+    origin: ConfigSource,
+) -> Result<Url, ConfigError> {
+    let invalid = |message: String| ConfigError::Invalid {
+        key,
+        origin,
+        message,
+    };
+    let url = Url::parse(endpoint.trim())
+        .map_err(|e| invalid(format!("{endpoint:?} is not a valid URL: {e}")))?;
+    match url.scheme() {
+        "http" | "https" => Ok(url),
+        scheme => Err(invalid(format!(
+            "unsupported scheme `{scheme}`, expected http or https"
+        ))),
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_effective_config(config: tauri::State<'_, LoadedConfig>) -> EffectiveConfig {
+    config.effective()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use clap::Parser;
+
+    use super::*;
+
+    fn args(flags: &[&str]) -> Args {
+        Args::parse_from(std::iter::once("craftgen").chain(flags.iter().copied()))
+    }
+
+    fn file(toml: &str) -> toml::Table {
+        toml::from_str(toml).expect("invalid test config")
+    }
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        move |name| vars.get(name).map(|value| value.to_string())
+    }
+
+    /// Applies the layers like [`load`], without reading a file.
+    fn layered(toml: &str, vars: &[(&str, &str)], flags: &[&str]) -> LoadedConfig {
+        let mut loaded = LoadedConfig::defaults(PathBuf::from(CONFIG_FILE_NAME));
+        apply_file(&mut loaded, file(toml));
+        apply_env(&mut loaded, &env(vars));
+        apply_args(&mut loaded, &args(flags));
+        loaded
+    }
+
+    #[test]
+    fn defaults_without_any_layer() {
+        let loaded = layered("", &[], &[]);
+        assert_eq!(loaded.config.runtime.port, DEFAULT_RUNTIME_PORT);
+        assert_eq!(loaded.source("runtime.port"), ConfigSource::Default);
+        assert!(loaded.problems().is_empty());
+    }
+
+    #[test]
+    fn file_overrides_defaults() {
+        let loaded = layered(
+            "[runtime]\nport = 1000\nargs = [\"-v\"]\n[updater]\ncheck_interval_hours = 0",
+            &[],
+            &[],
+        );
+        assert_eq!(loaded.config.runtime.port, 1000);
+        assert_eq!(loaded.config.runtime.args, ["-v"]);
+        assert_eq!(loaded.config.updater.check_interval, None);
+        assert_eq!(loaded.source("runtime.port"), ConfigSource::File);
+    }
+
+    #[test]
+    fn env_overrides_file() {
+        let loaded = layered(
+            "[runtime]\nport = 1000",
+            &[
+                (ENV_RUNTIME_PORT, "2000"),
+                (ENV_RUNTIME_ARGS, "-v --inspect"),
+            ],
+            &[],
+        );
+        assert_eq!(loaded.config.runtime.port, 2000);
+        assert_eq!(loaded.config.runtime.args, ["-v", "--inspect"]);
+        assert_eq!(loaded.source("runtime.port"), ConfigSource::Env);
+    }
+
+    #[test]
+    fn cli_overrides_env() {
+        let loaded = layered(
+            "[runtime]\nport = 1000",
+            &[(ENV_RUNTIME_PORT, "2000")],
+            &[
+                "--port",
+                "3000",
+                "--update-endpoint",
+                "https://example.com/latest.json",
+            ],
+        );
+        assert_eq!(loaded.config.runtime.port, 3000);
+        assert_eq!(loaded.source("runtime.port"), ConfigSource::Cli);
+        assert_eq!(
+            loaded.config.updater.endpoint.as_str(),
+            "https://example.com/latest.json"
+        );
+        assert_eq!(loaded.source("updater.endpoint"), ConfigSource::Cli);
+    }
+
+    #[test]
+    fn empty_env_vars_are_unset() {
+        let loaded = layered("[runtime]\nport = 1000", &[(ENV_RUNTIME_PORT, " ")], &[]);
+        assert_eq!(loaded.config.runtime.port, 1000);
+        assert!(loaded.problems().is_empty());
+    }
+
+    #[test]
+    fn empty_sentry_dsn_disables_crash_reports() {
+        let loaded = layered("", &[(ENV_SENTRY_DSN, "")], &[]);
+        assert!(loaded.config.telemetry.sentry_dsn.is_none());
+        assert_eq!(loaded.source("telemetry.sentry_dsn"), ConfigSource::Env);
+    }
+
+    #[test]
+    fn invalid_env_value_keeps_lower_layer_and_other_keys() {
+        let loaded = layered(
+            "[runtime]\nport = 1000",
+            &[
+                (ENV_RUNTIME_PORT, "not a port"),
+                (ENV_UPDATE_CHECK_INTERVAL, "12"),
+            ],
+            &[],
+        );
+        assert_eq!(loaded.config.runtime.port, 1000);
+        assert_eq!(loaded.source("runtime.port"), ConfigSource::File);
+        assert_eq!(
+            loaded.config.updater.check_interval,
+            Some(Duration::from_secs(12 * 3600))
+        );
+        let problems = loaded.problems();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("runtime.port"), "{}", problems[0]);
+    }
+
+    #[test]
+    fn invalid_file_value_keeps_default_and_other_keys() {
+        let loaded = layered(
+            "[runtime]\nport = 0\n[updater]\nendpoint = \"ftp://example.com\"\nbeta_endpoint = \"https://example.com/beta.json\"",
+            &[],
+            &[],
+        );
+        assert_eq!(loaded.config.runtime.port, DEFAULT_RUNTIME_PORT);
+        assert_eq!(loaded.source("runtime.port"), ConfigSource::Default);
+        assert_eq!(
+            loaded.config.updater.endpoint.as_str(),
+            DEFAULT_UPDATE_ENDPOINT
+        );
+        assert_eq!(
+            loaded.config.updater.beta_endpoint.as_str(),
+            "https://example.com/beta.json"
+        );
+        assert_eq!(loaded.problems().len(), 2);
+        let effective = loaded.effective();
+        let port = effective
+            .entries
+            .iter()
+            .find(|entry| entry.key == "runtime.port")
+            .unwrap();
+        assert!(port.error.is_some());
+    }
+
+    #[test]
+    fn mistyped_or_unknown_file_keys_only_lose_that_key() {
+        let loaded = layered(
+            "[runtime]\nport = 70000\nverbose = true\n[telemetry]\nsentry_dsn = \"https://key@sentry.example.com/1\"",
+            &[],
+            &[],
+        );
+        assert_eq!(loaded.config.runtime.port, DEFAULT_RUNTIME_PORT);
+        assert_eq!(
+            loaded
+                .config
+                .telemetry
+                .sentry_dsn
+                .as_ref()
+                .map(|dsn| dsn.host().to_string()),
+            Some("sentry.example.com".to_string())
+        );
+        assert_eq!(loaded.source("telemetry.sentry_dsn"), ConfigSource::File);
+        let problems = loaded.problems();
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        assert!(problems.iter().any(|p| p.contains("runtime.port")));
+        assert!(problems.iter().any(|p| p.contains("runtime.verbose")));
+    }
+
+    #[test]
+    fn overridden_file_errors_are_not_reported_for_the_value() {
+        let error = |loaded: &LoadedConfig, key: &str| {
+            loaded
+                .effective()
+                .entries
+                .into_iter()
+                .find(|entry| entry.key == key)
+                .unwrap()
+                .error
+        };
+        let loaded = layered("[runtime]\nport = 0", &[(ENV_RUNTIME_PORT, "2000")], &[]);
+        assert_eq!(loaded.config.runtime.port, 2000);
+        assert_eq!(error(&loaded, "runtime.port"), None);
+
+        let loaded = layered("[runtime]\nport = 1000", &[(ENV_RUNTIME_PORT, "0")], &[]);
+        assert_eq!(loaded.config.runtime.port, 1000);
+        assert!(error(&loaded, "runtime.port").is_some());
+    }
+
+    #[test]
+    fn unparsable_file_is_skipped_but_env_applies() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(&path, "[runtime\nport = 1000").unwrap();
+        let loaded = load(&args(&[]), path, env(&[(ENV_RUNTIME_PORT, "2000")]));
+
+        assert!(loaded.file_found);
+        assert!(loaded.error.is_some());
+        assert_eq!(loaded.config.runtime.port, 2000);
+        assert_eq!(loaded.problems().len(), 1);
+    }
+
+    #[test]
+    fn missing_file_is_not_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let loaded = load(&args(&[]), dir.path().join(CONFIG_FILE_NAME), env(&[]));
+        assert!(!loaded.file_found);
+        assert!(loaded.problems().is_empty());
+    }
+}
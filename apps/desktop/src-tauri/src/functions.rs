@@ -0,0 +1,499 @@
+//! The functions the edge runtime serves: the copy bundled with the app, or a
+//! newer signed bundle downloaded into `functions/` in the app data
+//! directory, which takes precedence.
+//!
+//! Bundles are announced by a manifest at `updater.functions_endpoint`:
+//!
+//! ```json
+//! { "version": "0.0.10-1", "appVersion": "0.0.10", "url": "…/functions.tar.gz", "signature": "…" }
+//! ```
+//!
+//! The signature is the `.sig` written by `tauri signer sign` with the
+//! updater's key. A bundle only applies to the app version it was built for,
+//! so an app update always starts from its own bundled copy. A new bundle
+//! stays on probation until the runtime is healthy with it, otherwise it is
+//! rejected and the bundled copy is used again.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::path::BaseDirectory;
+use tauri::{AppHandle, Manager, Url};
+use tauri_plugin_http::reqwest;
+use tauri_plugin_notification::NotificationExt;
+
+use crate::cmd::CommandError;
+use crate::config::LoadedConfig;
+use crate::json_file;
+use crate::modules::{self, ModuleError};
+use crate::runtime::{self, RuntimeError};
+use crate::updater::{self, SignatureError};
+
+const OVERLAY_DIR: &str = "functions";
+const CURRENT_FILE: &str = "current.json";
+const REJECTED_FILE: &str = "rejected.json";
+const STAGING_DIR: &str = ".staging";
+
+/// How long the runtime gets to become healthy with a new bundle.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, thiserror::Error)]
+pub enum FunctionsError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+    #[error("failed to download {url}: {error}")]
+    Download { url: Url, error: String },
+    #[error("invalid functions manifest: {0}")]
+    Manifest(String),
+    #[error(transparent)]
+    Signature(#[from] SignatureError),
+    #[error("invalid bundle: {0}")]
+    Bundle(#[from] ModuleError),
+    #[error("the bundle has no `main` service")]
+    MissingMain,
+    #[error("the runtime isn't healthy with functions {0}, using the bundled copy")]
+    Unhealthy(String),
+    #[error(transparent)]
+    Runtime(#[from] RuntimeError),
+}
+
+impl From<FunctionsError> for CommandError {
+    fn from(e: FunctionsError) -> Self {
+        match e {
+            FunctionsError::Io(e) => e.into(),
+            FunctionsError::Tauri(e) => e.into(),
+            FunctionsError::Runtime(e) => e.into(),
+            FunctionsError::Download { .. } | FunctionsError::Unhealthy(_) => {
+                CommandError::Runtime(e.to_string())
+            }
+            FunctionsError::Manifest(_)
+            | FunctionsError::Signature(_)
+            | FunctionsError::Bundle(_)
+            | FunctionsError::MissingMain => CommandError::InvalidInput(e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BundleManifest {
+    version: semver::Version,
+    /// The only app version the bundle runs on.
+    app_version: String,
+    url: Url,
+    signature: String,
+}
+
+/// The downloaded bundle in use.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Current {
+    version: String,
+    app_version: String,
+    /// Whether the runtime has been healthy with it.
+    verified: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Rejected {
+    version: String,
+}
+
+/// Where the runtime loads its functions from.
+pub fn dir(app: &AppHandle) -> tauri::Result<PathBuf> {
+    match overlay(app) {
+        Some((dir, _)) => Ok(dir),
+        None => app.path().resolve("functions", BaseDirectory::Resource),
+    }
+}
+
+/// The version of the downloaded bundle in use, `None` for the bundled copy.
+pub fn overlay_version(app: &AppHandle) -> Option<String> {
+    overlay(app).map(|(_, current)| current.version)
+}
+
+fn overlay(app: &AppHandle) -> Option<(PathBuf, Current)> {
+    let root = overlay_root(app).ok()?;
+    find_overlay(&root, &app.package_info().version.to_string())
+}
+
+/// The bundle in `root` to use on `app_version`, if there is one.
+fn find_overlay(root: &Path, app_version: &str) -> Option<(PathBuf, Current)> {
+    let current = json_file::read::<Current>(&root.join(CURRENT_FILE)).ok()?;
+    if current.app_version != app_version {
+        return None;
+    }
+    let dir = root.join(&current.version);
+    dir.join("main").is_dir().then_some((dir, current))
+}
+
+/// Installs the bundle announced by the manifest if it is newer than the one
+/// in use. With `restart` the runtime switches to it right away and falls
+/// back to the bundled copy if it doesn't become healthy, otherwise it is
+/// used and checked on the next start.
+pub async fn update(app: &AppHandle, restart: bool) -> Result<Option<String>, FunctionsError> {
+    let endpoint = app
+        .state::<LoadedConfig>()
+        .config
+        .updater
+        .functions_endpoint
+        .clone();
+    let root = overlay_root(app)?;
+    let app_version = app.package_info().version.to_string();
+    let public_key = updater::public_key(app)?;
+    let Some(version) = install(&endpoint, &root, &app_version, &public_key).await? else {
+        return Ok(None);
+    };
+
+    if restart {
+        let pid = runtime::start_sidecar_process(app).await?;
+        check_health(app, Some(pid)).await?;
+    }
+    Ok(Some(version))
+}
+
+/// Installs the bundle announced at `endpoint` into `root` if it is for
+/// `app_version`, newer than the one in use and signed with `public_key`.
+async fn install(
+    endpoint: &Url,
+    root: &Path,
+    app_version: &str,
+    public_key: &str,
+) -> Result<Option<String>, FunctionsError> {
+    let manifest: BundleManifest = serde_json::from_slice(&download(endpoint).await?)
+        .map_err(|e| FunctionsError::Manifest(e.to_string()))?;
+    let version = manifest.version.to_string();
+
+    if manifest.app_version != app_version {
+        log::debug!(
+            "functions {} are for Craftgen {}, not {}",
+            version,
+            manifest.app_version,
+            app_version
+        );
+        return Ok(None);
+    }
+    let installed = find_overlay(root, app_version)
+        .and_then(|(_, current)| semver::Version::parse(&current.version).ok());
+    if installed.is_some_and(|installed| installed >= manifest.version) {
+        return Ok(None);
+    }
+    if is_rejected(root, &version) {
+        log::debug!("functions {} were rejected before", version);
+        return Ok(None);
+    }
+
+    log::info!("downloading functions {}", version);
+    let bytes = download(&manifest.url).await?;
+    updater::verify_signature(public_key, &bytes, &manifest.signature)?;
+    {
+        let (root, version) = (root.to_path_buf(), version.clone());
+        tauri::async_runtime::spawn_blocking(move || unpack(&root, &version, &bytes)).await??;
+    }
+    json_file::write(
+        &root.join(CURRENT_FILE),
+        &Current {
+            version: version.clone(),
+            app_version: app_version.to_string(),
+            verified: false,
+        },
+    )?;
+    log::info!("installed functions {}", version);
+    Ok(Some(version))
+}
+
+/// Confirms a new bundle once the runtime process `pid`, just started with
+/// it, is healthy. Otherwise, or without a runtime, the bundle is rejected
+/// and the runtime restarted with the bundled copy.
+pub async fn check_health(app: &AppHandle, pid: Option<u32>) -> Result<(), FunctionsError> {
+    let Some((dir, mut current)) = overlay(app) else {
+        return Ok(());
+    };
+    if current.verified {
+        return Ok(());
+    }
+    let root = overlay_root(app)?;
+    let healthy = match pid {
+        Some(pid) => runtime::wait_until_serving(app, pid, HEALTH_CHECK_TIMEOUT).await,
+        None => false,
+    };
+    if healthy {
+        log::info!("functions {} are healthy", current.version);
+        current.verified = true;
+        json_file::write(&root.join(CURRENT_FILE), &current)?;
+        remove_other_versions(&root, &current.version);
+        return Ok(());
+    }
+
+    log::error!(
+        "runtime is {} with functions {}, falling back to the bundled copy",
+        runtime::status(app),
+        current.version
+    );
+    reject(&root, &dir, &current.version)?;
+    // A failed start is logged and shown as a crash, the rejection matters more here.
+    let _ = runtime::start_sidecar_process(app).await;
+    Err(FunctionsError::Unhealthy(current.version))
+}
+
+pub fn notify_failure(app: &AppHandle, error: &FunctionsError) {
+    log::error!("Functions update failed: {}", error);
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title("Functions update failed")
+        .body(error.to_string())
+        .show()
+    {
+        log::warn!("Failed to show notification: {}", e);
+    }
+}
+
+fn overlay_root(app: &AppHandle) -> tauri::Result<PathBuf> {
+    Ok(app.path().app_data_dir()?.join(OVERLAY_DIR))
+}
+
+async fn download(url: &Url) -> Result<Vec<u8>, FunctionsError> {
+    let error = |error: reqwest::Error| FunctionsError::Download {
+        url: url.clone(),
+        error: error.to_string(),
+    };
+    let response = reqwest::get(url.as_str())
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(error)?;
+    Ok(response.bytes().await.map_err(error)?.to_vec())
+}
+
+/// Unpacks a `.tar.gz` into `<root>/<version>`, replacing anything there.
+/// Every unpack gets its own staging directory, so concurrent updates can't
+/// mix their files.
+fn unpack(root: &Path, version: &str, bytes: &[u8]) -> Result<(), FunctionsError> {
+    let staging_root = root.join(STAGING_DIR);
+    let staging = staging_root.join(uuid::Uuid::new_v4().to_string());
+    fs::create_dir_all(&staging)?;
+    let unpacked = (|| {
+        modules::unpack_tar_gz(bytes, &staging)?;
+        if !staging.join("main").is_dir() {
+            return Err(FunctionsError::MissingMain);
+        }
+        let dir = root.join(version);
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::rename(&staging, &dir)?;
+        Ok(())
+    })();
+    if unpacked.is_err() {
+        let _ = fs::remove_dir_all(&staging);
+    }
+    // Fails while another update is unpacking, which then removes it.
+    let _ = fs::remove_dir(staging_root);
+    unpacked
+}
+
+/// Stops using the bundle `version` in `dir`, so the bundled copy is served
+/// again, and keeps it from being installed again.
+fn reject(root: &Path, dir: &Path, version: &str) -> io::Result<()> {
+    fs::remove_file(root.join(CURRENT_FILE))?;
+    json_file::write(
+        &root.join(REJECTED_FILE),
+        &Rejected {
+            version: version.to_string(),
+        },
+    )?;
+    if let Err(e) = fs::remove_dir_all(dir) {
+        log::warn!("Failed to remove {}: {}", dir.display(), e);
+    }
+    Ok(())
+}
+
+fn is_rejected(root: &Path, version: &str) -> bool {
+    json_file::read::<Rejected>(&root.join(REJECTED_FILE)).is_ok_and(|r| r.version == version)
+}
+
+/// Removes bundles other than `keep`, they can't be used again. Leaves the
+/// staging directory to updates that may be unpacking.
+fn remove_other_versions(root: &Path, keep: &str) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() && entry.file_name() != keep && entry.file_name() != STAGING_DIR {
+            if let Err(e) = fs::remove_dir_all(&path) {
+                log::warn!("Failed to remove {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::*;
+    use crate::loopback::{LoopbackServer, Response};
+    use crate::testing::{tar_entry, PUBLIC_KEY};
+
+    /// `main/index.ts` in a `.tar.gz`, signed with the test key.
+    const BUNDLE: &[u8] = include_bytes!("testdata/functions.tar.gz");
+    const BUNDLE_SIGNATURE: &str = include_str!("testdata/functions.tar.gz.sig");
+
+    /// A `.tar.gz` of `files`.
+    fn bundle(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (name, contents) in files {
+            tar_entry(
+                &mut builder,
+                name,
+                tar::EntryType::Regular,
+                contents.as_bytes(),
+                contents.len() as u64,
+            );
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn unpacks_a_bundle() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        unpack(root, "1.0.0-1", &bundle(&[("main/index.ts", "serve()")])).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("1.0.0-1/main/index.ts")).unwrap(),
+            "serve()"
+        );
+        assert!(!root.join(STAGING_DIR).exists());
+    }
+
+    #[test]
+    fn refuses_a_bundle_without_main() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let unpacked = unpack(root, "1.0.0-1", &bundle(&[("event/index.ts", "")]));
+        assert!(matches!(unpacked, Err(FunctionsError::MissingMain)));
+        assert!(!root.join("1.0.0-1").exists());
+        assert!(!root.join(STAGING_DIR).exists());
+    }
+
+    #[test]
+    fn rejects_entries_outside_the_bundle() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let escape = format!("escape-{}", uuid::Uuid::new_v4());
+        let files = [
+            ("main/index.ts", "serve()"),
+            (&format!("../../{}", escape), "escaped"),
+        ];
+        let unpacked = unpack(root, "1.0.0-1", &bundle(&files));
+        assert!(matches!(
+            unpacked,
+            Err(FunctionsError::Bundle(ModuleError::InvalidPath(_)))
+        ));
+        for dir in [root.parent().unwrap(), root] {
+            assert!(!dir.join(&escape).exists(), "{}", dir.display());
+        }
+        assert!(!root.join("1.0.0-1").exists());
+        assert!(!root.join(STAGING_DIR).exists());
+    }
+
+    #[test]
+    fn falls_back_to_the_bundled_copy_when_rejected() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        unpack(root, "1.0.0-2", &bundle(&[("main/index.ts", "")])).unwrap();
+        json_file::write(
+            &root.join(CURRENT_FILE),
+            &Current {
+                version: "1.0.0-2".to_string(),
+                app_version: "1.0.0".to_string(),
+                verified: false,
+            },
+        )
+        .unwrap();
+        let (dir, current) = find_overlay(root, "1.0.0").unwrap();
+        assert_eq!(dir, root.join("1.0.0-2"));
+        // A bundle only applies to the app version it was built for.
+        assert!(find_overlay(root, "1.0.1").is_none());
+
+        reject(root, &dir, &current.version).unwrap();
+        assert!(find_overlay(root, "1.0.0").is_none());
+        assert!(!dir.exists());
+        assert!(is_rejected(root, "1.0.0-2"));
+        assert!(!is_rejected(root, "1.0.0-3"));
+    }
+
+    /// Serves a manifest announcing functions `version` for `app_version`,
+    /// and `bundle` under the URL it names.
+    fn endpoint(version: &str, app_version: &str, bundle: &[u8]) -> (LoopbackServer, Url) {
+        let mut server = LoopbackServer::bind().unwrap();
+        let manifest = serde_json::json!({
+            "version": version,
+            "appVersion": app_version,
+            "url": server.url("/functions.tar.gz"),
+            "signature": BUNDLE_SIGNATURE,
+        });
+        let bundle = bundle.to_vec();
+        server.serve(0, move |request| {
+            let response = match request.path.as_str() {
+                "/latest.json" => Response::json("200 OK", &manifest),
+                "/functions.tar.gz" => Response::bytes("200 OK", bundle.clone()),
+                _ => Response::bytes("404 Not Found", Vec::new()),
+            };
+            async move { response }
+        });
+        let url = Url::parse(&server.url("/latest.json")).unwrap();
+        (server, url)
+    }
+
+    #[tokio::test]
+    async fn installs_a_signed_bundle_from_the_endpoint() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let (_server, url) = endpoint("1.0.0-1", "1.0.0", BUNDLE);
+
+        let installed = install(&url, root, "1.0.0", PUBLIC_KEY).await.unwrap();
+        assert_eq!(installed.as_deref(), Some("1.0.0-1"));
+        let (dir, current) = find_overlay(root, "1.0.0").unwrap();
+        assert_eq!(current.version, "1.0.0-1");
+        assert!(!current.verified);
+        assert!(dir.join("main/index.ts").is_file());
+
+        // Already in use, nothing to do.
+        let installed = install(&url, root, "1.0.0", PUBLIC_KEY).await.unwrap();
+        assert_eq!(installed, None);
+    }
+
+    #[tokio::test]
+    async fn rejects_a_bundle_that_doesnt_match_its_signature() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let mut tampered = BUNDLE.to_vec();
+        tampered.push(0);
+        let (_server, url) = endpoint("1.0.0-1", "1.0.0", &tampered);
+
+        let installed = install(&url, root, "1.0.0", PUBLIC_KEY).await;
+        assert!(matches!(installed, Err(FunctionsError::Signature(_))));
+        assert!(find_overlay(root, "1.0.0").is_none());
+        assert!(!root.join("1.0.0-1").exists());
+    }
+
+    #[tokio::test]
+    async fn skips_a_bundle_for_another_app_version() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path();
+        let (_server, url) = endpoint("1.0.1-1", "1.0.1", BUNDLE);
+
+        let installed = install(&url, root, "1.0.0", PUBLIC_KEY).await.unwrap();
+        assert_eq!(installed, None);
+        assert!(find_overlay(root, "1.0.0").is_none());
+        assert!(find_overlay(root, "1.0.1").is_none());
+    }
+}
@@ -0,0 +1,28 @@
+//! A lock file in the app data directory, held by the running app, so
+//! commands run from the command line can tell that the workspace is in use.
+
+use std::fs::{self, File, TryLockError};
+use std::io;
+use std::path::Path;
+
+const LOCK_FILE: &str = "craftgen.lock";
+
+/// Released when dropped or when the process exits, crashes included.
+pub struct InstanceLock(#[allow(dead_code)] File);
+
+impl InstanceLock {
+    /// Locks `data_dir`, `None` if another process holds the lock.
+    pub fn try_acquire(data_dir: &Path) -> io::Result<Option<Self>> {
+        fs::create_dir_all(data_dir)?;
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(data_dir.join(LOCK_FILE))?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(Self(file))),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(e),
+        }
+    }
+}
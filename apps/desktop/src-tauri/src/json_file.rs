@@ -0,0 +1,16 @@
+//! Small state files kept as pretty-printed JSON in the app data directory.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Reads `path`, a file that doesn't parse is an `InvalidData` error.
+pub fn read<T: DeserializeOwned>(path: &Path) -> io::Result<T> {
+    serde_json::from_slice(&fs::read(path)?).map_err(io::Error::from)
+}
+
+pub fn write<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    fs::write(path, serde_json::to_vec_pretty(value)?)
+}
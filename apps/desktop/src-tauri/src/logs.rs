@@ -0,0 +1,372 @@
+//! The log viewer: a window of its own that shows the app's and the runtime's
+//! logs from an in-memory buffer, so it keeps working when the main window
+//! doesn't load.
+//!
+//! Every record the log plugin accepts passes through [`capture`], which
+//! keeps the last [`CAPACITY`] entries. While the window is open, new entries
+//! are sent to it in batches as [`LogsAppended`]. Filtering, search and
+//! pausing happen in the window.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder};
+use tauri_plugin_dialog::DialogExt;
+use tauri_specta::Event;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::cmd::CommandError;
+use crate::window_state;
+
+pub const LABEL: &str = "logs";
+
+/// Target of the runtime's stdout and stderr lines.
+pub const RUNTIME_TARGET: &str = "runtime";
+
+/// How many entries the buffer keeps.
+const CAPACITY: usize = 10_000;
+
+/// How often new entries are sent to the open window.
+const STREAM_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<log::Level> for LogLevel {
+    fn from(level: log::Level) -> Self {
+        match level {
+            log::Level::Error => LogLevel::Error,
+            log::Level::Warn => LogLevel::Warn,
+            log::Level::Info => LogLevel::Info,
+            log::Level::Debug => LogLevel::Debug,
+            log::Level::Trace => LogLevel::Trace,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // `pad` so the export can align levels with `{:5}`.
+        f.pad(match self {
+            LogLevel::Error => "ERROR",
+            LogLevel::Warn => "WARN",
+            LogLevel::Info => "INFO",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Trace => "TRACE",
+        })
+    }
+}
+
+/// Where an entry comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum LogSource {
+    App,
+    /// The edge runtime's output.
+    Runtime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    /// Increases by one per entry, also across entries that were dropped.
+    pub id: u64,
+    /// Milliseconds since the unix epoch.
+    pub timestamp: u64,
+    pub level: LogLevel,
+    pub source: LogSource,
+    /// The module that logged it.
+    pub target: String,
+    pub message: String,
+}
+
+/// Emitted to the log viewer with the entries logged since the last batch.
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type, tauri_specta::Event)]
+pub struct LogsAppended {
+    pub entries: Vec<LogEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportLogsRequest {
+    /// Earliest entry to export, in milliseconds since the unix epoch.
+    pub from: Option<u64>,
+    /// Latest entry to export, in milliseconds since the unix epoch.
+    pub to: Option<u64>,
+}
+
+/// The last `capacity` entries.
+struct Buffer {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+    next_id: u64,
+}
+
+impl Buffer {
+    const fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+            next_id: 0,
+        }
+    }
+
+    /// Adds an entry with the next id, dropping the oldest one when full.
+    fn push(
+        &mut self,
+        timestamp: u64,
+        level: LogLevel,
+        source: LogSource,
+        target: &str,
+        message: String,
+    ) {
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        self.entries.push_back(LogEntry {
+            id,
+            timestamp,
+            level,
+            source,
+            target: target.to_string(),
+            message,
+        });
+    }
+
+    /// The entries, only those after `after` if given.
+    fn after(&self, after: Option<u64>) -> Vec<LogEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| after.map_or(true, |after| entry.id > after))
+            .cloned()
+            .collect()
+    }
+}
+
+/// A global rather than app state: logging starts before the app exists.
+static BUFFER: Mutex<Buffer> = Mutex::new(Buffer::new(CAPACITY));
+
+/// Keeps a record, called from the log plugin's formatter.
+pub fn capture(record: &log::Record, message: &fmt::Arguments) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64;
+    let source = match record.target() {
+        RUNTIME_TARGET => LogSource::Runtime,
+        _ => LogSource::App,
+    };
+    let message = message.to_string();
+
+    BUFFER.lock().unwrap_or_else(|e| e.into_inner()).push(
+        timestamp,
+        record.level().into(),
+        source,
+        record.target(),
+        message,
+    );
+}
+
+/// The buffered entries, only those after `after` if given.
+fn entries_after(after: Option<u64>) -> Vec<LogEntry> {
+    BUFFER
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .after(after)
+}
+
+pub fn open_log_viewer(app: &AppHandle) -> Result<(), CommandError> {
+    if let Some(window) = app.get_webview_window(LABEL) {
+        window.unminimize()?;
+        window.show()?;
+        window.set_focus()?;
+        return Ok(());
+    }
+    let url = WebviewUrl::App("logs.html".into());
+    let window = WebviewWindowBuilder::new(app, LABEL, url)
+        .title("Craftgen Logs")
+        .inner_size(1000.0, 640.0)
+        .min_inner_size(600.0, 300.0)
+        .visible(false)
+        .build()?;
+    window_state::track(&window);
+    window.show()?;
+    window.set_focus()?;
+    spawn_stream(app);
+    Ok(())
+}
+
+/// Sends new entries to the window until it is closed. The window fetches
+/// what was logged before it loaded with `get_logs`, and ignores entries it
+/// already has.
+fn spawn_stream(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut last = BUFFER
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .next_id
+            .checked_sub(1);
+        while app.get_webview_window(LABEL).is_some() {
+            tokio::time::sleep(STREAM_INTERVAL).await;
+            let entries = entries_after(last);
+            let Some(entry) = entries.last() else {
+                continue;
+            };
+            last = Some(entry.id);
+            // Not logged, that would add an entry every time.
+            let _ = LogsAppended { entries }.emit_to(&app, LABEL);
+        }
+    });
+}
+
+/// Writes those of `entries` logged between `from` and `to` to `path` as
+/// plain text lines.
+fn export(entries: Vec<LogEntry>, request: &ExportLogsRequest, path: &Path) -> io::Result<usize> {
+    let entries: Vec<_> = entries
+        .into_iter()
+        .filter(|entry| request.from.map_or(true, |from| entry.timestamp >= from))
+        .filter(|entry| request.to.map_or(true, |to| entry.timestamp <= to))
+        .collect();
+    let mut file = io::BufWriter::new(fs::File::create(path)?);
+    for entry in &entries {
+        let time = OffsetDateTime::from_unix_timestamp_nanos(entry.timestamp as i128 * 1_000_000)
+            .ok()
+            .and_then(|time| time.format(&Rfc3339).ok())
+            .unwrap_or_else(|| entry.timestamp.to_string());
+        writeln!(
+            file,
+            "{} {:5} [{}] {}",
+            time, entry.level, entry.target, entry.message
+        )?;
+    }
+    file.flush()?;
+    Ok(entries.len())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn show_log_viewer(app_handle: AppHandle) -> Result<(), CommandError> {
+    open_log_viewer(&app_handle)
+}
+
+/// The buffered entries, only those after the entry with id `after` if given.
+#[tauri::command]
+#[specta::specta]
+pub fn get_logs(after: Option<u64>) -> Vec<LogEntry> {
+    entries_after(after)
+}
+
+/// Asks where to save the logs and returns how many entries were written,
+/// `None` if the user cancelled.
+#[tauri::command]
+#[specta::specta]
+pub async fn export_logs(
+    app_handle: AppHandle,
+    request: ExportLogsRequest,
+) -> Result<Option<usize>, CommandError> {
+    // The dialog blocks until it's closed, which must not happen on the main
+    // thread.
+    let exported = tauri::async_runtime::spawn_blocking(move || {
+        let Some(path) = app_handle
+            .dialog()
+            .file()
+            .set_title("Export Logs")
+            .set_file_name("craftgen.log")
+            .add_filter("Log", &["log", "txt"])
+            .blocking_save_file()
+        else {
+            return Ok(None);
+        };
+        let path = path
+            .into_path()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+        export(entries_after(None), &request, &path).map(Some)
+    })
+    .await??;
+    Ok(exported)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(capacity: usize, timestamps: &[u64]) -> Buffer {
+        let mut buffer = Buffer::new(capacity);
+        for (i, timestamp) in timestamps.iter().enumerate() {
+            buffer.push(
+                *timestamp,
+                LogLevel::Info,
+                LogSource::App,
+                "craftgen",
+                format!("entry {}", i),
+            );
+        }
+        buffer
+    }
+
+    fn ids(entries: &[LogEntry]) -> Vec<u64> {
+        entries.iter().map(|entry| entry.id).collect()
+    }
+
+    #[test]
+    fn drops_the_oldest_entries_over_capacity() {
+        let buffer = buffer(3, &[1, 2, 3, 4, 5]);
+        assert_eq!(ids(&buffer.after(None)), [2, 3, 4]);
+        assert_eq!(buffer.next_id, 5);
+    }
+
+    #[test]
+    fn lists_entries_after_an_id() {
+        let buffer = buffer(3, &[1, 2, 3, 4, 5]);
+        assert_eq!(ids(&buffer.after(Some(3))), [4]);
+        assert!(buffer.after(Some(4)).is_empty());
+        // Ids that were dropped since still count.
+        assert_eq!(ids(&buffer.after(Some(0))), [2, 3, 4]);
+    }
+
+    #[test]
+    fn exports_the_entries_in_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("craftgen.log");
+        let entries = buffer(10, &[1_000, 2_000, 3_000, 4_000]).after(None);
+
+        let request = ExportLogsRequest {
+            from: Some(2_000),
+            to: Some(3_000),
+        };
+        assert_eq!(export(entries.clone(), &request, &path).unwrap(), 2);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "1970-01-01T00:00:02Z INFO  [craftgen] entry 1\n\
+             1970-01-01T00:00:03Z INFO  [craftgen] entry 2\n"
+        );
+
+        let request = ExportLogsRequest {
+            from: Some(2_500),
+            to: None,
+        };
+        assert_eq!(export(entries.clone(), &request, &path).unwrap(), 2);
+        let request = ExportLogsRequest {
+            from: None,
+            to: None,
+        };
+        assert_eq!(export(entries, &request, &path).unwrap(), 4);
+    }
+}
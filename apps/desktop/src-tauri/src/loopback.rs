@@ -0,0 +1,261 @@
+//! A minimal HTTP/1.1 server on a random port on `127.0.0.1`, for what the
+//! app serves to local clients, the job queue's API for the edge runtime. One
+//! request per connection, bodies need a `Content-Length`.
+
+use std::future::Future;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, Take};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Limits on the request line and headers together, far above what the
+/// local clients send.
+const MAX_HEAD_BYTES: u64 = 16 * 1024;
+const MAX_HEADERS: usize = 64;
+/// A client that hasn't sent its whole request by then is dropped, so idle
+/// connections don't pile up.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    headers: Vec<(String, String)>,
+    /// `None` if it is larger than the server accepts, it isn't read then.
+    pub body: Option<Vec<u8>>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    /// Serves files to the clients under test.
+    #[cfg(test)]
+    pub fn bytes(status: &'static str, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            content_type: "application/octet-stream",
+            body,
+        }
+    }
+
+    pub fn json(status: &'static str, value: &impl Serialize) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_vec(value).unwrap_or_default(),
+        }
+    }
+}
+
+/// Stops serving when dropped.
+pub struct LoopbackServer {
+    addr: SocketAddr,
+    listener: Option<std::net::TcpListener>,
+    task: Option<tauri::async_runtime::JoinHandle<()>>,
+}
+
+impl LoopbackServer {
+    /// Takes a port, requests are only answered once [`serve`](Self::serve)
+    /// is called.
+    pub fn bind() -> io::Result<Self> {
+        let listener = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            addr: listener.local_addr()?,
+            listener: Some(listener),
+            task: None,
+        })
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// Answers every request with `handler`, reading bodies of up to
+    /// `max_body` bytes.
+    pub fn serve<F, Fut>(&mut self, max_body: usize, handler: F)
+    where
+        F: Fn(Request) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Response> + Send + 'static,
+    {
+        let Some(listener) = self.listener.take() else {
+            return;
+        };
+        let handler = Arc::new(handler);
+        self.task = Some(tauri::async_runtime::spawn(async move {
+            let listener = match TcpListener::from_std(listener) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    log::error!("Failed to serve on the loopback port: {}", e);
+                    return;
+                }
+            };
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = respond(stream, max_body, &*handler).await {
+                        log::debug!("loopback request failed: {}", e);
+                    }
+                });
+            }
+        }));
+    }
+}
+
+impl Drop for LoopbackServer {
+    fn drop(&mut self) {
+        if let Some(task) = &self.task {
+            task.abort();
+        }
+    }
+}
+
+async fn respond<F, Fut>(mut stream: TcpStream, max_body: usize, handler: &F) -> io::Result<()>
+where
+    F: Fn(Request) -> Fut,
+    Fut: Future<Output = Response>,
+{
+    let (read, mut write) = stream.split();
+    let mut read = BufReader::new(read);
+    let request = tokio::time::timeout(READ_TIMEOUT, read_request(&mut read, max_body))
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "the request took too long"))??;
+    let response = handler(request).await;
+
+    write
+        .write_all(
+            format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nContent-Type: {}\r\nConnection: close\r\n\r\n",
+                response.status,
+                response.body.len(),
+                response.content_type
+            )
+            .as_bytes(),
+        )
+        .await?;
+    write.write_all(&response.body).await?;
+    write.shutdown().await
+}
+
+async fn read_request(
+    read: &mut (impl AsyncBufRead + Unpin),
+    max_body: usize,
+) -> io::Result<Request> {
+    let mut head = (&mut *read).take(MAX_HEAD_BYTES);
+    let request_line = read_line(&mut head).await?;
+    let mut headers = Vec::new();
+    let mut content_length = 0;
+    loop {
+        let line = read_line(&mut head).await?;
+        if line.trim().is_empty() {
+            break;
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if headers.len() == MAX_HEADERS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "too many headers",
+            ));
+        }
+        let (name, value) = (name.trim().to_string(), value.trim().to_string());
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.parse().unwrap_or(usize::MAX);
+        }
+        headers.push((name, value));
+    }
+
+    let body = match content_length <= max_body {
+        true => {
+            let mut body = vec![0; content_length];
+            read.read_exact(&mut body).await?;
+            Some(body)
+        }
+        false => None,
+    };
+    let mut parts = request_line.split_whitespace();
+    Ok(Request {
+        method: parts.next().unwrap_or_default().to_string(),
+        path: parts.next().unwrap_or_default().to_string(),
+        headers,
+        body,
+    })
+}
+
+/// A line of the request head, which fails once the head is over
+/// [`MAX_HEAD_BYTES`]. The end of the stream reads as an empty line.
+async fn read_line(head: &mut Take<impl AsyncBufRead + Unpin>) -> io::Result<String> {
+    let mut line = String::new();
+    head.read_line(&mut line).await?;
+    if head.limit() == 0 && !line.ends_with('\n') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the request head is too large",
+        ));
+    }
+    Ok(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn read(request: &[u8]) -> io::Result<Request> {
+        read_request(&mut BufReader::new(request), 1024).await
+    }
+
+    #[tokio::test]
+    async fn reads_a_request() {
+        let request = read(b"POST /jobs HTTP/1.1\r\nContent-Length: 2\r\nX-Id: 1\r\n\r\n{}")
+            .await
+            .unwrap();
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("POST", "/jobs")
+        );
+        assert_eq!(request.header("x-id"), Some("1"));
+        assert_eq!(request.body.as_deref(), Some(&b"{}"[..]));
+    }
+
+    #[tokio::test]
+    async fn skips_a_body_over_the_limit() {
+        let request = read(b"POST /jobs HTTP/1.1\r\nContent-Length: 4096\r\n\r\n")
+            .await
+            .unwrap();
+        assert!(request.body.is_none());
+    }
+
+    #[tokio::test]
+    async fn refuses_a_head_over_the_limits() {
+        let long = format!(
+            "GET / HTTP/1.1\r\nX-Long: {}\r\n\r\n",
+            "a".repeat(MAX_HEAD_BYTES as usize)
+        );
+        let error = read(long.as_bytes()).await.err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let many = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            "X-Header: 1\r\n".repeat(MAX_HEADERS + 1)
+        );
+        let error = read(many.as_bytes()).await.err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
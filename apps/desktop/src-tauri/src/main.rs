@@ -4,23 +4,77 @@
 
 use tauri_plugin_autostart::MacosLauncher;
 
+mod cli;
 mod cmd;
+mod config;
+mod functions;
+mod instance;
+mod json_file;
+mod logs;
+mod loopback;
+mod modules;
+mod palette;
+mod queue;
 mod runtime;
+mod settings;
+mod telemetry;
 mod setup;
+#[cfg(test)]
+mod testing;
 mod tray;
+mod updater;
+mod window_state;
+mod windows;
+mod workspace;
 use clap::Parser;
 use tauri_plugin_log::{fern::colors::{Color, ColoredLevelConfig}, Target, TargetKind};
 use tokio::sync::Mutex;
 
+/// The log plugin's own timestamp format, in UTC like its default, which a
+/// custom formatter has to redo.
+const LOG_TIME_FORMAT: &[time::format_description::BorrowedFormatItem<'static>] =
+    time::macros::format_description!("[year]-[month]-[day]][[hour]:[minute]:[second]");
+
 #[cfg(target_os = "macos")]
 mod dock;
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(version, about, long_about = None)]
 struct Args {
     /// Start app minimized
     #[arg(short, long)]
     minimized: bool,
+
+    /// Open the quick-launch palette instead of the main window
+    #[arg(long)]
+    palette: bool,
+
+    /// Path to the configuration file (defaults to craftgen.toml in the config dir)
+    #[arg(long, value_name = "PATH")]
+    config: Option<std::path::PathBuf>,
+
+    /// Port the edge runtime listens on
+    #[arg(long)]
+    port: Option<u16>,
+
+    /// Extra argument passed to the edge runtime, can be repeated
+    #[arg(long = "runtime-arg", value_name = "ARG", allow_hyphen_values = true)]
+    runtime_arg: Vec<String>,
+
+    /// Sentry DSN for crash reports, an empty value disables them
+    #[arg(long, value_name = "DSN")]
+    sentry_dsn: Option<String>,
+
+    /// Update manifest endpoint of the stable channel
+    #[arg(long, value_name = "URL")]
+    update_endpoint: Option<String>,
+
+    /// Functions bundle manifest endpoint
+    #[arg(long, value_name = "URL")]
+    functions_endpoint: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<cli::Command>,
 }
 
 #[derive(Debug)]
@@ -29,10 +83,33 @@ struct AppState {
 }
 
 fn main() {
-    let _guard = sentry::init(("https://9d430bec9a3518bcb0c34c7f8b9fe1d8@o4507501119799296.ingest.us.sentry.io/4507501176029184", sentry::ClientOptions {
-        release: sentry::release_name!(),
-        ..Default::default()
-    }));
+    let args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(e) => {
+            #[cfg(windows)]
+            cli::attach_console();
+            e.exit()
+        }
+    };
+    #[cfg(windows)]
+    if args.command.is_some() {
+        cli::attach_console();
+    }
+    let context = tauri::generate_context!();
+    let config_dir = dirs::config_dir()
+        .unwrap_or_default()
+        .join(&context.config().identifier);
+    let config = config::load_or_default(&args, &config_dir);
+
+    // Same as `app.path().app_data_dir()`, which isn't available yet.
+    let data_dir = dirs::data_dir()
+        .unwrap_or_default()
+        .join(&context.config().identifier);
+    let telemetry = telemetry::init(&config.config.telemetry, &data_dir);
+
+    let commands = cmd::builder();
+    #[cfg(debug_assertions)]
+    cmd::export_bindings(&commands);
 
     tauri::Builder::default()
         .plugin(tauri_plugin_updater::Builder::new().build())
@@ -47,14 +124,28 @@ fn main() {
                     Target::new(TargetKind::LogDir {
                         file_name: Some("craftgen".to_string()),
                     }),
-                    Target::new(TargetKind::Webview),
                 ])
-                .with_colors(ColoredLevelConfig {
-                    error: Color::Red,
-                    warn: Color::Yellow,
-                    debug: Color::White,
-                    info: Color::BrightGreen,
-                    trace: Color::Cyan,
+                .format({
+                    let colors = ColoredLevelConfig {
+                        error: Color::Red,
+                        warn: Color::Yellow,
+                        debug: Color::White,
+                        info: Color::BrightGreen,
+                        trace: Color::Cyan,
+                    };
+                    move |out, message, record| {
+                        // Also keeps it for the log viewer, which replaces the
+                        // webview target so that it works without the main window.
+                        logs::capture(record, message);
+                        let now = time::OffsetDateTime::now_utc();
+                        out.finish(format_args!(
+                            "[{}][{}][{}] {}",
+                            now.format(LOG_TIME_FORMAT).unwrap_or_default(),
+                            record.target(),
+                            colors.color(record.level()),
+                            message
+                        ))
+                    }
                 })
                 .build(),
         )
@@ -62,6 +153,14 @@ fn main() {
         .manage(Mutex::new(AppState {
             sidecar_handle: None
         }))
+        .manage(runtime::RuntimeHealth::default())
+        .manage(updater::PendingUpdate::default())
+        .manage(updater::LastUpdateFailure::default())
+        .manage(updater::OfferedUpdate::default())
+        .manage(windows::OpenWindows::default())
+        .manage(args)
+        .manage(config)
+        .manage(telemetry)
         .plugin(tauri_plugin_store::Builder::new().build())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_autostart::init(
@@ -69,10 +168,13 @@ fn main() {
             Some(vec!["--minimized"]),
         ))
         .plugin(tauri_plugin_shell::init())
-        .invoke_handler(tauri::generate_handler![
-        ])
-        .setup(setup::setup)
-        .build(tauri::generate_context!())
+        .plugin(tauri_plugin_clipboard_manager::init())
+        .invoke_handler(commands.invoke_handler())
+        .setup(move |app| {
+            commands.mount_events(app);
+            setup::setup(app)
+        })
+        .build(context)
         .expect("error while running tauri application")
         .run(runtime::on_run_event);
 }
@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path};
+
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+use super::ModuleError;
+
+/// File name of the manifest at the root of every installed module.
+pub const MANIFEST_FILE: &str = "module.json";
+
+/// Describes an installed module, stored as `module.json` next to its code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ModuleManifest {
+    /// `<project>/<module>`, e.g. `my-org/scraper`.
+    pub name: String,
+    /// Semver version of the module.
+    pub version: String,
+    /// Path of the file the runtime starts, relative to the module root.
+    pub entrypoint: String,
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+    /// Other modules this one needs, as `name -> semver requirement`.
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, String>,
+}
+
+/// Capabilities a module asks the runtime for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum Permission {
+    /// Outbound network access.
+    Net,
+    /// Read access to the host environment variables.
+    Env,
+}
+
+impl ModuleManifest {
+    pub fn validate(&self) -> Result<(), ModuleError> {
+        let invalid = |message: String| ModuleError::InvalidManifest(message);
+
+        split_name(&self.name)?;
+        semver::Version::parse(&self.version)
+            .map_err(|e| invalid(format!("version {:?} is not valid semver: {}", self.version, e)))?;
+        if !is_relative_path(&self.entrypoint) {
+            return Err(invalid(format!(
+                "entrypoint {:?} must be a relative path inside the module",
+                self.entrypoint
+            )));
+        }
+        for (name, requirement) in &self.dependencies {
+            split_name(name)?;
+            semver::VersionReq::parse(requirement).map_err(|e| {
+                invalid(format!(
+                    "dependency {} has an invalid version requirement {:?}: {}",
+                    name, requirement, e
+                ))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// The `(project, module)` pair this module is installed under.
+    pub fn slugs(&self) -> Result<(&str, &str), ModuleError> {
+        split_name(&self.name)
+    }
+}
+
+/// Splits `<project>/<module>` and checks both halves are valid slugs.
+pub fn split_name(name: &str) -> Result<(&str, &str), ModuleError> {
+    match name.split_once('/') {
+        Some((project, module)) if is_slug(project) && is_slug(module) => Ok((project, module)),
+        _ => Err(ModuleError::InvalidManifest(format!(
+            "name {:?} must look like `<project>/<module>` using lowercase letters, digits, `-` and `_`",
+            name
+        ))),
+    }
+}
+
+/// Which installed version of a module to use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionSelector {
+    /// The version `current.json` points at.
+    Current,
+    /// The highest installed version.
+    Latest,
+    Exact(Version),
+    /// The highest installed version matching the requirement.
+    Range(VersionReq),
+}
+
+/// Parses `<project>/<module>[@<version>]`, where the version is exact,
+/// `latest` or a range.
+///
+/// Ranges follow npm rather than Cargo, since the runtime resolves the same
+/// selectors with Deno's semver: whitespace separated comparators such as
+/// `>=1.2.0 <2`, `^1.2`, `~1.2` or `1.x`, where a bare partial version like
+/// `1.2` means `1.2.x`. `||` and hyphen ranges aren't supported.
+pub fn parse_spec(spec: &str) -> Result<(&str, VersionSelector), ModuleError> {
+    let invalid = || ModuleError::InvalidSpec(spec.to_string());
+    let (name, selector) = match spec.split_once('@') {
+        None => (spec, VersionSelector::Current),
+        Some((name, "latest")) => (name, VersionSelector::Latest),
+        Some((name, version)) => {
+            let selector = match Version::parse(version) {
+                Ok(version) => VersionSelector::Exact(version),
+                Err(_) => parse_range(version)
+                    .map(VersionSelector::Range)
+                    .ok_or_else(invalid)?,
+            };
+            (name, selector)
+        }
+    };
+    split_name(name).map_err(|_| invalid())?;
+    Ok((name, selector))
+}
+
+/// Translates an npm style range into a [`VersionReq`]. Cargo reads a bare
+/// `1.2` as `^1.2`, its `=1.2` is what npm means by it.
+fn parse_range(range: &str) -> Option<VersionReq> {
+    if range.contains([',', '|']) {
+        return None;
+    }
+    let comparators: Vec<_> = range
+        .split_whitespace()
+        .map(|comparator| {
+            if comparator.starts_with(|c: char| c.is_ascii_digit()) {
+                format!("={}", comparator)
+            } else {
+                comparator.to_string()
+            }
+        })
+        .collect();
+    if comparators.iter().any(|comparator| comparator == "-") {
+        return None;
+    }
+    VersionReq::parse(&comparators.join(", ")).ok()
+}
+
+fn is_slug(value: &str) -> bool {
+    let mut chars = value.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_lowercase() || c.is_ascii_digit())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+/// Whether `path` stays inside the directory it is joined onto.
+pub fn is_relative_path(path: &str) -> bool {
+    let path = Path::new(path);
+    path.components().next().is_some()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
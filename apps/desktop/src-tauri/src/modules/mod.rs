@@ -0,0 +1,763 @@
+//! Modules installed on disk for the edge runtime.
+//!
+//! Versions of a module are installed side by side at
+//! `<modules dir>/<project>/<module>/<version>`, each with its
+//! [`ModuleManifest`] as `module.json`. `current.json` in the module directory
+//! names the version `/run` uses when a request doesn't pin one, so switching
+//! or rolling back is a single pointer write. Installs are written to a staging
+//! directory and renamed into place, so the runtime never sees a half-written
+//! module.
+
+mod manifest;
+mod pointer;
+mod source;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+pub use manifest::{parse_spec, ModuleManifest, Permission, VersionSelector, MANIFEST_FILE};
+use pointer::Pointer;
+pub use source::{unpack_tar_gz, ModuleSource};
+
+use crate::cmd::CommandError;
+use crate::workspace::WorkspacePaths;
+
+const STAGING_DIR: &str = ".staging";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ModuleError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("invalid module manifest: {0}")]
+    InvalidManifest(String),
+    #[error("invalid file path {0:?}, paths must be relative and stay inside the module")]
+    InvalidPath(String),
+    #[error("invalid module reference {0:?}, expected `<project>/<module>[@<version>]`")]
+    InvalidSpec(String),
+    #[error("module {0} is not installed")]
+    NotFound(String),
+    #[error("{0} is the current version, switch to another version before removing it")]
+    VersionInUse(String),
+    #[error("module {0} has no previous version to roll back to")]
+    NoPreviousVersion(String),
+    #[error("module is too large, {0}")]
+    TooLarge(String),
+    #[error("cannot install module: {0}")]
+    Source(String),
+}
+
+impl From<ModuleError> for CommandError {
+    fn from(e: ModuleError) -> Self {
+        match e {
+            ModuleError::Io(e) => e.into(),
+            ModuleError::NotFound(_) => CommandError::NotFound(e.to_string()),
+            ModuleError::Json(_)
+            | ModuleError::InvalidManifest(_)
+            | ModuleError::InvalidPath(_)
+            | ModuleError::InvalidSpec(_)
+            | ModuleError::VersionInUse(_)
+            | ModuleError::NoPreviousVersion(_)
+            | ModuleError::TooLarge(_)
+            | ModuleError::Source(_) => CommandError::InvalidInput(e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleFile {
+    /// Path relative to the module root.
+    pub path: String,
+    pub contents: String,
+}
+
+#[derive(Debug, Clone, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallModuleRequest {
+    pub manifest: ModuleManifest,
+    pub files: Vec<ModuleFile>,
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledModule {
+    /// Manifest of the current version.
+    pub manifest: ModuleManifest,
+    /// Directory of the current version.
+    pub path: PathBuf,
+    /// Every installed version, newest first.
+    pub versions: Vec<String>,
+    /// The version a rollback would switch back to.
+    pub previous: Option<String>,
+}
+
+/// A module version picked by [`ModuleStore::resolve`].
+#[derive(Debug, Clone, Serialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedModule {
+    pub manifest: ModuleManifest,
+    pub path: PathBuf,
+}
+
+/// What is installed for one module.
+struct ModuleVersions {
+    dir: PathBuf,
+    pointer: Option<Pointer>,
+    /// Newest first.
+    versions: Vec<Version>,
+}
+
+impl ModuleVersions {
+    /// The pointed-at version, or the newest one if the pointer is missing.
+    fn current(&self) -> Option<String> {
+        match &self.pointer {
+            Some(pointer) => Some(pointer.version.clone()),
+            None => self.versions.first().map(Version::to_string),
+        }
+    }
+
+    fn has(&self, version: &str) -> bool {
+        self.versions.iter().any(|v| v.to_string() == version)
+    }
+
+    fn select(&self, selector: &VersionSelector) -> Option<String> {
+        match selector {
+            VersionSelector::Current => self.current(),
+            VersionSelector::Latest => self.versions.first().map(Version::to_string),
+            VersionSelector::Exact(version) => self
+                .versions
+                .contains(version)
+                .then(|| version.to_string()),
+            VersionSelector::Range(req) => self
+                .versions
+                .iter()
+                .find(|v| req.matches(v))
+                .map(Version::to_string),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ModuleStore {
+    root: PathBuf,
+}
+
+impl ModuleStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn open(app_handle: &AppHandle) -> tauri::Result<Self> {
+        Ok(Self::new(WorkspacePaths::resolve(app_handle)?.modules))
+    }
+
+    fn module_dir(&self, project: &str, module: &str) -> PathBuf {
+        self.root.join(project).join(module)
+    }
+
+    pub fn install(&self, request: InstallModuleRequest) -> Result<InstalledModule, ModuleError> {
+        let manifest = request.manifest;
+        manifest.validate()?;
+        if !request.files.iter().any(|file| file.path == manifest.entrypoint) {
+            return Err(ModuleError::InvalidManifest(format!(
+                "entrypoint {:?} is not one of the module files",
+                manifest.entrypoint
+            )));
+        }
+
+        let staging = self.staging_dir()?;
+        let written = (|| -> Result<(), ModuleError> {
+            for file in &request.files {
+                if !manifest::is_relative_path(&file.path) || file.path == MANIFEST_FILE {
+                    return Err(ModuleError::InvalidPath(file.path.clone()));
+                }
+                let path = staging.join(&file.path);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, &file.contents)?;
+            }
+            fs::write(
+                staging.join(MANIFEST_FILE),
+                serde_json::to_vec_pretty(&manifest)?,
+            )?;
+            Ok(())
+        })();
+        if let Err(e) = written {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+
+        self.activate(&staging, &manifest)
+    }
+
+    /// Moves a validated module from `staging` into its version directory and
+    /// makes it the current version.
+    fn activate(
+        &self,
+        staging: &Path,
+        manifest: &ModuleManifest,
+    ) -> Result<InstalledModule, ModuleError> {
+        let (project, module) = manifest.slugs()?;
+        self.migrate_unversioned(&self.module_dir(project, module))?;
+        let installed = self.versions(project, module)?;
+        self.replace(staging, &installed.dir.join(&manifest.version))?;
+        // Reinstalling the current version keeps the existing rollback target.
+        let previous = match installed.current() {
+            Some(current) if current == manifest.version => {
+                installed.pointer.and_then(|pointer| pointer.previous)
+            }
+            current => current,
+        };
+        pointer::write(
+            &installed.dir,
+            &Pointer {
+                version: manifest.version.clone(),
+                previous,
+            },
+        )?;
+        log::info!("Installed module {}@{}", manifest.name, manifest.version);
+
+        self.installed(project, module)
+    }
+
+    /// Removes one version of a module, or the whole module when `version` is
+    /// `None`. The current version can only go with the rest of the module.
+    pub fn uninstall(&self, name: &str, version: Option<&str>) -> Result<(), ModuleError> {
+        let (project, module) = manifest::split_name(name)?;
+        let installed = self.versions(project, module)?;
+        if installed.versions.is_empty() {
+            return Err(ModuleError::NotFound(name.to_string()));
+        }
+        let Some(version) = version else {
+            self.remove(&installed.dir)?;
+            let project_dir = self.root.join(project);
+            if fs::read_dir(&project_dir)?.next().is_none() {
+                fs::remove_dir(project_dir)?;
+            }
+            log::info!("Uninstalled module {}", name);
+            return Ok(());
+        };
+
+        if !installed.has(version) {
+            return Err(ModuleError::NotFound(format!("{}@{}", name, version)));
+        }
+        if installed.current().as_deref() == Some(version) {
+            return Err(ModuleError::VersionInUse(format!("{}@{}", name, version)));
+        }
+        if let Some(pointer) = installed.pointer {
+            if pointer.previous.as_deref() == Some(version) {
+                pointer::write(
+                    &installed.dir,
+                    &Pointer {
+                        previous: None,
+                        ..pointer
+                    },
+                )?;
+            }
+        }
+        self.remove(&installed.dir.join(version))?;
+        log::info!("Uninstalled module {}@{}", name, version);
+        Ok(())
+    }
+
+    /// Points the module at another installed version.
+    pub fn set_current(&self, name: &str, version: &str) -> Result<InstalledModule, ModuleError> {
+        let (project, module) = manifest::split_name(name)?;
+        let installed = self.versions(project, module)?;
+        let current = installed
+            .current()
+            .ok_or_else(|| ModuleError::NotFound(name.to_string()))?;
+        if !installed.has(version) {
+            return Err(ModuleError::NotFound(format!("{}@{}", name, version)));
+        }
+        if current != version {
+            pointer::write(
+                &installed.dir,
+                &Pointer {
+                    version: version.to_string(),
+                    previous: Some(current),
+                },
+            )?;
+            log::info!("Switched module {} to {}", name, version);
+        }
+        self.installed(project, module)
+    }
+
+    /// Switches back to the version that was current before the last switch
+    /// or install.
+    pub fn rollback(&self, name: &str) -> Result<InstalledModule, ModuleError> {
+        let (project, module) = manifest::split_name(name)?;
+        let previous = self
+            .versions(project, module)?
+            .pointer
+            .and_then(|pointer| pointer.previous)
+            .ok_or_else(|| ModuleError::NoPreviousVersion(name.to_string()))?;
+        self.set_current(name, &previous)
+    }
+
+    /// Picks the installed version matching `spec`, see [`parse_spec`].
+    pub fn resolve(&self, spec: &str) -> Result<ResolvedModule, ModuleError> {
+        let (name, selector) = parse_spec(spec)?;
+        let (project, module) = manifest::split_name(name)?;
+        let installed = self.versions(project, module)?;
+        let version = installed
+            .select(&selector)
+            .ok_or_else(|| ModuleError::NotFound(spec.to_string()))?;
+        let path = installed.dir.join(version);
+        Ok(ResolvedModule {
+            manifest: read_manifest(&path)?,
+            path,
+        })
+    }
+
+    pub fn list(&self) -> Result<Vec<InstalledModule>, ModuleError> {
+        let mut modules = Vec::new();
+        for project in read_dirs(&self.root)? {
+            for module in read_dirs(&project)? {
+                let (Some(project), Some(module)) = (dir_name(&project), dir_name(&module)) else {
+                    continue;
+                };
+                match self.installed(project, module) {
+                    Ok(installed) => modules.push(installed),
+                    Err(e) => log::warn!("Skipping module {}/{}: {}", project, module, e),
+                }
+            }
+        }
+        modules.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
+        Ok(modules)
+    }
+
+    fn installed(&self, project: &str, module: &str) -> Result<InstalledModule, ModuleError> {
+        let installed = self.versions(project, module)?;
+        let current = installed
+            .current()
+            .ok_or_else(|| ModuleError::NotFound(format!("{}/{}", project, module)))?;
+        let path = installed.dir.join(current);
+        Ok(InstalledModule {
+            manifest: read_manifest(&path)?,
+            path,
+            versions: installed.versions.iter().map(Version::to_string).collect(),
+            previous: installed.pointer.and_then(|pointer| pointer.previous),
+        })
+    }
+
+    fn versions(&self, project: &str, module: &str) -> Result<ModuleVersions, ModuleError> {
+        let dir = self.module_dir(project, module);
+        let mut versions: Vec<Version> = read_dirs(&dir)?
+            .iter()
+            .filter_map(|path| dir_name(path)?.parse().ok())
+            .collect();
+        versions.sort_by(|a, b| b.cmp(a));
+        Ok(ModuleVersions {
+            pointer: pointer::read(&dir)?,
+            dir,
+            versions,
+        })
+    }
+
+    /// Moves every module installed before versions were kept side by side
+    /// into its version directory. Runs at startup, lookups don't migrate.
+    pub fn migrate(&self) -> Result<(), ModuleError> {
+        for project in read_dirs(&self.root)? {
+            for module in read_dirs(&project)? {
+                self.migrate_unversioned(&module)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves a module installed before versions were kept side by side into
+    /// its version directory.
+    fn migrate_unversioned(&self, dir: &Path) -> Result<(), ModuleError> {
+        if !dir.join(MANIFEST_FILE).is_file() {
+            return Ok(());
+        }
+        let manifest = read_manifest(dir)?;
+        let staging = self.staging_dir()?;
+        let moved = staging.join(&manifest.version);
+        fs::rename(dir, &moved)?;
+        fs::create_dir(dir)?;
+        fs::rename(&moved, dir.join(&manifest.version))?;
+        fs::remove_dir(staging)?;
+        pointer::write(
+            dir,
+            &Pointer {
+                version: manifest.version.clone(),
+                previous: None,
+            },
+        )?;
+        log::info!("Migrated module {} to a versioned layout", manifest.name);
+        Ok(())
+    }
+
+    /// Deletes `dir`, moving it out of the runtime's reach first so a failing
+    /// delete can't leave a partially removed module behind.
+    fn remove(&self, dir: &Path) -> Result<(), ModuleError> {
+        let trash = self.staging_dir()?;
+        fs::rename(dir, trash.join("removed"))?;
+        fs::remove_dir_all(&trash)?;
+        Ok(())
+    }
+
+    /// Swaps `staging` in as `target`, keeping whatever was there until the
+    /// new copy is in place.
+    fn replace(&self, staging: &Path, target: &Path) -> Result<(), ModuleError> {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if target.exists() {
+            let trash = self.staging_dir()?;
+            let old = trash.join("previous");
+            fs::rename(target, &old)?;
+            if let Err(e) = fs::rename(staging, target) {
+                fs::rename(&old, target)?;
+                return Err(e.into());
+            }
+            fs::remove_dir_all(trash)?;
+        } else {
+            fs::rename(staging, target)?;
+        }
+        Ok(())
+    }
+
+    /// A fresh directory on the same filesystem as the modules, hidden from
+    /// the runtime by its leading dot.
+    fn staging_dir(&self) -> io::Result<PathBuf> {
+        let dir = self
+            .root
+            .join(STAGING_DIR)
+            .join(uuid::Uuid::new_v4().to_string());
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+}
+
+pub fn read_manifest(module_dir: &Path) -> Result<ModuleManifest, ModuleError> {
+    let manifest: ModuleManifest =
+        serde_json::from_slice(&fs::read(module_dir.join(MANIFEST_FILE))?)?;
+    manifest.validate()?;
+    Ok(manifest)
+}
+
+fn dir_name(path: &Path) -> Option<&str> {
+    path.file_name()?.to_str()
+}
+
+/// Visible subdirectories of `dir`, empty if it doesn't exist.
+fn read_dirs(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut dirs = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() && !entry.file_name().to_string_lossy().starts_with('.') {
+            dirs.push(entry.path());
+        }
+    }
+    Ok(dirs)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn install_module(
+    app_handle: AppHandle,
+    request: InstallModuleRequest,
+) -> Result<InstalledModule, CommandError> {
+    let store = ModuleStore::open(&app_handle)?;
+    let module = tauri::async_runtime::spawn_blocking(move || store.install(request)).await??;
+    Ok(module)
+}
+
+/// Installs a module from an archive, a directory or a local git repository.
+#[tauri::command]
+#[specta::specta]
+pub async fn install_module_from(
+    app_handle: AppHandle,
+    source: ModuleSource,
+) -> Result<InstalledModule, CommandError> {
+    let store = ModuleStore::open(&app_handle)?;
+    let module =
+        tauri::async_runtime::spawn_blocking(move || store.install_from(&source)).await??;
+    Ok(module)
+}
+
+/// Removes `version` of the module, or every version when it is omitted.
+#[tauri::command]
+#[specta::specta]
+pub async fn uninstall_module(
+    app_handle: AppHandle,
+    name: String,
+    version: Option<String>,
+) -> Result<(), CommandError> {
+    let store = ModuleStore::open(&app_handle)?;
+    tauri::async_runtime::spawn_blocking(move || store.uninstall(&name, version.as_deref()))
+        .await??;
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn list_modules(app_handle: AppHandle) -> Result<Vec<InstalledModule>, CommandError> {
+    let store = ModuleStore::open(&app_handle)?;
+    let modules = tauri::async_runtime::spawn_blocking(move || store.list()).await??;
+    Ok(modules)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn set_module_version(
+    app_handle: AppHandle,
+    name: String,
+    version: String,
+) -> Result<InstalledModule, CommandError> {
+    let store = ModuleStore::open(&app_handle)?;
+    let module =
+        tauri::async_runtime::spawn_blocking(move || store.set_current(&name, &version)).await??;
+    Ok(module)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn rollback_module(
+    app_handle: AppHandle,
+    name: String,
+) -> Result<InstalledModule, CommandError> {
+    let store = ModuleStore::open(&app_handle)?;
+    let module = tauri::async_runtime::spawn_blocking(move || store.rollback(&name)).await??;
+    Ok(module)
+}
+
+/// Resolves `<project>/<module>[@<version>]` the same way `/run` does.
+#[tauri::command]
+#[specta::specta]
+pub async fn resolve_module(
+    app_handle: AppHandle,
+    spec: String,
+) -> Result<ResolvedModule, CommandError> {
+    let store = ModuleStore::open(&app_handle)?;
+    let module = tauri::async_runtime::spawn_blocking(move || store.resolve(&spec)).await??;
+    Ok(module)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pointer::POINTER_FILE;
+
+    /// Shared with the runtime's `modules_test.ts`, so both resolve selectors
+    /// the same way.
+    const SELECTORS: &str =
+        include_str!("../../../../edge/functions/main/testdata/version_selectors.json");
+
+    #[derive(Deserialize)]
+    struct Selectors {
+        installed: Vec<Version>,
+        cases: Vec<SelectorCase>,
+    }
+
+    #[derive(Deserialize)]
+    struct SelectorCase {
+        selector: String,
+        #[serde(default)]
+        selects: Option<String>,
+        #[serde(default)]
+        invalid: bool,
+    }
+
+    fn manifest(version: &str) -> ModuleManifest {
+        ModuleManifest {
+            name: "acme/scraper".to_string(),
+            version: version.to_string(),
+            entrypoint: "index.ts".to_string(),
+            permissions: Vec::new(),
+            dependencies: Default::default(),
+        }
+    }
+
+    fn request(version: &str) -> InstallModuleRequest {
+        InstallModuleRequest {
+            manifest: manifest(version),
+            files: vec![ModuleFile {
+                path: "index.ts".to_string(),
+                contents: format!("export const version = {:?};", version),
+            }],
+        }
+    }
+
+    fn current(store: &ModuleStore) -> (String, Option<String>) {
+        let module = store.installed("acme", "scraper").unwrap();
+        (module.manifest.version, module.previous)
+    }
+
+    /// Everything in the module directory, which only ever holds the
+    /// version directories and the pointer.
+    fn entries(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn pair(version: &str, previous: &str) -> (String, Option<String>) {
+        (version.to_string(), Some(previous.to_string()))
+    }
+
+    #[test]
+    fn switches_and_rolls_back_between_installed_versions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = ModuleStore::new(tmp.path());
+        let dir = tmp.path().join("acme/scraper");
+
+        let installed = store.install(request("1.0.0")).unwrap();
+        assert_eq!(installed.path, dir.join("1.0.0"));
+        assert_eq!(installed.previous, None);
+        store.install(request("1.1.0")).unwrap();
+        assert_eq!(current(&store), pair("1.1.0", "1.0.0"));
+        assert_eq!(
+            store.installed("acme", "scraper").unwrap().versions,
+            ["1.1.0", "1.0.0"]
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("1.1.0/index.ts")).unwrap(),
+            r#"export const version = "1.1.0";"#
+        );
+
+        store.set_current("acme/scraper", "1.0.0").unwrap();
+        assert_eq!(current(&store), pair("1.0.0", "1.1.0"));
+        assert_eq!(
+            store.resolve("acme/scraper").unwrap().path,
+            dir.join("1.0.0")
+        );
+        store.rollback("acme/scraper").unwrap();
+        assert_eq!(current(&store), pair("1.1.0", "1.0.0"));
+
+        // Reinstalling the current version keeps what a rollback returns to.
+        store.install(request("1.1.0")).unwrap();
+        assert_eq!(current(&store), pair("1.1.0", "1.0.0"));
+
+        assert!(matches!(
+            store.set_current("acme/scraper", "2.0.0"),
+            Err(ModuleError::NotFound(_))
+        ));
+        assert_eq!(entries(&dir), ["1.0.0", "1.1.0", POINTER_FILE]);
+        assert_eq!(entries(&tmp.path().join(STAGING_DIR)), Vec::<String>::new());
+    }
+
+    #[test]
+    fn replaces_the_pointer_without_leaving_temporary_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let first = Pointer {
+            version: "1.0.0".to_string(),
+            previous: None,
+        };
+        let second = Pointer {
+            version: "1.1.0".to_string(),
+            previous: Some("1.0.0".to_string()),
+        };
+
+        pointer::write(tmp.path(), &first).unwrap();
+        assert_eq!(pointer::read(tmp.path()).unwrap(), Some(first));
+        pointer::write(tmp.path(), &second).unwrap();
+        assert_eq!(pointer::read(tmp.path()).unwrap(), Some(second));
+        assert_eq!(entries(tmp.path()), [POINTER_FILE]);
+    }
+
+    #[test]
+    fn removes_the_current_version_only_with_the_module() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = ModuleStore::new(tmp.path());
+        store.install(request("1.0.0")).unwrap();
+        store.install(request("1.1.0")).unwrap();
+
+        assert!(matches!(
+            store.uninstall("acme/scraper", Some("1.1.0")),
+            Err(ModuleError::VersionInUse(_))
+        ));
+        store.uninstall("acme/scraper", Some("1.0.0")).unwrap();
+        assert_eq!(
+            current(&store),
+            ("1.1.0".to_string(), None),
+            "the removed version is no rollback target"
+        );
+        assert!(matches!(
+            store.rollback("acme/scraper"),
+            Err(ModuleError::NoPreviousVersion(_))
+        ));
+
+        store.uninstall("acme/scraper", None).unwrap();
+        assert!(!tmp.path().join("acme").exists());
+        assert!(store.list().unwrap().is_empty());
+        assert!(matches!(
+            store.uninstall("acme/scraper", None),
+            Err(ModuleError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn migrates_a_module_installed_before_versions_were_kept() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = ModuleStore::new(tmp.path());
+        let dir = tmp.path().join("acme/scraper");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(MANIFEST_FILE),
+            serde_json::to_vec(&manifest("1.0.0")).unwrap(),
+        )
+        .unwrap();
+        fs::write(dir.join("index.ts"), "export {};").unwrap();
+
+        store.migrate().unwrap();
+        assert_eq!(entries(&dir), ["1.0.0", POINTER_FILE]);
+        assert_eq!(
+            fs::read_to_string(dir.join("1.0.0/index.ts")).unwrap(),
+            "export {};"
+        );
+        assert_eq!(current(&store), ("1.0.0".to_string(), None));
+        // Already migrated, nothing to do.
+        store.migrate().unwrap();
+        assert_eq!(entries(&dir), ["1.0.0", POINTER_FILE]);
+
+        store.install(request("2.0.0")).unwrap();
+        assert_eq!(current(&store), pair("2.0.0", "1.0.0"));
+    }
+
+    #[test]
+    fn selects_the_same_versions_as_the_runtime() {
+        let selectors: Selectors = serde_json::from_str(SELECTORS).unwrap();
+        let mut versions = selectors.installed;
+        versions.sort_by(|a, b| b.cmp(a));
+        let installed = ModuleVersions {
+            dir: PathBuf::new(),
+            pointer: None,
+            versions,
+        };
+        for case in selectors.cases {
+            let spec = format!("acme/scraper@{}", case.selector);
+            match parse_spec(&spec) {
+                Ok((_, selector)) => {
+                    assert!(!case.invalid, "{:?} should be invalid", case.selector);
+                    assert_eq!(
+                        installed.select(&selector),
+                        case.selects,
+                        "{:?}",
+                        case.selector
+                    );
+                }
+                Err(e) => assert!(case.invalid, "{:?} failed to parse: {}", case.selector, e),
+            }
+        }
+    }
+}
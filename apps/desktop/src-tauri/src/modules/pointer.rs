@@ -0,0 +1,37 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// File next to the version directories recording which one is active.
+pub const POINTER_FILE: &str = "current.json";
+
+/// The active version of a module and the one it replaced, for rollbacks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pointer {
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>,
+}
+
+pub fn read(module_dir: &Path) -> io::Result<Option<Pointer>> {
+    match fs::read(module_dir.join(POINTER_FILE)) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Replaces the pointer with a rename so readers see either the old or the
+/// new version, never a partial write.
+pub fn write(module_dir: &Path, pointer: &Pointer) -> io::Result<()> {
+    let tmp = module_dir.join(format!(".{}.{}", POINTER_FILE, uuid::Uuid::new_v4()));
+    fs::write(&tmp, serde_json::to_vec_pretty(pointer)?)?;
+    fs::rename(&tmp, module_dir.join(POINTER_FILE)).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
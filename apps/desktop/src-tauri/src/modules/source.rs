@@ -0,0 +1,506 @@
+//! Installing modules from archives, directories and local git repositories.
+//!
+//! Every source is unpacked into a staging directory through [`Unpacker`],
+//! which only accepts regular files and directories at relative paths and
+//! enforces the size limits below, before the manifest is validated and the
+//! module is activated like any other install.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::Deserialize;
+
+use super::{read_manifest, InstalledModule, ModuleError, ModuleStore, MANIFEST_FILE};
+
+/// Largest single file accepted from a source.
+const MAX_FILE_BYTES: u64 = 16 * 1024 * 1024;
+/// Largest module accepted, summed over all of its files.
+const MAX_MODULE_BYTES: u64 = 64 * 1024 * 1024;
+const MAX_FILES: usize = 5_000;
+
+/// Where to install a module from.
+#[derive(Debug, Clone, Deserialize, specta::Type)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ModuleSource {
+    /// A `.tar.gz`, `.tgz`, `.tar` or `.zip` file.
+    Archive { path: PathBuf },
+    /// A directory with `module.json` at its root. `.git` is skipped.
+    Directory { path: PathBuf },
+    /// A git repository on this machine, as a path or `file://` URL.
+    Git {
+        repository: String,
+        /// Branch, tag or commit to install, `HEAD` when omitted.
+        reference: Option<String>,
+    },
+}
+
+impl ModuleSource {
+    /// Guesses the kind of source from a command line argument. Directories
+    /// are copied as they are on disk, including uncommitted changes, unless a
+    /// git `reference` is given or the source is a `file://` URL or a bare
+    /// `.git` repository.
+    pub fn detect(source: &str, reference: Option<String>) -> Self {
+        if reference.is_some() || source.starts_with("file://") || source.ends_with(".git") {
+            ModuleSource::Git {
+                repository: source.to_string(),
+                reference,
+            }
+        } else if Path::new(source).is_dir() {
+            ModuleSource::Directory {
+                path: source.into(),
+            }
+        } else {
+            ModuleSource::Archive {
+                path: source.into(),
+            }
+        }
+    }
+}
+
+impl ModuleStore {
+    pub fn install_from(&self, source: &ModuleSource) -> Result<InstalledModule, ModuleError> {
+        let staging = self.staging_dir()?;
+        let installed = self.unpack_and_activate(source, &staging);
+        // Already gone when the staging directory itself became the module.
+        let _ = fs::remove_dir_all(&staging);
+        installed
+    }
+
+    fn unpack_and_activate(
+        &self,
+        source: &ModuleSource,
+        staging: &Path,
+    ) -> Result<InstalledModule, ModuleError> {
+        let mut unpacker = Unpacker::new(staging);
+        match source {
+            ModuleSource::Archive { path } => unpack_archive(path, &mut unpacker)?,
+            ModuleSource::Directory { path } => copy_dir(path, Path::new(""), &mut unpacker)?,
+            ModuleSource::Git {
+                repository,
+                reference,
+            } => unpack_git(repository, reference.as_deref(), &mut unpacker)?,
+        }
+
+        let root = module_root(staging)?;
+        let manifest = read_manifest(&root)?;
+        if !root.join(&manifest.entrypoint).is_file() {
+            return Err(ModuleError::InvalidManifest(format!(
+                "entrypoint {:?} is not one of the module files",
+                manifest.entrypoint
+            )));
+        }
+        self.activate(&root, &manifest)
+    }
+}
+
+/// Writes files below `root`, refusing anything that would land outside it
+/// or push the module over the size limits.
+struct Unpacker {
+    root: PathBuf,
+    files: usize,
+    bytes: u64,
+}
+
+impl Unpacker {
+    fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            files: 0,
+            bytes: 0,
+        }
+    }
+
+    fn dir(&mut self, path: &Path) -> Result<(), ModuleError> {
+        if let Some(path) = self.resolve(path)? {
+            fs::create_dir_all(path)?;
+        }
+        Ok(())
+    }
+
+    fn file(&mut self, path: &Path, size: u64, contents: impl Read) -> Result<(), ModuleError> {
+        let Some(dest) = self.resolve(path)? else {
+            return Err(ModuleError::InvalidPath(path.display().to_string()));
+        };
+        self.files += 1;
+        if self.files > MAX_FILES {
+            return Err(ModuleError::TooLarge(format!(
+                "it has more than {} files",
+                MAX_FILES
+            )));
+        }
+        let limit = MAX_FILE_BYTES.min(MAX_MODULE_BYTES - self.bytes);
+        let too_large = || {
+            ModuleError::TooLarge(format!(
+                "{} would exceed the limit of {} MiB per file and {} MiB in total",
+                path.display(),
+                MAX_FILE_BYTES >> 20,
+                MAX_MODULE_BYTES >> 20
+            ))
+        };
+        // Headers can lie, so the copy is capped as well.
+        if size > limit {
+            return Err(too_large());
+        }
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let written = io::copy(&mut contents.take(limit + 1), &mut File::create(&dest)?)?;
+        if written > limit {
+            return Err(too_large());
+        }
+        self.bytes += written;
+        Ok(())
+    }
+
+    /// Joins `path` onto the root, or `None` for the root itself.
+    fn resolve(&self, path: &Path) -> Result<Option<PathBuf>, ModuleError> {
+        let mut relative = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(part) => relative.push(part),
+                Component::CurDir => {}
+                _ => return Err(ModuleError::InvalidPath(path.display().to_string())),
+            }
+        }
+        Ok((!relative.as_os_str().is_empty()).then(|| self.root.join(relative)))
+    }
+}
+
+fn unpack_archive(path: &Path, unpacker: &mut Unpacker) -> Result<(), ModuleError> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let file = File::open(path)?;
+    if name.ends_with(".zip") {
+        unpack_zip(file, unpacker)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        unpack_tar(flate2::read::GzDecoder::new(file), unpacker)
+    } else if name.ends_with(".tar") {
+        unpack_tar(file, unpacker)
+    } else {
+        Err(ModuleError::Source(format!(
+            "{} is not a .tar.gz, .tgz, .tar or .zip archive",
+            path.display()
+        )))
+    }
+}
+
+/// Unpacks a `.tar.gz` into `dir` under the same rules and limits as a
+/// module, for other archives the app installs.
+pub fn unpack_tar_gz(reader: impl Read, dir: &Path) -> Result<(), ModuleError> {
+    unpack_tar(
+        flate2::read::GzDecoder::new(reader),
+        &mut Unpacker::new(dir),
+    )
+}
+
+fn unpack_tar(reader: impl Read, unpacker: &mut Unpacker) -> Result<(), ModuleError> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let entry = entry?;
+        let path = entry.path()?.into_owned();
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => unpacker.dir(&path)?,
+            tar::EntryType::Regular | tar::EntryType::Continuous => {
+                let size = entry.size();
+                unpacker.file(&path, size, entry)?
+            }
+            // `git archive` records the commit in a global pax header.
+            tar::EntryType::XGlobalHeader => {}
+            _ => return Err(not_a_file(&path)),
+        }
+    }
+    Ok(())
+}
+
+fn unpack_zip(reader: impl Read + Seek, unpacker: &mut Unpacker) -> Result<(), ModuleError> {
+    let zip_error = |e: zip::result::ZipError| ModuleError::Source(e.to_string());
+    let mut archive = zip::ZipArchive::new(reader).map_err(zip_error)?;
+    for index in 0..archive.len() {
+        let entry = archive.by_index(index).map_err(zip_error)?;
+        let path = PathBuf::from(entry.name());
+        if entry.is_dir() {
+            unpacker.dir(&path)?;
+        } else if entry
+            .unix_mode()
+            .is_some_and(|mode| mode & 0o170000 == 0o120000)
+        {
+            return Err(not_a_file(&path));
+        } else {
+            let size = entry.size();
+            unpacker.file(&path, size, entry)?;
+        }
+    }
+    Ok(())
+}
+
+fn copy_dir(root: &Path, relative: &Path, unpacker: &mut Unpacker) -> Result<(), ModuleError> {
+    for entry in fs::read_dir(root.join(relative))? {
+        let entry = entry?;
+        if entry.file_name() == ".git" {
+            continue;
+        }
+        let path = relative.join(entry.file_name());
+        // Doesn't follow symlinks, so a link can't pull in files from
+        // elsewhere on disk.
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            unpacker.dir(&path)?;
+            copy_dir(root, &path, unpacker)?;
+        } else if file_type.is_file() {
+            let file = File::open(entry.path())?;
+            let size = file.metadata()?.len();
+            unpacker.file(&path, size, file)?;
+        } else {
+            return Err(not_a_file(&path));
+        }
+    }
+    Ok(())
+}
+
+/// Streams `git archive` of `reference` straight into the unpacker, so the
+/// repository's working tree and history are never copied.
+fn unpack_git(
+    repository: &str,
+    reference: Option<&str>,
+    unpacker: &mut Unpacker,
+) -> Result<(), ModuleError> {
+    let repository = local_repository(repository)?;
+    let reference = reference.unwrap_or("HEAD");
+    if reference.starts_with('-') {
+        return Err(ModuleError::Source(format!(
+            "{:?} is not a valid git reference",
+            reference
+        )));
+    }
+
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(&repository)
+        .args(["archive", "--format=tar", reference])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => {
+                ModuleError::Source("git is not installed or not on the PATH".to_string())
+            }
+            _ => e.into(),
+        })?;
+    let unpacked = match child.stdout.take() {
+        Some(stdout) => unpack_tar(stdout, unpacker),
+        None => Ok(()),
+    };
+    if unpacked.is_err() {
+        let _ = child.kill();
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() && unpacked.is_ok() {
+        return Err(ModuleError::Source(format!(
+            "git archive {} failed: {}",
+            reference,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    unpacked
+}
+
+fn local_repository(repository: &str) -> Result<PathBuf, ModuleError> {
+    if !repository.contains("://") {
+        return Ok(repository.into());
+    }
+    tauri::Url::parse(repository)
+        .ok()
+        .filter(|url| url.scheme() == "file")
+        .and_then(|url| url.to_file_path().ok())
+        .ok_or_else(|| {
+            ModuleError::Source(format!(
+                "{} is not a local repository, only paths and file:// URLs are supported",
+                repository
+            ))
+        })
+}
+
+/// The directory holding `module.json`. Archives often wrap the module in a
+/// single top-level directory, which is looked through.
+fn module_root(staging: &Path) -> Result<PathBuf, ModuleError> {
+    if staging.join(MANIFEST_FILE).is_file() {
+        return Ok(staging.to_path_buf());
+    }
+    let entries = fs::read_dir(staging)?.collect::<Result<Vec<_>, _>>()?;
+    if let [entry] = entries.as_slice() {
+        if entry.file_type()?.is_dir() && entry.path().join(MANIFEST_FILE).is_file() {
+            return Ok(entry.path());
+        }
+    }
+    Err(ModuleError::Source(format!(
+        "no {} found at the root of the module",
+        MANIFEST_FILE
+    )))
+}
+
+fn not_a_file(path: &Path) -> ModuleError {
+    ModuleError::Source(format!(
+        "{} is a link or special file, only regular files and directories can be installed",
+        path.display()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+    use crate::testing::tar_entry;
+
+    fn unpack_tar_entries(entries: &[(&str, tar::EntryType, &[u8])]) -> Result<(), ModuleError> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, kind, data) in entries {
+            tar_entry(&mut builder, name, *kind, *data, data.len() as u64);
+        }
+        let archive = builder.into_inner().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        unpack_tar(Cursor::new(archive), &mut Unpacker::new(root.path()))
+    }
+
+    fn unpack_zip_with(
+        build: impl FnOnce(&mut zip::ZipWriter<Cursor<Vec<u8>>>),
+    ) -> Result<(), ModuleError> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        build(&mut writer);
+        let archive = writer.finish().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        unpack_zip(archive, &mut Unpacker::new(root.path()))
+    }
+
+    #[test]
+    fn unpacks_a_tar() {
+        let root = tempfile::tempdir().unwrap();
+        let mut builder = tar::Builder::new(Vec::new());
+        tar_entry(
+            &mut builder,
+            "scraper/",
+            tar::EntryType::Directory,
+            io::empty(),
+            0,
+        );
+        tar_entry(
+            &mut builder,
+            "scraper/index.ts",
+            tar::EntryType::Regular,
+            &b"ok"[..],
+            2,
+        );
+        let archive = builder.into_inner().unwrap();
+        unpack_tar(Cursor::new(archive), &mut Unpacker::new(root.path())).unwrap();
+        assert_eq!(
+            fs::read(root.path().join("scraper/index.ts")).unwrap(),
+            b"ok"
+        );
+    }
+
+    #[test]
+    fn rejects_tar_entries_leaving_the_module() {
+        for name in ["../evil.ts", "scraper/../../evil.ts", "/etc/evil.ts"] {
+            let unpacked = unpack_tar_entries(&[(name, tar::EntryType::Regular, b"evil")]);
+            assert!(
+                matches!(unpacked, Err(ModuleError::InvalidPath(_))),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_tar_links() {
+        for kind in [tar::EntryType::Symlink, tar::EntryType::Link] {
+            let unpacked = unpack_tar_entries(&[("index.ts", kind, b"")]);
+            assert!(
+                matches!(unpacked, Err(ModuleError::Source(_))),
+                "{:?}",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_an_oversized_tar_entry() {
+        let size = MAX_FILE_BYTES + 1;
+        let mut builder = tar::Builder::new(Vec::new());
+        tar_entry(
+            &mut builder,
+            "big.bin",
+            tar::EntryType::Regular,
+            io::repeat(0).take(size),
+            size,
+        );
+        let archive = builder.into_inner().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        let unpacked = unpack_tar(Cursor::new(archive), &mut Unpacker::new(root.path()));
+        assert!(matches!(unpacked, Err(ModuleError::TooLarge(_))));
+    }
+
+    #[test]
+    fn rejects_a_tar_with_too_many_entries() {
+        let names: Vec<_> = (0..=MAX_FILES).map(|i| format!("{}.ts", i)).collect();
+        let entries: Vec<_> = names
+            .iter()
+            .map(|name| (name.as_str(), tar::EntryType::Regular, &b""[..]))
+            .collect();
+        assert!(matches!(
+            unpack_tar_entries(&entries),
+            Err(ModuleError::TooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_zip_entries_leaving_the_module() {
+        for name in ["../evil.ts", "scraper/../../evil.ts", "/etc/evil.ts"] {
+            let unpacked = unpack_zip_with(|zip| {
+                zip.start_file(name, SimpleFileOptions::default()).unwrap();
+                zip.write_all(b"evil").unwrap();
+            });
+            assert!(
+                matches!(unpacked, Err(ModuleError::InvalidPath(_))),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_zip_symlinks() {
+        let unpacked = unpack_zip_with(|zip| {
+            zip.add_symlink("index.ts", "/etc/passwd", SimpleFileOptions::default())
+                .unwrap();
+        });
+        assert!(matches!(unpacked, Err(ModuleError::Source(_))));
+    }
+
+    #[test]
+    fn rejects_an_oversized_zip_entry() {
+        let unpacked = unpack_zip_with(|zip| {
+            zip.start_file("big.bin", SimpleFileOptions::default())
+                .unwrap();
+            io::copy(&mut io::repeat(0).take(MAX_FILE_BYTES + 1), zip).unwrap();
+        });
+        assert!(matches!(unpacked, Err(ModuleError::TooLarge(_))));
+    }
+
+    #[test]
+    fn rejects_a_zip_with_too_many_entries() {
+        let unpacked = unpack_zip_with(|zip| {
+            for i in 0..=MAX_FILES {
+                zip.start_file(format!("{}.ts", i), SimpleFileOptions::default())
+                    .unwrap();
+            }
+        });
+        assert!(matches!(unpacked, Err(ModuleError::TooLarge(_))));
+    }
+}
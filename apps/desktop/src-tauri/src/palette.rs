@@ -0,0 +1,137 @@
+//! The quick-launch palette: a small frameless window for running an
+//! installed module, or opening a workflow, without opening the main window.
+//!
+//! The window is created on first use and hidden, not closed, when it loses
+//! focus, so later openings reuse the loaded page. It is only destroyed with
+//! the main window when the app doesn't keep running in the background. Runs go through
+//! [`run_module`], so the window doesn't need to know the runtime's port.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder, WindowEvent};
+use tauri_plugin_http::reqwest;
+
+use crate::cmd::CommandError;
+use crate::config::LoadedConfig;
+use crate::modules;
+
+pub const LABEL: &str = "palette";
+
+#[derive(Debug, Clone, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct RunModuleRequest {
+    /// `<project>/<module>[@<version>]`, as `/run` takes it.
+    pub spec: String,
+    /// Sent as JSON if it parses as JSON, as plain text otherwise.
+    pub input: String,
+}
+
+/// The runtime's response to a run.
+#[derive(Debug, Clone, Serialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct RunResult {
+    pub status: u16,
+    pub ok: bool,
+    pub body: String,
+    pub execution_id: Option<String>,
+}
+
+pub fn open_palette(app_handle: &AppHandle) -> Result<(), CommandError> {
+    let window = match app_handle.get_webview_window(LABEL) {
+        Some(window) => window,
+        None => {
+            let url = WebviewUrl::App("palette.html".into());
+            let window = WebviewWindowBuilder::new(app_handle, LABEL, url)
+                .title("Craftgen")
+                .inner_size(640.0, 420.0)
+                .decorations(false)
+                .always_on_top(true)
+                .resizable(false)
+                .skip_taskbar(true)
+                .visible(false)
+                .build()?;
+            let handle = window.clone();
+            window.on_window_event(move |event| match event {
+                WindowEvent::Focused(false) => {
+                    if let Err(e) = handle.hide() {
+                        log::error!("Failed to hide palette: {}", e);
+                    }
+                }
+                WindowEvent::CloseRequested { api, .. } => {
+                    api.prevent_close();
+                    if let Err(e) = handle.hide() {
+                        log::error!("Failed to hide palette: {}", e);
+                    }
+                }
+                _ => {}
+            });
+            window
+        }
+    };
+
+    // Centered on every opening, the active monitor may have changed.
+    window.center()?;
+    window.show()?;
+    window.set_focus()?;
+    Ok(())
+}
+
+/// Destroys the palette, which otherwise only ever hides and would keep the
+/// app from exiting once the main window is closed.
+pub fn close_palette(app_handle: &AppHandle) {
+    if let Some(window) = app_handle.get_webview_window(LABEL) {
+        if let Err(e) = window.destroy() {
+            log::error!("Failed to close palette: {}", e);
+        }
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn show_palette(app_handle: AppHandle) -> Result<(), CommandError> {
+    open_palette(&app_handle)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn hide_palette(app_handle: AppHandle) -> Result<(), CommandError> {
+    if let Some(window) = app_handle.get_webview_window(LABEL) {
+        window.hide()?;
+    }
+    Ok(())
+}
+
+/// Runs a module through the runtime, on the port from the loaded config.
+#[tauri::command]
+#[specta::specta]
+pub async fn run_module(
+    app_handle: AppHandle,
+    request: RunModuleRequest,
+) -> Result<RunResult, CommandError> {
+    modules::parse_spec(&request.spec)?;
+    let port = app_handle.state::<LoadedConfig>().config.runtime.port;
+    let content_type = match serde_json::from_str::<serde::de::IgnoredAny>(&request.input) {
+        Ok(_) => "application/json",
+        Err(_) => "text/plain",
+    };
+    let unreachable =
+        |e: reqwest::Error| CommandError::Runtime(format!("the runtime didn't respond: {}", e));
+    let response = reqwest::Client::new()
+        .post(format!("http://localhost:{}/run/{}/", port, request.spec))
+        .header(reqwest::header::CONTENT_TYPE, content_type)
+        .body(request.input)
+        .send()
+        .await
+        .map_err(unreachable)?;
+    let status = response.status();
+    let execution_id = response
+        .headers()
+        .get("x-craftgen-execution-id")
+        .and_then(|id| id.to_str().ok())
+        .map(str::to_string);
+    Ok(RunResult {
+        status: status.as_u16(),
+        ok: status.is_success(),
+        body: response.text().await.map_err(unreachable)?,
+        execution_id,
+    })
+}
@@ -0,0 +1,239 @@
+//! A durable job queue owned by the app rather than the edge runtime, so jobs
+//! survive runtime restarts and crashes and can be inspected from here.
+//!
+//! Jobs live in `queue.sqlite` in the app data directory. Workers in the
+//! runtime lease due jobs of a queue, and ack them when done or nack them to
+//! be retried with exponential backoff. A job that used up its attempts, or
+//! whose lease ran out on its last attempt, becomes dead and stays until it is
+//! retried or deleted. The runtime reaches the queue over the loopback HTTP
+//! API in [`server`], at the URL and with the token it gets as
+//! `CRAFTGEN_QUEUE_URL` and `CRAFTGEN_QUEUE_TOKEN`.
+
+mod server;
+mod store;
+
+use std::io;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tauri::{AppHandle, Manager};
+
+pub use server::QueueEndpoint;
+pub use store::JobQueue;
+
+use crate::cmd::CommandError;
+
+const DATABASE_FILE: &str = "queue.sqlite";
+
+#[derive(Debug, thiserror::Error)]
+pub enum QueueError {
+    #[error(transparent)]
+    Database(#[from] rusqlite::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+    #[error("job {0} doesn't exist")]
+    NotFound(String),
+    #[error("job {0} isn't leased with this token, its lease may have run out")]
+    LeaseLost(String),
+    #[error("{0}")]
+    InvalidInput(String),
+}
+
+impl From<QueueError> for CommandError {
+    fn from(e: QueueError) -> Self {
+        match e {
+            QueueError::Io(e) => e.into(),
+            QueueError::Tauri(e) => e.into(),
+            QueueError::Database(_) => CommandError::Io(e.to_string()),
+            QueueError::NotFound(_) => CommandError::NotFound(e.to_string()),
+            QueueError::LeaseLost(_) | QueueError::InvalidInput(_) => {
+                CommandError::InvalidInput(e.to_string())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum JobState {
+    /// Waiting for its `runAt`, or due and waiting for a worker.
+    Pending,
+    Leased,
+    Succeeded,
+    /// Failed on every attempt, kept until it is retried or deleted.
+    Dead,
+}
+
+impl JobState {
+    fn as_str(self) -> &'static str {
+        match self {
+            JobState::Pending => "pending",
+            JobState::Leased => "leased",
+            JobState::Succeeded => "succeeded",
+            JobState::Dead => "dead",
+        }
+    }
+
+    fn parse(state: &str) -> Option<Self> {
+        match state {
+            "pending" => Some(JobState::Pending),
+            "leased" => Some(JobState::Leased),
+            "succeeded" => Some(JobState::Succeeded),
+            "dead" => Some(JobState::Dead),
+            _ => None,
+        }
+    }
+}
+
+/// Times are milliseconds since the unix epoch.
+#[derive(Debug, Clone, Serialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: String,
+    pub queue: String,
+    pub payload: JsonValue,
+    pub state: JobState,
+    /// Leases so far, including the current one.
+    pub attempts: u32,
+    pub max_attempts: u32,
+    /// When the job is due, or was last due.
+    pub run_at: u64,
+    pub leased_until: Option<u64>,
+    /// From the last nack or expired lease.
+    pub last_error: Option<String>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// A leased job and the token to ack, nack or extend it with.
+#[derive(Debug, Clone, Serialize)]
+pub struct Lease {
+    pub job: Job,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnqueueRequest {
+    pub queue: String,
+    pub payload: JsonValue,
+    /// Runs the job this much later instead of right away, at most 30 days.
+    pub delay_ms: Option<u64>,
+    /// Defaults to 5.
+    pub max_attempts: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaseRequest {
+    /// At most this many jobs, defaults to 1.
+    pub limit: Option<u32>,
+    /// Defaults to 30 seconds.
+    pub lease_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NackRequest {
+    pub token: String,
+    pub error: Option<String>,
+    /// Retries after this long instead of the backoff, at most 30 days.
+    pub delay_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct JobFilter {
+    pub queue: Option<String>,
+    pub state: Option<JobState>,
+    /// Defaults to 100, at most 1000.
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueStats {
+    pub queue: String,
+    pub pending: u32,
+    pub leased: u32,
+    pub succeeded: u32,
+    pub dead: u32,
+    /// When the earliest pending job is due.
+    pub next_run_at: Option<u64>,
+}
+
+/// Opens the queue and starts its HTTP API. The app runs without a queue if
+/// either fails, the runtime just doesn't get its URL then.
+pub fn start(app: &AppHandle) {
+    let queue = app
+        .path()
+        .app_data_dir()
+        .map_err(QueueError::from)
+        .and_then(|dir| JobQueue::open(&dir.join(DATABASE_FILE)));
+    let queue = match queue {
+        Ok(queue) => Arc::new(queue),
+        Err(e) => {
+            log::error!("Failed to open the job queue: {}", e);
+            return;
+        }
+    };
+    app.manage(queue.clone());
+    match server::start(queue) {
+        Ok(endpoint) => {
+            log::info!("job queue listening on {}", endpoint.url);
+            app.manage(endpoint);
+        }
+        Err(e) => log::error!("Failed to start the job queue API: {}", e),
+    }
+}
+
+fn queue(app: &AppHandle) -> Result<Arc<JobQueue>, CommandError> {
+    app.try_state::<Arc<JobQueue>>()
+        .map(|queue| queue.inner().clone())
+        .ok_or_else(|| CommandError::Runtime("the job queue isn't available".to_string()))
+}
+
+/// The most recently created jobs matching `filter`.
+#[tauri::command]
+#[specta::specta]
+pub async fn list_jobs(app_handle: AppHandle, filter: JobFilter) -> Result<Vec<Job>, CommandError> {
+    let queue = queue(&app_handle)?;
+    let jobs = tauri::async_runtime::spawn_blocking(move || queue.list(&filter)).await??;
+    Ok(jobs)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn get_job(app_handle: AppHandle, id: String) -> Result<Job, CommandError> {
+    let queue = queue(&app_handle)?;
+    let job = tauri::async_runtime::spawn_blocking(move || queue.get(&id)).await??;
+    Ok(job)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn list_queues(app_handle: AppHandle) -> Result<Vec<QueueStats>, CommandError> {
+    let queue = queue(&app_handle)?;
+    let stats = tauri::async_runtime::spawn_blocking(move || queue.stats()).await??;
+    Ok(stats)
+}
+
+/// Puts a dead job back on its queue with fresh attempts.
+#[tauri::command]
+#[specta::specta]
+pub async fn retry_job(app_handle: AppHandle, id: String) -> Result<Job, CommandError> {
+    let queue = queue(&app_handle)?;
+    let job = tauri::async_runtime::spawn_blocking(move || queue.retry(&id)).await??;
+    Ok(job)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn delete_job(app_handle: AppHandle, id: String) -> Result<(), CommandError> {
+    let queue = queue(&app_handle)?;
+    tauri::async_runtime::spawn_blocking(move || queue.delete(&id)).await??;
+    Ok(())
+}
@@ -0,0 +1,159 @@
+//! The queue's HTTP API for the edge runtime, on a random port on
+//! `127.0.0.1`. Every request needs `Authorization: Bearer <token>` with the
+//! token the runtime was started with. Bodies and responses are JSON, errors
+//! are `{ kind, message }` like the commands'.
+//!
+//! - `POST /jobs` enqueues, see [`EnqueueRequest`].
+//! - `GET /jobs/<id>` returns a job.
+//! - `POST /queues/<queue>/lease` leases due jobs, see [`LeaseRequest`].
+//! - `POST /jobs/<id>/ack` with `{ token }` marks a job done.
+//! - `POST /jobs/<id>/nack` gives it back to be retried, see [`NackRequest`].
+//! - `POST /jobs/<id>/extend` with `{ token, leaseMs? }` extends a lease.
+//! - `GET /queues` counts the jobs of each queue.
+
+use std::io;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use super::{EnqueueRequest, JobQueue, LeaseRequest, NackRequest, QueueError};
+use crate::loopback::{LoopbackServer, Request, Response};
+
+/// Larger payloads belong in a file or database, with a reference in the job.
+const MAX_BODY: usize = 1024 * 1024;
+
+/// Where the runtime finds the API.
+pub struct QueueEndpoint {
+    pub url: String,
+    pub token: String,
+    _server: LoopbackServer,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TokenRequest {
+    token: String,
+    lease_ms: Option<u64>,
+}
+
+fn error(status: &'static str, kind: &str, message: impl ToString) -> Response {
+    #[derive(Serialize)]
+    struct Error<'a> {
+        kind: &'a str,
+        message: String,
+    }
+    Response::json(
+        status,
+        &Error {
+            kind,
+            message: message.to_string(),
+        },
+    )
+}
+
+impl From<QueueError> for Response {
+    fn from(e: QueueError) -> Self {
+        match e {
+            QueueError::NotFound(_) => error("404 Not Found", "notFound", e),
+            QueueError::LeaseLost(_) => error("409 Conflict", "leaseLost", e),
+            QueueError::InvalidInput(_) => error("400 Bad Request", "invalidInput", e),
+            QueueError::Database(_) | QueueError::Io(_) | QueueError::Tauri(_) => {
+                log::error!("job queue request failed: {}", e);
+                error("500 Internal Server Error", "io", e)
+            }
+        }
+    }
+}
+
+/// Binds the API and serves it for as long as the app runs.
+pub(super) fn start(queue: Arc<JobQueue>) -> io::Result<QueueEndpoint> {
+    let mut server = LoopbackServer::bind()?;
+    let token = uuid::Uuid::new_v4().to_string();
+    let expected = Arc::new(token.clone());
+    server.serve(MAX_BODY, move |request| {
+        let (queue, expected) = (queue.clone(), expected.clone());
+        async move { respond(queue, &expected, request).await }
+    });
+    Ok(QueueEndpoint {
+        url: server.url(""),
+        token,
+        _server: server,
+    })
+}
+
+async fn respond(queue: Arc<JobQueue>, token: &str, request: Request) -> Response {
+    let authorized = request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|bearer| constant_time_eq(bearer.as_bytes(), token.as_bytes()));
+    if !authorized {
+        return error("401 Unauthorized", "unauthorized", "missing or wrong token");
+    }
+    let Some(body) = request.body else {
+        return error(
+            "413 Payload Too Large",
+            "invalidInput",
+            format!("bodies are limited to {} bytes", MAX_BODY),
+        );
+    };
+    let Request { method, path, .. } = request;
+    tauri::async_runtime::spawn_blocking(move || route(&queue, &method, &path, &body))
+        .await
+        .unwrap_or_else(|e| error("500 Internal Server Error", "io", e))
+}
+
+/// Compares every byte, so how long a wrong token takes to reject doesn't
+/// tell how much of it was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T, Response> {
+    // Lets requests without options leave out the body.
+    let body: &[u8] = if body.is_empty() { b"{}" } else { body };
+    serde_json::from_slice(body).map_err(|e| error("400 Bad Request", "invalidInput", e))
+}
+
+fn route(queue: &JobQueue, method: &str, path: &str, body: &[u8]) -> Response {
+    let segments: Vec<_> = path.trim_matches('/').split('/').collect();
+    let result = match (method, segments.as_slice()) {
+        ("POST", ["jobs"]) => parse::<EnqueueRequest>(body).map(|request| {
+            queue
+                .enqueue(request)
+                .map(|job| Response::json("201 Created", &job))
+        }),
+        ("GET", ["jobs", id]) => Ok(queue.get(id).map(|job| Response::json("200 OK", &job))),
+        ("POST", ["jobs", id, "ack"]) => parse::<TokenRequest>(body).map(|request| {
+            queue
+                .ack(id, &request.token)
+                .map(|job| Response::json("200 OK", &job))
+        }),
+        ("POST", ["jobs", id, "nack"]) => parse::<NackRequest>(body).map(|request| {
+            queue
+                .nack(id, request)
+                .map(|job| Response::json("200 OK", &job))
+        }),
+        ("POST", ["jobs", id, "extend"]) => parse::<TokenRequest>(body).map(|request| {
+            queue
+                .extend(id, &request.token, request.lease_ms)
+                .map(|job| Response::json("200 OK", &job))
+        }),
+        ("POST", ["queues", name, "lease"]) => parse::<LeaseRequest>(body).map(|request| {
+            queue
+                .lease(name, request)
+                .map(|leases| Response::json("200 OK", &leases))
+        }),
+        ("GET", ["queues"]) => Ok(queue.stats().map(|stats| Response::json("200 OK", &stats))),
+        _ => Err(error(
+            "404 Not Found",
+            "notFound",
+            format!("no route {} {}", method, path),
+        )),
+    };
+    match result {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => e.into(),
+        Err(response) => response,
+    }
+}
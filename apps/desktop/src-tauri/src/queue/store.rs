@@ -0,0 +1,577 @@
+//! The jobs table and every state change of a job.
+
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde_json::Value as JsonValue;
+
+use super::{
+    EnqueueRequest, Job, JobFilter, JobState, Lease, LeaseRequest, NackRequest, QueueError,
+    QueueStats,
+};
+
+const SCHEMA: &str = "
+    PRAGMA journal_mode = WAL;
+    CREATE TABLE IF NOT EXISTS jobs (
+        id TEXT PRIMARY KEY,
+        queue TEXT NOT NULL,
+        payload TEXT NOT NULL,
+        state TEXT NOT NULL,
+        attempts INTEGER NOT NULL DEFAULT 0,
+        max_attempts INTEGER NOT NULL,
+        run_at INTEGER NOT NULL,
+        lease_token TEXT,
+        leased_until INTEGER,
+        last_error TEXT,
+        created_at INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS jobs_ready ON jobs (queue, state, run_at);
+    CREATE INDEX IF NOT EXISTS jobs_state ON jobs (state, updated_at);
+";
+
+const COLUMNS: &str = "id, queue, payload, state, attempts, max_attempts, run_at, \
+    leased_until, last_error, created_at, updated_at";
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+const DEFAULT_LEASE_MS: u64 = 30_000;
+const MAX_LEASE_MS: u64 = 60 * 60 * 1000;
+const MAX_LEASE_LIMIT: u32 = 100;
+const DEFAULT_LIST_LIMIT: u32 = 100;
+const MAX_LIST_LIMIT: u32 = 1000;
+/// The longest a job can be put off, by its delay or a retry's.
+const MAX_DELAY_MS: u64 = 30 * 24 * 60 * 60 * 1000;
+
+/// The first retry waits this long, every further one twice as long.
+const BACKOFF_BASE_MS: u64 = 1000;
+const BACKOFF_MAX_MS: u64 = 60 * 60 * 1000;
+
+/// How long succeeded jobs stay around for inspection.
+const SUCCEEDED_RETENTION_MS: u64 = 24 * 60 * 60 * 1000;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// How long to wait before the next attempt after `attempts` failed.
+fn backoff(attempts: u32) -> u64 {
+    let exponent = attempts.saturating_sub(1).min(32);
+    BACKOFF_BASE_MS
+        .saturating_mul(1 << exponent)
+        .min(BACKOFF_MAX_MS)
+}
+
+/// Queue names end up in URLs.
+fn validate_queue(queue: &str) -> Result<(), QueueError> {
+    let valid = !queue.is_empty()
+        && queue.len() <= 128
+        && queue
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+    if valid {
+        Ok(())
+    } else {
+        Err(QueueError::InvalidInput(format!(
+            "invalid queue name {:?}, use up to 128 letters, digits, `-`, `_`, `.` and `:`",
+            queue
+        )))
+    }
+}
+
+fn job_from_row(row: &Row) -> rusqlite::Result<Job> {
+    let state: String = row.get(3)?;
+    Ok(Job {
+        id: row.get(0)?,
+        queue: row.get(1)?,
+        payload: row.get::<_, JsonValue>(2)?,
+        state: JobState::parse(&state).ok_or_else(|| {
+            rusqlite::Error::FromSqlConversionFailure(
+                3,
+                rusqlite::types::Type::Text,
+                format!("unknown job state {:?}", state).into(),
+            )
+        })?,
+        attempts: row.get(4)?,
+        max_attempts: row.get(5)?,
+        run_at: row.get(6)?,
+        leased_until: row.get(7)?,
+        last_error: row.get(8)?,
+        created_at: row.get(9)?,
+        updated_at: row.get(10)?,
+    })
+}
+
+/// The queue database, `queue.sqlite` in the app data directory, managed as
+/// app state.
+pub struct JobQueue {
+    conn: Mutex<Connection>,
+}
+
+impl JobQueue {
+    pub fn open(path: &Path) -> Result<Self, QueueError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self, QueueError> {
+        conn.execute_batch(SCHEMA)?;
+        let queue = Self {
+            conn: Mutex::new(conn),
+        };
+        queue.reclaim()?;
+        Ok(queue)
+    }
+
+    /// Returns leases that ran out to the queue, or to the dead letters if
+    /// they used up their attempts, and drops old succeeded jobs. Runs before
+    /// anything reads jobs, so there is no timer to miss.
+    fn reclaim_locked(conn: &Connection, now: u64) -> rusqlite::Result<()> {
+        let expired = conn
+            .prepare("SELECT id, attempts FROM jobs WHERE state = 'leased' AND leased_until <= ?1")?
+            .query_map(params![now], |row| {
+                Ok((row.get::<_, String>(0)?, row.get(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        // Backs off like a nack, a worker that keeps dying on a job shouldn't
+        // get it again right away.
+        for (id, attempts) in expired {
+            conn.execute(
+                "UPDATE jobs SET
+                    state = CASE WHEN attempts >= max_attempts THEN 'dead' ELSE 'pending' END,
+                    last_error = 'lease expired', lease_token = NULL, leased_until = NULL,
+                    run_at = ?2, updated_at = ?3
+                WHERE id = ?1",
+                params![id, now.saturating_add(backoff(attempts)), now],
+            )?;
+        }
+        conn.execute(
+            "DELETE FROM jobs WHERE state = 'succeeded' AND updated_at < ?1",
+            params![now.saturating_sub(SUCCEEDED_RETENTION_MS)],
+        )?;
+        Ok(())
+    }
+
+    fn reclaim(&self) -> Result<(), QueueError> {
+        let conn = self.conn.lock().unwrap();
+        Ok(Self::reclaim_locked(&conn, now())?)
+    }
+
+    fn get_locked(conn: &Connection, id: &str) -> Result<Job, QueueError> {
+        conn.query_row(
+            &format!("SELECT {} FROM jobs WHERE id = ?1", COLUMNS),
+            params![id],
+            job_from_row,
+        )
+        .optional()?
+        .ok_or_else(|| QueueError::NotFound(id.to_string()))
+    }
+
+    pub fn enqueue(&self, request: EnqueueRequest) -> Result<Job, QueueError> {
+        validate_queue(&request.queue)?;
+        let max_attempts = request.max_attempts.unwrap_or(DEFAULT_MAX_ATTEMPTS);
+        if max_attempts == 0 {
+            return Err(QueueError::InvalidInput(
+                "maxAttempts has to be at least 1".to_string(),
+            ));
+        }
+        let now = now();
+        let id = uuid::Uuid::new_v4().to_string();
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO jobs (id, queue, payload, state, max_attempts, run_at, created_at, updated_at)
+            VALUES (?1, ?2, ?3, 'pending', ?4, ?5, ?6, ?6)",
+            params![
+                id,
+                request.queue,
+                request.payload,
+                max_attempts,
+                now.saturating_add(request.delay_ms.unwrap_or(0).min(MAX_DELAY_MS)),
+                now
+            ],
+        )?;
+        Self::get_locked(&conn, &id)
+    }
+
+    /// Leases up to `limit` jobs of `queue` that are due, oldest first. Each
+    /// lease counts as an attempt.
+    pub fn lease(&self, queue: &str, request: LeaseRequest) -> Result<Vec<Lease>, QueueError> {
+        validate_queue(queue)?;
+        let limit = request.limit.unwrap_or(1).clamp(1, MAX_LEASE_LIMIT);
+        let lease_ms = request
+            .lease_ms
+            .unwrap_or(DEFAULT_LEASE_MS)
+            .clamp(1, MAX_LEASE_MS);
+        let now = now();
+
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        Self::reclaim_locked(&tx, now)?;
+        let ids = tx
+            .prepare(
+                "SELECT id FROM jobs WHERE queue = ?1 AND state = 'pending' AND run_at <= ?2
+                ORDER BY run_at, created_at LIMIT ?3",
+            )?
+            .query_map(params![queue, now, limit], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let mut leases = Vec::with_capacity(ids.len());
+        for id in ids {
+            let token = uuid::Uuid::new_v4().to_string();
+            tx.execute(
+                "UPDATE jobs SET state = 'leased', attempts = attempts + 1, lease_token = ?2,
+                    leased_until = ?3, updated_at = ?4
+                WHERE id = ?1",
+                params![id, token, now.saturating_add(lease_ms), now],
+            )?;
+            leases.push(Lease {
+                job: Self::get_locked(&tx, &id)?,
+                token,
+            });
+        }
+        tx.commit()?;
+        Ok(leases)
+    }
+
+    /// Fails with [`QueueError::LeaseLost`] unless `id` is leased with
+    /// `token`, e.g. because the lease expired and the job was leased again.
+    fn check_lease(conn: &Connection, id: &str, token: &str) -> Result<Job, QueueError> {
+        let job = Self::get_locked(conn, id)?;
+        let current: Option<String> = conn.query_row(
+            "SELECT lease_token FROM jobs WHERE id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
+        if job.state != JobState::Leased || current.as_deref() != Some(token) {
+            return Err(QueueError::LeaseLost(id.to_string()));
+        }
+        Ok(job)
+    }
+
+    /// Marks a leased job as done.
+    pub fn ack(&self, id: &str, token: &str) -> Result<Job, QueueError> {
+        let now = now();
+        let conn = self.conn.lock().unwrap();
+        Self::reclaim_locked(&conn, now)?;
+        Self::check_lease(&conn, id, token)?;
+        conn.execute(
+            "UPDATE jobs SET state = 'succeeded', lease_token = NULL, leased_until = NULL,
+                last_error = NULL, updated_at = ?2
+            WHERE id = ?1",
+            params![id, now],
+        )?;
+        Self::get_locked(&conn, id)
+    }
+
+    /// Gives a leased job back after it failed. It is retried after
+    /// `delay_ms` or an exponential backoff, or dead-lettered once it used up
+    /// its attempts.
+    pub fn nack(&self, id: &str, request: NackRequest) -> Result<Job, QueueError> {
+        let now = now();
+        let conn = self.conn.lock().unwrap();
+        Self::reclaim_locked(&conn, now)?;
+        let job = Self::check_lease(&conn, id, &request.token)?;
+        let state = if job.attempts >= job.max_attempts {
+            JobState::Dead
+        } else {
+            JobState::Pending
+        };
+        let delay = request
+            .delay_ms
+            .unwrap_or_else(|| backoff(job.attempts))
+            .min(MAX_DELAY_MS);
+        conn.execute(
+            "UPDATE jobs SET state = ?2, lease_token = NULL, leased_until = NULL,
+                last_error = ?3, run_at = ?4, updated_at = ?5
+            WHERE id = ?1",
+            params![
+                id,
+                state.as_str(),
+                request.error,
+                now.saturating_add(delay),
+                now
+            ],
+        )?;
+        Self::get_locked(&conn, id)
+    }
+
+    /// Extends a lease for a job that takes longer than expected.
+    pub fn extend(&self, id: &str, token: &str, lease_ms: Option<u64>) -> Result<Job, QueueError> {
+        let now = now();
+        let lease_ms = lease_ms.unwrap_or(DEFAULT_LEASE_MS).clamp(1, MAX_LEASE_MS);
+        let conn = self.conn.lock().unwrap();
+        Self::reclaim_locked(&conn, now)?;
+        Self::check_lease(&conn, id, token)?;
+        conn.execute(
+            "UPDATE jobs SET leased_until = ?2, updated_at = ?3 WHERE id = ?1",
+            params![id, now.saturating_add(lease_ms), now],
+        )?;
+        Self::get_locked(&conn, id)
+    }
+
+    pub fn get(&self, id: &str) -> Result<Job, QueueError> {
+        let conn = self.conn.lock().unwrap();
+        Self::reclaim_locked(&conn, now())?;
+        Self::get_locked(&conn, id)
+    }
+
+    /// Newest first.
+    pub fn list(&self, filter: &JobFilter) -> Result<Vec<Job>, QueueError> {
+        let conn = self.conn.lock().unwrap();
+        Self::reclaim_locked(&conn, now())?;
+        let jobs = conn
+            .prepare(&format!(
+                "SELECT {} FROM jobs
+                WHERE (?1 IS NULL OR queue = ?1) AND (?2 IS NULL OR state = ?2)
+                ORDER BY created_at DESC LIMIT ?3",
+                COLUMNS
+            ))?
+            .query_map(
+                params![
+                    filter.queue,
+                    filter.state.map(JobState::as_str),
+                    filter
+                        .limit
+                        .unwrap_or(DEFAULT_LIST_LIMIT)
+                        .clamp(1, MAX_LIST_LIMIT)
+                ],
+                job_from_row,
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(jobs)
+    }
+
+    /// How many jobs each queue has in each state.
+    pub fn stats(&self) -> Result<Vec<QueueStats>, QueueError> {
+        let conn = self.conn.lock().unwrap();
+        Self::reclaim_locked(&conn, now())?;
+        let stats = conn
+            .prepare(
+                "SELECT queue,
+                    SUM(state = 'pending'), SUM(state = 'leased'),
+                    SUM(state = 'succeeded'), SUM(state = 'dead'),
+                    MIN(CASE WHEN state = 'pending' THEN run_at END)
+                FROM jobs GROUP BY queue ORDER BY queue",
+            )?
+            .query_map([], |row| {
+                Ok(QueueStats {
+                    queue: row.get(0)?,
+                    pending: row.get(1)?,
+                    leased: row.get(2)?,
+                    succeeded: row.get(3)?,
+                    dead: row.get(4)?,
+                    next_run_at: row.get(5)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(stats)
+    }
+
+    /// Puts a dead-lettered job back on its queue with fresh attempts.
+    pub fn retry(&self, id: &str) -> Result<Job, QueueError> {
+        let now = now();
+        let conn = self.conn.lock().unwrap();
+        Self::reclaim_locked(&conn, now)?;
+        let job = Self::get_locked(&conn, id)?;
+        if job.state != JobState::Dead {
+            return Err(QueueError::InvalidInput(format!(
+                "job {} is {}, only dead jobs can be retried",
+                id,
+                job.state.as_str()
+            )));
+        }
+        conn.execute(
+            "UPDATE jobs SET state = 'pending', attempts = 0, run_at = ?2, updated_at = ?2
+            WHERE id = ?1",
+            params![id, now],
+        )?;
+        Self::get_locked(&conn, id)
+    }
+
+    /// Removes a job in any state. A worker still holding its lease gets
+    /// [`QueueError::NotFound`] when it acks.
+    pub fn delete(&self, id: &str) -> Result<(), QueueError> {
+        let conn = self.conn.lock().unwrap();
+        if conn.execute("DELETE FROM jobs WHERE id = ?1", params![id])? == 0 {
+            return Err(QueueError::NotFound(id.to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue() -> JobQueue {
+        JobQueue::with_connection(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    fn enqueue(queue: &JobQueue, max_attempts: u32, delay_ms: Option<u64>) -> Job {
+        queue
+            .enqueue(EnqueueRequest {
+                queue: "test".to_string(),
+                payload: serde_json::json!({ "input": 1 }),
+                delay_ms,
+                max_attempts: Some(max_attempts),
+            })
+            .unwrap()
+    }
+
+    fn lease(queue: &JobQueue) -> Vec<Lease> {
+        queue
+            .lease(
+                "test",
+                LeaseRequest {
+                    limit: Some(10),
+                    lease_ms: None,
+                },
+            )
+            .unwrap()
+    }
+
+    fn nack(queue: &JobQueue, lease: &Lease) -> Job {
+        queue
+            .nack(
+                &lease.job.id,
+                NackRequest {
+                    token: lease.token.clone(),
+                    error: Some("failed".to_string()),
+                    delay_ms: None,
+                },
+            )
+            .unwrap()
+    }
+
+    /// Makes a pending job due now, instead of waiting out its backoff.
+    fn make_due(queue: &JobQueue, id: &str) {
+        let conn = queue.conn.lock().unwrap();
+        conn.execute("UPDATE jobs SET run_at = 0 WHERE id = ?1", params![id])
+            .unwrap();
+    }
+
+    #[test]
+    fn leases_due_jobs_once() {
+        let queue = queue();
+        let first = enqueue(&queue, 5, None);
+        let second = enqueue(&queue, 5, None);
+        enqueue(&queue, 5, Some(60_000));
+
+        let leases = lease(&queue);
+        let mut ids: Vec<_> = leases.iter().map(|lease| lease.job.id.as_str()).collect();
+        ids.sort();
+        let mut due = [first.id.as_str(), second.id.as_str()];
+        due.sort();
+        assert_eq!(ids, due);
+        assert!(leases
+            .iter()
+            .all(|lease| lease.job.state == JobState::Leased && lease.job.attempts == 1));
+        assert!(lease(&queue).is_empty());
+
+        let job = queue.ack(&leases[0].job.id, &leases[0].token).unwrap();
+        assert_eq!(job.state, JobState::Succeeded);
+        assert!(matches!(
+            queue.ack(&leases[1].job.id, "wrong token"),
+            Err(QueueError::LeaseLost(_))
+        ));
+    }
+
+    #[test]
+    fn backs_off_exponentially_between_attempts() {
+        let queue = queue();
+        let id = enqueue(&queue, 5, None).id;
+        for attempt in 1..=3 {
+            let leases = lease(&queue);
+            assert_eq!(leases.len(), 1);
+            assert_eq!(leases[0].job.attempts, attempt);
+            let before = now();
+            let job = nack(&queue, &leases[0]);
+            assert_eq!(job.state, JobState::Pending);
+            assert_eq!(job.last_error.as_deref(), Some("failed"));
+            let wait = BACKOFF_BASE_MS << (attempt - 1);
+            assert!(job.run_at >= before + wait && job.run_at <= now() + wait);
+            assert!(lease(&queue).is_empty());
+            make_due(&queue, &id);
+        }
+        assert_eq!(backoff(40), BACKOFF_MAX_MS);
+    }
+
+    #[test]
+    fn dead_letters_after_the_last_attempt() {
+        let queue = queue();
+        let id = enqueue(&queue, 2, None).id;
+        nack(&queue, &lease(&queue)[0]);
+        make_due(&queue, &id);
+        let job = nack(&queue, &lease(&queue)[0]);
+        assert_eq!(job.state, JobState::Dead);
+        make_due(&queue, &id);
+        assert!(lease(&queue).is_empty());
+
+        let job = queue.retry(&id).unwrap();
+        assert_eq!((job.state, job.attempts), (JobState::Pending, 0));
+        assert_eq!(lease(&queue).len(), 1);
+    }
+
+    #[test]
+    fn reclaims_expired_leases_with_backoff() {
+        let queue = queue();
+        let id = enqueue(&queue, 2, None).id;
+        let first = lease(&queue).remove(0);
+
+        let later = now() + MAX_LEASE_MS;
+        JobQueue::reclaim_locked(&queue.conn.lock().unwrap(), later).unwrap();
+        let job = queue.get(&id).unwrap();
+        assert_eq!(job.state, JobState::Pending);
+        assert_eq!(job.last_error.as_deref(), Some("lease expired"));
+        assert_eq!(job.run_at, later + backoff(1));
+        assert!(matches!(
+            queue.ack(&id, &first.token),
+            Err(QueueError::LeaseLost(_))
+        ));
+
+        make_due(&queue, &id);
+        lease(&queue);
+        JobQueue::reclaim_locked(&queue.conn.lock().unwrap(), later).unwrap();
+        assert_eq!(queue.get(&id).unwrap().state, JobState::Dead);
+    }
+
+    #[test]
+    fn caps_delays_and_list_limits() {
+        let queue = queue();
+        let job = enqueue(&queue, 5, Some(u64::MAX));
+        assert!(job.run_at <= now() + MAX_DELAY_MS);
+
+        make_due(&queue, &job.id);
+        let leases = lease(&queue);
+        let job = queue
+            .nack(
+                &leases[0].job.id,
+                NackRequest {
+                    token: leases[0].token.clone(),
+                    error: None,
+                    delay_ms: Some(u64::MAX),
+                },
+            )
+            .unwrap();
+        assert!(job.run_at <= now() + MAX_DELAY_MS);
+
+        enqueue(&queue, 5, None);
+        let list = |limit| {
+            queue
+                .list(&JobFilter {
+                    limit: Some(limit),
+                    ..Default::default()
+                })
+                .unwrap()
+                .len()
+        };
+        assert_eq!(list(0), 1);
+        assert_eq!(list(u32::MAX), 2);
+    }
+}
@@ -1,13 +1,379 @@
-use tauri::Manager;
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Manager};
+use tauri_plugin_http::reqwest;
+use tauri_plugin_shell::{process::{CommandChild, CommandEvent}, ShellExt};
 use tokio::sync::Mutex;
 
-use crate::cmd::open_main_window;
-// use crate::tray::EXIT_FLAG;
+use crate::cmd::{open_main_window, CommandError};
+use crate::config::LoadedConfig;
+use crate::{functions, logs, queue, settings, telemetry, tray, updater, window_state};
+use crate::workspace::WorkspacePaths;
+use crate::tray::EXIT_FLAG;
 use crate::{ AppState};
 
 #[cfg(target_os = "macos")]
 use crate::dock;
 
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+/// How long a starting runtime may fail health checks before it counts as
+/// degraded.
+const STARTUP_GRACE: Duration = Duration::from_secs(30);
+/// Lines of runtime output attached to a crash report.
+const CRASH_LOG_LINES: usize = 50;
+
+/// Lifecycle of the edge runtime, shown by the tray icon and tooltip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeStatus {
+    Starting,
+    Healthy,
+    /// Running but failing health checks.
+    Degraded,
+    /// Exited without being asked to.
+    Crashed,
+    /// An app update is being downloaded or installed.
+    Updating,
+}
+
+impl RuntimeStatus {
+    /// Whether the user should be offered to restart the runtime.
+    pub fn is_failure(self) -> bool {
+        matches!(self, RuntimeStatus::Degraded | RuntimeStatus::Crashed)
+    }
+}
+
+impl fmt::Display for RuntimeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RuntimeStatus::Starting => "starting",
+            RuntimeStatus::Healthy => "healthy",
+            RuntimeStatus::Degraded => "degraded",
+            RuntimeStatus::Crashed => "crashed",
+            RuntimeStatus::Updating => "updating",
+        })
+    }
+}
+
+/// Why the runtime couldn't be started.
+#[derive(Debug, thiserror::Error)]
+pub enum RuntimeError {
+    #[error("failed to resolve the runtime's directories: {0}")]
+    Paths(#[from] tauri::Error),
+    #[error("failed to spawn the edge runtime: {0}")]
+    Spawn(#[from] tauri_plugin_shell::Error),
+}
+
+impl From<RuntimeError> for CommandError {
+    fn from(e: RuntimeError) -> Self {
+        CommandError::Runtime(e.to_string())
+    }
+}
+
+/// What is known about the sidecar's health.
+pub struct RuntimeHealth(std::sync::Mutex<HealthState>);
+
+struct HealthState {
+    /// Last observed status of the process itself, never `Updating`.
+    status: RuntimeStatus,
+    since: Instant,
+    updating: bool,
+    /// The process `kill_sidecar_process` stopped, whose exit isn't a crash.
+    killed_pid: Option<u32>,
+}
+
+impl HealthState {
+    fn effective(&self) -> RuntimeStatus {
+        if self.updating {
+            RuntimeStatus::Updating
+        } else {
+            self.status
+        }
+    }
+
+    fn set(&mut self, status: RuntimeStatus) {
+        if self.status != status {
+            self.status = status;
+            self.since = Instant::now();
+        }
+    }
+}
+
+impl Default for RuntimeHealth {
+    fn default() -> Self {
+        Self(std::sync::Mutex::new(HealthState {
+            status: RuntimeStatus::Starting,
+            since: Instant::now(),
+            updating: false,
+            killed_pid: None,
+        }))
+    }
+}
+
+pub fn status(app_handle: &AppHandle) -> RuntimeStatus {
+    app_handle.state::<RuntimeHealth>().0.lock().unwrap().effective()
+}
+
+/// Applies `f` to the health state and updates the tray if the status changed.
+fn update_health(app_handle: &AppHandle, f: impl FnOnce(&mut HealthState)) {
+    let (before, after) = {
+        let mut state = app_handle.state::<RuntimeHealth>().0.lock().unwrap();
+        let before = state.effective();
+        f(&mut state);
+        (before, state.effective())
+    };
+    if before != after {
+        log::info!("Runtime status: {} -> {}", before, after);
+        tray::set_status(app_handle, after);
+    }
+}
+
+/// Marks an app update as in progress, which takes precedence over the
+/// runtime's own status until it is cleared.
+pub fn set_updating(app_handle: &AppHandle, updating: bool) {
+    update_health(app_handle, |state| state.updating = updating);
+}
+
+/// Polls the runtime's health endpoint for the lifetime of the app.
+pub fn spawn_health_monitor(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        let client = reqwest::Client::new();
+        loop {
+            tokio::time::sleep(HEALTH_CHECK_INTERVAL).await;
+            let healthy = is_serving(&app_handle, &client).await;
+            update_health(&app_handle, |state| match (state.status, healthy) {
+                // Only a restart clears a crash.
+                (RuntimeStatus::Crashed, _) => {}
+                (_, true) => state.set(RuntimeStatus::Healthy),
+                (RuntimeStatus::Starting, false) if state.since.elapsed() < STARTUP_GRACE => {}
+                (_, false) => state.set(RuntimeStatus::Degraded),
+            });
+        }
+    });
+}
+
+/// Whether the runtime answers its health endpoint.
+async fn is_serving(app_handle: &AppHandle, client: &reqwest::Client) -> bool {
+    let port = app_handle.state::<LoadedConfig>().config.runtime.port;
+    client
+        .get(format!("http://localhost:{}/_internal/health", port))
+        .timeout(HEALTH_CHECK_TIMEOUT)
+        .send()
+        .await
+        .is_ok_and(|response| response.status().is_success())
+}
+
+/// Whether any runtime answers on the configured port, e.g. the one of the
+/// app while this process only runs a CLI command.
+pub async fn is_answering(app_handle: &AppHandle) -> bool {
+    is_serving(app_handle, &reqwest::Client::new()).await
+}
+
+/// Waits until the runtime process `pid` answers its health endpoint, giving
+/// up when it exits, is replaced or `timeout` runs out. Unlike
+/// [`wait_until_healthy`] this asks the runtime itself, the monitor's status
+/// may still describe the process `pid` replaced.
+pub async fn wait_until_serving(app_handle: &AppHandle, pid: u32, timeout: Duration) -> bool {
+    let client = reqwest::Client::new();
+    let started = Instant::now();
+    while started.elapsed() < timeout {
+        let running = app_handle
+            .state::<Mutex<AppState>>()
+            .lock()
+            .await
+            .sidecar_handle
+            .as_ref()
+            .map(CommandChild::pid);
+        // A crash of `pid` is the only one recorded since it was started.
+        let crashed =
+            app_handle.state::<RuntimeHealth>().0.lock().unwrap().status == RuntimeStatus::Crashed;
+        if running != Some(pid) || crashed {
+            return false;
+        }
+        if is_serving(app_handle, &client).await {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+    false
+}
+
+/// Waits until the runtime passes a health check, giving up when it crashes
+/// or `timeout` runs out. Callers that just (re)started it should allow for
+/// its startup grace period.
+pub async fn wait_until_healthy(app_handle: &AppHandle, timeout: Duration) -> bool {
+    let started = Instant::now();
+    loop {
+        match status(app_handle) {
+            RuntimeStatus::Healthy => return true,
+            RuntimeStatus::Crashed => return false,
+            _ if started.elapsed() > timeout => return false,
+            _ => tokio::time::sleep(Duration::from_secs(1)).await,
+        }
+    }
+}
+
+/// Stops the runtime if it is running and starts it again.
+pub fn restart(app_handle: &AppHandle) {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn(async move {
+        // Already logged, and shown as a crash by the tray.
+        let _ = start_sidecar_process(&app_handle).await;
+    });
+}
+
+pub async fn start_edge_runtime(
+    app_handle: tauri::AppHandle,
+) -> Result<CommandChild, RuntimeError> {
+    log::info!("Starting edge runtime");
+
+    kill_sidecar_process(&app_handle);
+    update_health(&app_handle, |state| state.set(RuntimeStatus::Starting));
+
+    let resource_path = functions::dir(&app_handle)?;
+    let main_service = resource_path.join("main");
+    log::info!(
+        "Serving functions from {}, main service {}",
+        resource_path.display(),
+        main_service.display()
+    );
+
+    let event_worker = resource_path.join("event");
+    let import_map = resource_path.join("import_map.json");
+    let config = app_handle.state::<LoadedConfig>().config.runtime.clone();
+    let port = config.port.to_string();
+
+    let workspace = WorkspacePaths::resolve(&app_handle)?;
+    if let Err(e) = workspace.ensure() {
+        log::error!("Failed to create workspace directories: {:?}", e);
+    }
+    // `DB_LOCATION` is used as a filename prefix, so it needs the trailing separator.
+    // Modules are looked up in the workspace first, then in the bundled functions.
+    let mut env = vec![
+        (
+            "SERVICE_BASE_DIR",
+            resource_path.to_string_lossy().to_string(),
+        ),
+        (
+            "CRAFTGEN_MODULES_DIR",
+            workspace.modules.to_string_lossy().to_string(),
+        ),
+        (
+            "DB_LOCATION",
+            format!("{}{}", workspace.db.to_string_lossy(), std::path::MAIN_SEPARATOR),
+        ),
+    ];
+    if let Some(endpoint) = app_handle.try_state::<queue::QueueEndpoint>() {
+        env.push(("CRAFTGEN_QUEUE_URL", endpoint.url.clone()));
+        env.push(("CRAFTGEN_QUEUE_TOKEN", endpoint.token.clone()));
+    }
+
+    // let ipc_command = app_handle
+    //     .shell()
+    //     .sidecar("deno")
+    //     .unwrap()
+    //     .args([
+    //         "run",
+    //         "./functions/ipc-api/server.ts"
+    //     ])
+    //     .envs(env.clone());
+
+
+
+    let sidecar_command = app_handle
+        .shell()
+        .sidecar("edge-runtime")?
+        .args([
+            "start",
+            "--main-service",
+            &main_service.to_string_lossy(),
+            "--event-worker",
+            &event_worker.to_string_lossy(),
+            // "-v",
+            "--import-map", 
+            &import_map.to_string_lossy(),
+            "-p",
+            &port,
+        ])
+        .args(&config.args)
+        .envs(env.clone());
+
+    let (mut rx, child) = sidecar_command.spawn()?;
+    let pid = child.pid();
+    let monitor_handle = app_handle.clone();
+
+
+
+
+
+    // let window = app.get_webview_window("main").unwrap();
+    // window.open_devtools();
+    // let window: tauri::Window = webview_window.as_ref().window();
+
+    tauri::async_runtime::spawn(async move {
+        let mut log_tail = VecDeque::with_capacity(CRASH_LOG_LINES);
+        // read events such as stdout
+        while let Some(event) = rx.recv().await {
+            match event {
+                CommandEvent::Stdout(line) => {
+                    remember(&mut log_tail, &line);
+                    let line_str = String::from_utf8_lossy(&line); // Convert Vec<u8> to String
+                    log::info!(target: logs::RUNTIME_TARGET, "{}", line_str.trim_end());
+                }
+                CommandEvent::Stderr(line) => {
+                    remember(&mut log_tail, &line);
+                    let line_str = String::from_utf8_lossy(&line); // Convert Vec<u8> to String
+                    log::error!(target: logs::RUNTIME_TARGET, "{}", line_str.trim_end());
+                }
+                CommandEvent::Terminated(payload) => {
+                    // let line_str = String::from_utf8_lossy(&payload); // Convert Vec<u8> to String
+                    let formatted_line = format!("'{:#?}'", payload);
+
+                    // window
+                    //     .emit("message", Some(format!("Terminated: {:#?}", payload)))
+                    //     .expect("failed to emit event");
+                    log::error!("{:?}", formatted_line); // Log the formatted line
+                    let mut crashed = false;
+                    update_health(&monitor_handle, |state| {
+                        if state.killed_pid != Some(pid) {
+                            state.set(RuntimeStatus::Crashed);
+                            crashed = true;
+                        }
+                    });
+                    if crashed {
+                        telemetry::capture_runtime_crash(
+                            payload.code,
+                            payload.signal,
+                            log_tail.drain(..).collect(),
+                        );
+                    }
+                }
+                _ => log::trace!("{:?}", event),
+            };
+        }
+    });
+
+    Ok(child)
+}
+
+/// Keeps the last [`CRASH_LOG_LINES`] lines of runtime output.
+fn remember(log_tail: &mut VecDeque<String>, line: &[u8]) {
+    if log_tail.len() == CRASH_LOG_LINES {
+        log_tail.pop_front();
+    }
+    log_tail.push_back(String::from_utf8_lossy(line).trim_end().to_string());
+}
+
+/// Whether closing every window leaves the app running in the tray, which
+/// it doesn't once the user chose to quit.
+pub fn keep_running_in_background(app_handle: &AppHandle) -> bool {
+    !EXIT_FLAG.load(std::sync::atomic::Ordering::Relaxed)
+        && settings::get_bool(app_handle, settings::KEEP_RUNNING_IN_BACKGROUND)
+}
+
 pub fn on_run_event(app_handle: &tauri::AppHandle, event: tauri::RunEvent) {
     match event {
         tauri::RunEvent::Ready { .. } => {
@@ -16,29 +382,33 @@ pub fn on_run_event(app_handle: &tauri::AppHandle, event: tauri::RunEvent) {
 
         #[cfg(target_os = "macos")]
         tauri::RunEvent::Reopen { .. } => {
-            open_main_window(app_handle).unwrap();
+            if let Err(e) = open_main_window(app_handle) {
+                log::error!("Failed to open main window: {}", e);
+            }
         }
-        tauri::RunEvent::ExitRequested {  api, .. } => {
-            log::info!("[Event] Exit requested, shutting down API...");
+        // Without a code the last window was closed, rather than the app
+        // being told to quit.
+        tauri::RunEvent::ExitRequested { code: None, api, .. }
+            if keep_running_in_background(app_handle) =>
+        {
+            log::info!("[Event] All windows closed, keeping the runtime running");
             api.prevent_exit();
+            #[cfg(target_os = "macos")]
+            {
+                dock::set_dock_visible(false);
+            }
+        }
+        tauri::RunEvent::ExitRequested { .. } => {
+            log::info!("[Event] Exit requested, shutting down API...");
             kill_sidecar_process(app_handle);
             log::info!("Exiting app");
-            app_handle.exit(0)
-
-            // if !EXIT_FLAG.load(std::sync::atomic::Ordering::Relaxed) {
-            //     api.prevent_exit();
-            //     #[cfg(target_os = "macos")]
-            //     {
-            //         dock::set_dock_visible(false);
-            //     }
-            //     for (_label, window) in app_handle.webview_windows() {
-            //         window.close().unwrap();
-            //     }
-            // }
         }
         tauri::RunEvent::Exit { } => {
             log::info!("[Event] Exit");
+            window_state::save(app_handle);
             kill_sidecar_process(app_handle);
+            // Once the runtime is gone, so it can be replaced too.
+            updater::install_pending(app_handle);
             app_handle.exit(0)
         }
         _ => {}
@@ -58,6 +428,9 @@ pub fn kill_sidecar_process(app_handle: &tauri::AppHandle) {
                 // Kill the sidecar process.
                 if let Some(handle) = app_state.sidecar_handle.take() {
                     log::info!("Killing sidecar process");
+                    if let Some(health) = app_handle.try_state::<RuntimeHealth>() {
+                        health.0.lock().unwrap().killed_pid = Some(handle.pid());
+                    }
                     if let Err(e) = handle.kill() {
                         log::error!("Failed to kill sidecar process: {:?}", e);
                     } else {
@@ -72,4 +445,49 @@ pub fn kill_sidecar_process(app_handle: &tauri::AppHandle) {
     } // MutexGuard is dropped here
 
     // The `state` variable lives until here, which is fine now
-}
\ No newline at end of file
+}
+
+/// Starts the runtime, replacing a running one, and returns its pid. A
+/// runtime that fails to start counts as crashed, so the tray offers to
+/// restart it.
+pub async fn start_sidecar_process(app_handle: &tauri::AppHandle) -> Result<u32, RuntimeError> {
+    let runtime_handle = match start_edge_runtime(app_handle.clone()).await {
+        Ok(handle) => handle,
+        Err(e) => {
+            log::error!("Failed to start the edge runtime: {}", e);
+            update_health(app_handle, |state| state.set(RuntimeStatus::Crashed));
+            return Err(e);
+        }
+    };
+    let pid = runtime_handle.pid();
+    let app_state = app_handle.state::<Mutex<AppState>>();
+    let mut app_state_guard = app_state.lock().await;
+    app_state_guard.sidecar_handle = Some(runtime_handle);
+    Ok(pid)
+}
+
+/// Runs `f` on a blocking thread with the sidecar stopped so nothing writes to
+/// the workspace underneath it, then starts the sidecar again if it was running.
+pub async fn with_sidecar_stopped<T, F>(app_handle: &tauri::AppHandle, f: F) -> tauri::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let was_running = app_handle
+        .state::<Mutex<AppState>>()
+        .lock()
+        .await
+        .sidecar_handle
+        .is_some();
+    if was_running {
+        kill_sidecar_process(app_handle);
+    }
+
+    let result = tauri::async_runtime::spawn_blocking(f).await;
+
+    if was_running {
+        // Already logged, and shown as a crash by the tray.
+        let _ = start_sidecar_process(app_handle).await;
+    }
+    result
+}
@@ -0,0 +1,620 @@
+//! User settings, declared in [`SETTINGS`] and persisted in `app_data.bin`
+//! through the store plugin.
+//!
+//! The store is loaded once at startup and shared as [`Settings`]. Values are
+//! checked against their declared type when written, and unset or invalid
+//! values read as the declared default. Every change emits
+//! [`SettingChanged`], and settings scoped to the tray show up there as check
+//! items without any code in the tray module.
+
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tauri::{AppHandle, Manager, Wry};
+use tauri_plugin_store::{Store, StoreBuilder};
+use tauri_specta::Event;
+
+use crate::cmd::CommandError;
+use crate::tray;
+
+/// File name of the store, relative to the app data directory.
+pub const STORE_FILE: &str = "app_data.bin";
+
+/// Where the schema version of the store is kept, next to the settings.
+const VERSION_KEY: &str = "$schema_version";
+
+pub const SHARE_USAGE_DATA: &str = "share_usage_data";
+pub const UPDATE_CHANNEL: &str = "update_channel";
+pub const KEEP_RUNNING_IN_BACKGROUND: &str = "keep_running_in_background";
+
+/// Every setting the app knows about. Keys are never reused for a different
+/// type, add a migration instead.
+pub const SETTINGS: &[Setting] = &[
+    Setting {
+        key: SHARE_USAGE_DATA,
+        label: "Share Usage Data",
+        description: "Send anonymous crash reports and usage statistics to help improve Craftgen.",
+        scope: SettingScope::Tray,
+        default: DefaultValue::Boolean(false),
+        options: &[],
+    },
+    Setting {
+        key: UPDATE_CHANNEL,
+        label: "Update Channel",
+        description: "Which releases to update to. Beta and nightly builds get features first but may be unstable.",
+        scope: SettingScope::Tray,
+        default: DefaultValue::String("stable"),
+        options: &[
+            SettingOption {
+                value: "stable",
+                label: "Stable",
+            },
+            SettingOption {
+                value: "beta",
+                label: "Beta",
+            },
+            SettingOption {
+                value: "nightly",
+                label: "Nightly",
+            },
+        ],
+    },
+    Setting {
+        key: KEEP_RUNNING_IN_BACKGROUND,
+        label: "Keep Running in Background",
+        description: "Keep the runtime and its agents running when all windows are closed. Quit from the tray to stop them.",
+        scope: SettingScope::Tray,
+        default: DefaultValue::Boolean(false),
+        options: &[],
+    },
+];
+
+/// Upgrades the store one schema version at a time: `MIGRATIONS[n]` runs on a
+/// store at version `n`. Stores written before the registry have no version
+/// and start at 0.
+const MIGRATIONS: &[Migration] = &[drop_invalid_values];
+
+type Migration = fn(&mut dyn Values) -> Result<(), SettingsError>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SettingsError {
+    #[error("unknown setting `{0}`")]
+    UnknownKey(String),
+    #[error("`{key}` must be a {expected}, got {value}")]
+    InvalidValue {
+        key: String,
+        expected: SettingKind,
+        value: JsonValue,
+    },
+    #[error("`{key}` must be one of {options}, got {value}")]
+    InvalidOption {
+        key: String,
+        options: String,
+        value: JsonValue,
+    },
+    #[error(transparent)]
+    Store(#[from] tauri_plugin_store::Error),
+}
+
+impl From<SettingsError> for CommandError {
+    fn from(e: SettingsError) -> Self {
+        match e {
+            SettingsError::UnknownKey(_) => CommandError::NotFound(e.to_string()),
+            SettingsError::InvalidValue { .. } | SettingsError::InvalidOption { .. } => {
+                CommandError::InvalidInput(e.to_string())
+            }
+            SettingsError::Store(_) => CommandError::Io(e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum SettingKind {
+    Boolean,
+    Integer,
+    String,
+}
+
+impl std::fmt::Display for SettingKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SettingKind::Boolean => "boolean",
+            SettingKind::Integer => "integer",
+            SettingKind::String => "string",
+        })
+    }
+}
+
+/// Where a setting is offered to the user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum SettingScope {
+    /// Only in the app's settings.
+    App,
+    /// Also in the tray menu, as a check item for booleans or a submenu for
+    /// settings with options.
+    Tray,
+}
+
+/// One of the values a setting is restricted to.
+#[derive(Debug, Clone, Copy, Serialize, specta::Type)]
+pub struct SettingOption {
+    pub value: &'static str,
+    pub label: &'static str,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum DefaultValue {
+    Boolean(bool),
+    Integer(i64),
+    String(&'static str),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Setting {
+    pub key: &'static str,
+    pub label: &'static str,
+    pub description: &'static str,
+    pub scope: SettingScope,
+    pub default: DefaultValue,
+    /// The only values a string setting accepts, any string when empty.
+    pub options: &'static [SettingOption],
+}
+
+impl Setting {
+    pub fn kind(&self) -> SettingKind {
+        match self.default {
+            DefaultValue::Boolean(_) => SettingKind::Boolean,
+            DefaultValue::Integer(_) => SettingKind::Integer,
+            DefaultValue::String(_) => SettingKind::String,
+        }
+    }
+
+    pub fn default_value(&self) -> JsonValue {
+        match self.default {
+            DefaultValue::Boolean(value) => value.into(),
+            DefaultValue::Integer(value) => value.into(),
+            DefaultValue::String(value) => value.into(),
+        }
+    }
+
+    /// Whether `value` may be written, see [`accepts`](Self::accepts).
+    fn check(&self, value: &JsonValue) -> Result<(), SettingsError> {
+        if self.accepts(value) {
+            return Ok(());
+        }
+        if value.is_string() && !self.options.is_empty() {
+            return Err(SettingsError::InvalidOption {
+                key: self.key.to_string(),
+                options: self
+                    .options
+                    .iter()
+                    .map(|option| format!("`{}`", option.value))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                value: value.clone(),
+            });
+        }
+        Err(SettingsError::InvalidValue {
+            key: self.key.to_string(),
+            expected: self.kind(),
+            value: value.clone(),
+        })
+    }
+
+    fn accepts(&self, value: &JsonValue) -> bool {
+        match self.kind() {
+            SettingKind::Boolean => value.is_boolean(),
+            SettingKind::Integer => value.is_i64(),
+            SettingKind::String => value.as_str().is_some_and(|value| {
+                self.options.is_empty() || self.options.iter().any(|o| o.value == value)
+            }),
+        }
+    }
+}
+
+pub fn setting(key: &str) -> Result<&'static Setting, SettingsError> {
+    SETTINGS
+        .iter()
+        .find(|setting| setting.key == key)
+        .ok_or_else(|| SettingsError::UnknownKey(key.to_string()))
+}
+
+/// A setting together with its current value, as listed to the frontend.
+#[derive(Debug, Clone, Serialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingEntry {
+    pub key: String,
+    pub label: String,
+    pub description: String,
+    pub scope: SettingScope,
+    pub kind: SettingKind,
+    pub default: JsonValue,
+    pub value: JsonValue,
+    pub options: Vec<SettingOption>,
+}
+
+/// Emitted after a setting was written, from the app or the tray.
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type, tauri_specta::Event)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingChanged {
+    pub key: String,
+    pub value: JsonValue,
+}
+
+/// What reading and migrating settings needs from the store, so that it can
+/// be tested on a plain map.
+trait Values {
+    fn get(&self, key: &str) -> Option<&JsonValue>;
+    fn insert(&mut self, key: String, value: JsonValue) -> Result<(), SettingsError>;
+    fn delete(&mut self, key: &str) -> Result<(), SettingsError>;
+}
+
+impl Values for Store<Wry> {
+    fn get(&self, key: &str) -> Option<&JsonValue> {
+        Store::get(self, key)
+    }
+
+    fn insert(&mut self, key: String, value: JsonValue) -> Result<(), SettingsError> {
+        Ok(Store::insert(self, key, value)?)
+    }
+
+    fn delete(&mut self, key: &str) -> Result<(), SettingsError> {
+        Store::delete(self, key)?;
+        Ok(())
+    }
+}
+
+/// The settings store, opened once at startup and shared by every window.
+pub struct Settings(Mutex<Store<Wry>>);
+
+impl Settings {
+    pub fn load(app: &AppHandle) -> Result<Self, SettingsError> {
+        Ok(Self(Mutex::new(open(app)?)))
+    }
+
+    /// The stored value, or the default when it is unset or of the wrong type.
+    pub fn get(&self, key: &str) -> Result<JsonValue, SettingsError> {
+        let setting = setting(key)?;
+        let store = self.0.lock().unwrap();
+        Ok(value(&*store, setting))
+    }
+
+    pub fn set(&self, key: &str, value: JsonValue) -> Result<(), SettingsError> {
+        setting(key)?.check(&value)?;
+        let mut store = self.0.lock().unwrap();
+        store.insert(key.to_string(), value)?;
+        store.save()?;
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<SettingEntry> {
+        let store = self.0.lock().unwrap();
+        SETTINGS
+            .iter()
+            .map(|setting| SettingEntry {
+                key: setting.key.to_string(),
+                label: setting.label.to_string(),
+                description: setting.description.to_string(),
+                scope: setting.scope,
+                kind: setting.kind(),
+                default: setting.default_value(),
+                value: value(&*store, setting),
+                options: setting.options.to_vec(),
+            })
+            .collect()
+    }
+
+    /// App state kept next to the settings that isn't a setting itself, like
+    /// skipped updates. It isn't listed, validated or announced.
+    pub fn get_state(&self, key: &str) -> Option<JsonValue> {
+        self.0.lock().unwrap().get(key).cloned()
+    }
+
+    pub fn set_state(&self, key: &str, value: JsonValue) -> Result<(), SettingsError> {
+        let mut store = self.0.lock().unwrap();
+        store.insert(key.to_string(), value)?;
+        store.save()?;
+        Ok(())
+    }
+
+    /// Rereads the store from disk, e.g. after a workspace import replaced it.
+    pub fn reload(&self, app: &AppHandle) -> Result<(), SettingsError> {
+        let store = open(app)?;
+        *self.0.lock().unwrap() = store;
+        if let Err(e) = tray::refresh(app) {
+            log::error!("Failed to rebuild tray menu: {}", e);
+        }
+        Ok(())
+    }
+}
+
+fn value(store: &dyn Values, setting: &Setting) -> JsonValue {
+    match store.get(setting.key) {
+        Some(value) if setting.accepts(value) => value.clone(),
+        Some(value) => {
+            log::warn!(
+                "ignoring {} for `{}`, expected a {}",
+                value,
+                setting.key,
+                setting.kind()
+            );
+            setting.default_value()
+        }
+        None => setting.default_value(),
+    }
+}
+
+/// Loads the store and brings it up to the current schema version.
+fn open(app: &AppHandle) -> Result<Store<Wry>, SettingsError> {
+    let mut store = StoreBuilder::new(STORE_FILE).build(app.clone());
+    if let Err(e) = store.load() {
+        // A missing file is a fresh install. Anything else is logged and the
+        // defaults are used rather than refusing to start.
+        if !matches!(&e, tauri_plugin_store::Error::Io(io) if io.kind() == std::io::ErrorKind::NotFound)
+        {
+            log::error!("Failed to load settings, using defaults: {}", e);
+        }
+    }
+
+    let version = migrate(&mut store)?;
+    if version < MIGRATIONS.len() {
+        store.save()?;
+        log::info!(
+            "migrated settings from schema {} to {}",
+            version,
+            MIGRATIONS.len()
+        );
+    }
+    Ok(store)
+}
+
+/// Runs the migrations the store is missing, returning the schema version it
+/// was at. A store of a newer version is left alone.
+fn migrate(store: &mut dyn Values) -> Result<usize, SettingsError> {
+    let version = store
+        .get(VERSION_KEY)
+        .and_then(JsonValue::as_u64)
+        .unwrap_or(0) as usize;
+    if version > MIGRATIONS.len() {
+        log::warn!(
+            "settings were written by a newer version (schema {}, this build knows {})",
+            version,
+            MIGRATIONS.len()
+        );
+        return Ok(version);
+    }
+    if version < MIGRATIONS.len() {
+        for migration in &MIGRATIONS[version..] {
+            migration(store)?;
+        }
+        store.insert(VERSION_KEY.to_string(), MIGRATIONS.len().into())?;
+    }
+    Ok(version)
+}
+
+/// 0 -> 1: values were written without a schema, so drop any of the wrong
+/// type and let them fall back to their default.
+fn drop_invalid_values(store: &mut dyn Values) -> Result<(), SettingsError> {
+    for setting in SETTINGS {
+        if store
+            .get(setting.key)
+            .is_some_and(|value| !setting.accepts(value))
+        {
+            store.delete(setting.key)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads a boolean setting, falling back to its default on any error.
+pub fn get_bool(app: &AppHandle, key: &str) -> bool {
+    let settings = app.state::<Settings>();
+    let store = settings.0.lock().unwrap();
+    bool_value(&*store, key)
+}
+
+fn bool_value(store: &dyn Values, key: &str) -> bool {
+    match setting(key).map(|setting| value(store, setting)) {
+        Ok(JsonValue::Bool(value)) => value,
+        Ok(_) | Err(_) => {
+            log::error!("`{}` is not a boolean setting", key);
+            default_value(key).as_bool().unwrap_or_default()
+        }
+    }
+}
+
+/// The registered default of `key`, null for an unknown key.
+fn default_value(key: &str) -> JsonValue {
+    setting(key).map(Setting::default_value).unwrap_or_default()
+}
+
+/// Reads a string setting, falling back to its default on any error.
+pub fn get_string(app: &AppHandle, key: &str) -> String {
+    let settings = app.state::<Settings>();
+    let store = settings.0.lock().unwrap();
+    string_value(&*store, key)
+}
+
+fn string_value(store: &dyn Values, key: &str) -> String {
+    match setting(key).map(|setting| value(store, setting)) {
+        Ok(JsonValue::String(value)) => value,
+        Ok(_) | Err(_) => {
+            log::error!("`{}` is not a string setting", key);
+            default_value(key)
+                .as_str()
+                .map(str::to_string)
+                .unwrap_or_default()
+        }
+    }
+}
+
+/// Writes a setting and tells the frontend and the tray about it.
+pub fn set(app: &AppHandle, key: &str, value: JsonValue) -> Result<(), SettingsError> {
+    app.state::<Settings>().set(key, value.clone())?;
+    log::info!("{}: {}", key, value);
+
+    if let Err(e) = (SettingChanged {
+        key: key.to_string(),
+        value,
+    })
+    .emit(app)
+    {
+        log::error!("Failed to emit setting change: {}", e);
+    }
+    if setting(key)?.scope == SettingScope::Tray {
+        if let Err(e) = tray::refresh(app) {
+            log::error!("Failed to rebuild tray menu: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Flips a boolean setting.
+pub fn toggle(app: &AppHandle, key: &str) -> Result<(), SettingsError> {
+    set(app, key, (!get_bool(app, key)).into())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn list_settings(settings: tauri::State<'_, Settings>) -> Vec<SettingEntry> {
+    settings.list()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_setting(
+    settings: tauri::State<'_, Settings>,
+    key: String,
+) -> Result<JsonValue, CommandError> {
+    Ok(settings.get(&key)?)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn set_setting(
+    app_handle: AppHandle,
+    key: String,
+    value: JsonValue,
+) -> Result<(), CommandError> {
+    Ok(set(&app_handle, &key, value)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use super::*;
+
+    impl Values for HashMap<String, JsonValue> {
+        fn get(&self, key: &str) -> Option<&JsonValue> {
+            HashMap::get(self, key)
+        }
+
+        fn insert(&mut self, key: String, value: JsonValue) -> Result<(), SettingsError> {
+            HashMap::insert(self, key, value);
+            Ok(())
+        }
+
+        fn delete(&mut self, key: &str) -> Result<(), SettingsError> {
+            HashMap::remove(self, key);
+            Ok(())
+        }
+    }
+
+    fn store(values: JsonValue) -> HashMap<String, JsonValue> {
+        serde_json::from_value(values).unwrap()
+    }
+
+    #[test]
+    fn migrates_a_store_without_a_schema_version() {
+        let mut values = store(json!({
+            SHARE_USAGE_DATA: "yes",
+            UPDATE_CHANNEL: "beta",
+            KEEP_RUNNING_IN_BACKGROUND: true,
+            "skipped_update": "1.2.3",
+        }));
+
+        assert_eq!(migrate(&mut values).unwrap(), 0);
+        assert_eq!(
+            values,
+            store(json!({
+                VERSION_KEY: MIGRATIONS.len(),
+                UPDATE_CHANNEL: "beta",
+                KEEP_RUNNING_IN_BACKGROUND: true,
+                "skipped_update": "1.2.3",
+            }))
+        );
+
+        let migrated = values.clone();
+        assert_eq!(migrate(&mut values).unwrap(), MIGRATIONS.len());
+        assert_eq!(values, migrated);
+    }
+
+    #[test]
+    fn leaves_a_store_of_a_newer_version_alone() {
+        let newer = MIGRATIONS.len() + 1;
+        let mut values = store(json!({ VERSION_KEY: newer, SHARE_USAGE_DATA: "yes" }));
+        let before = values.clone();
+
+        assert_eq!(migrate(&mut values).unwrap(), newer);
+        assert_eq!(values, before);
+    }
+
+    #[test]
+    fn reads_the_default_for_missing_or_mistyped_values() {
+        let empty = store(json!({}));
+        assert!(!bool_value(&empty, SHARE_USAGE_DATA));
+        assert_eq!(string_value(&empty, UPDATE_CHANNEL), "stable");
+
+        let mistyped = store(json!({
+            SHARE_USAGE_DATA: "yes",
+            UPDATE_CHANNEL: 2,
+            KEEP_RUNNING_IN_BACKGROUND: null,
+        }));
+        assert!(!bool_value(&mistyped, SHARE_USAGE_DATA));
+        assert!(!bool_value(&mistyped, KEEP_RUNNING_IN_BACKGROUND));
+        assert_eq!(string_value(&mistyped, UPDATE_CHANNEL), "stable");
+        assert_eq!(
+            string_value(&store(json!({ UPDATE_CHANNEL: "canary" })), UPDATE_CHANNEL),
+            "stable"
+        );
+
+        let set = store(json!({ SHARE_USAGE_DATA: true, UPDATE_CHANNEL: "beta" }));
+        assert!(bool_value(&set, SHARE_USAGE_DATA));
+        assert_eq!(string_value(&set, UPDATE_CHANNEL), "beta");
+
+        // Asking for the wrong type or an unknown key doesn't panic either.
+        assert!(!bool_value(&set, UPDATE_CHANNEL));
+        assert_eq!(string_value(&set, "unknown"), "");
+    }
+
+    #[test]
+    fn rejects_values_outside_the_options() {
+        let channel = setting(UPDATE_CHANNEL).unwrap();
+        channel.check(&json!("nightly")).unwrap();
+        assert!(matches!(
+            channel.check(&json!("canary")),
+            Err(SettingsError::InvalidOption { .. })
+        ));
+        assert!(matches!(
+            channel.check(&json!(true)),
+            Err(SettingsError::InvalidValue { .. })
+        ));
+
+        let share = setting(SHARE_USAGE_DATA).unwrap();
+        share.check(&json!(false)).unwrap();
+        assert!(matches!(
+            share.check(&json!("true")),
+            Err(SettingsError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            setting("unknown"),
+            Err(SettingsError::UnknownKey(_))
+        ));
+    }
+}
@@ -1,30 +1,94 @@
 use std::error::Error;
 
-use clap::Parser;
 use tauri::{ App,  Manager};
-use tokio::sync::Mutex;
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
 
-use crate::{cmd, tray, AppState, Args};
+use crate::config::LoadedConfig;
+use crate::{
+    cli, cmd, functions, instance, modules, palette, queue, runtime, settings, telemetry, tray, updater, window_state, Args,
+};
 
 pub  fn setup(app: &mut App) -> Result<(), Box<dyn Error>> {
-    let args = Args::parse();
+    let args = app.state::<Args>().inner().clone();
     log::debug!("args: {:?}", args);
+
+    let config = app.state::<LoadedConfig>();
+    log::info!(
+        "config: {} ({})",
+        config.path.display(),
+        if config.file_found { "loaded" } else { "not found, using defaults" }
+    );
+    let problems = config.problems();
+    for problem in &problems {
+        log::error!("invalid configuration: {}", problem);
+    }
+    if !problems.is_empty() {
+        app.dialog()
+            .message(format!(
+                "{}\n\nCraftgen started without these values, using the defaults or \
+                 other sources for them.",
+                problems.join("\n")
+            ))
+            .title("Invalid configuration")
+            .kind(MessageDialogKind::Error)
+            .show(|_| {});
+    }
+
+    // Before anything reads a setting, the tray menu included.
+    app.manage(settings::Settings::load(app.app_handle())?);
+    telemetry::start(app.app_handle());
+
+    if let Some(command) = args.command {
+        cli::run(app.app_handle().clone(), command);
+        return Ok(());
+    }
+
+    // Only tells CLI commands that the workspace is in use.
+    match instance::InstanceLock::try_acquire(&app.path().app_data_dir()?) {
+        Ok(Some(lock)) => {
+            app.manage(lock);
+        }
+        Ok(None) => log::warn!("another Craftgen process is using the workspace"),
+        Err(e) => log::warn!("Failed to lock the workspace: {}", e),
+    }
+    app.manage(window_state::WindowStates::load(app.app_handle())?);
+    // Created from tauri.conf.json, later main windows are tracked when
+    // they are built.
+    if let Some(window) = app.get_webview_window(cmd::MAIN_WINDOW) {
+        cmd::track_main_window(&window);
+    }
+
     if args.minimized {
         #[cfg(target_os = "macos")]
         {
             crate::dock::set_dock_visible(false);
         }
-    } else {
-        cmd::open_main_window(app.app_handle()).unwrap();
+    } else if !args.palette {
+        cmd::open_main_window(app.app_handle())?;
+    }
+    if args.palette {
+        palette::open_palette(app.app_handle())?;
     }
 
     tray::build(app.app_handle());
+    updater::start(app.app_handle());
+    // Before the runtime, which gets the queue's URL when it starts.
+    queue::start(app.app_handle());
+    // Before the runtime looks anything up in the modules directory.
+    if let Err(e) = modules::ModuleStore::open(app.app_handle())?.migrate() {
+        log::error!("Failed to migrate installed modules: {}", e);
+    }
     let app_handle = app.app_handle().clone();
     tauri::async_runtime::spawn(async move {
-        let runtime_handle = cmd::start_edge_runtime(app_handle.clone()).await;
-        let app_state = app_handle.state::<Mutex<AppState>>();
-        let mut app_state_guard = app_state.lock().await;
-        app_state_guard.sidecar_handle = Some(runtime_handle.expect("craftgen runtime not started"));
+        // A failed start is already logged, and shown as a crash by the tray.
+        let pid = runtime::start_sidecar_process(&app_handle).await.ok();
+        // Checks a bundle installed while the app wasn't running, e.g. from
+        // the CLI.
+        if let Err(e) = functions::check_health(&app_handle, pid).await {
+            functions::notify_failure(&app_handle, &e);
+        }
     });
+    runtime::spawn_health_monitor(app.app_handle());
+    updater::spawn_health_check(app.app_handle());
     Ok(())
 }
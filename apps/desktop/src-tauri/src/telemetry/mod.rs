@@ -0,0 +1,468 @@
+//! Crash reporting through Sentry.
+//!
+//! The client is created in `main` so panics on every thread are captured.
+//! Captured events are scrubbed of the home directory and other users'
+//! directories and queued on disk by [`reports`] instead of being sent. The queue is drained
+//! in the background only while "Share Usage Data" is on, otherwise reports
+//! wait for the user to review them.
+
+mod reports;
+
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use sentry::types::Dsn;
+use serde_json::Value as JsonValue;
+use tauri::{AppHandle, Manager};
+use tauri_specta::Event;
+use tokio::sync::Notify;
+
+pub use reports::{CrashReport, ReportError};
+
+use crate::cmd::CommandError;
+use crate::config::TelemetryConfig;
+use crate::settings::{self, SettingChanged, SHARE_USAGE_DATA};
+use reports::{ReportQueue, REPORTS_DIR};
+
+/// How often queued reports are retried while sending fails.
+const RETRY_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+pub struct Telemetry {
+    consent: AtomicBool,
+    dsn: Option<Dsn>,
+    reports: Arc<ReportQueue>,
+    /// Wakes the sender when a report is queued or consent is given.
+    wake: Arc<Notify>,
+    _guard: Option<sentry::ClientInitGuard>,
+}
+
+impl Telemetry {
+    /// Whether queued reports are sent without asking.
+    pub fn is_enabled(&self) -> bool {
+        self.dsn.is_some() && self.consent.load(Ordering::Relaxed)
+    }
+
+    pub fn set_consent(&self, granted: bool) {
+        if self.consent.swap(granted, Ordering::Relaxed) == granted || self.dsn.is_none() {
+            return;
+        }
+        if granted {
+            log::info!("crash reporting enabled");
+            self.wake.notify_one();
+        } else {
+            log::info!("crash reporting disabled, reports are kept for review");
+        }
+    }
+
+    pub fn pending_reports(&self) -> usize {
+        self.reports.pending().map_or(0, |pending| pending.len())
+    }
+
+    /// Sends a queued report regardless of consent, the user asked for it.
+    pub async fn send(&self, id: &str) -> Result<(), ReportError> {
+        let dsn = self.dsn.as_ref().ok_or(ReportError::NoDsn)?;
+        self.reports.send(dsn, id).await
+    }
+
+    /// Captures a message event and sends it right away, to check the DSN end
+    /// to end, e.g. against a local mock endpoint.
+    pub async fn send_test_event(&self) -> Result<sentry::types::Uuid, ReportError> {
+        if self.dsn.is_none() {
+            return Err(ReportError::NoDsn);
+        }
+        let id = sentry::capture_message("Craftgen test event", sentry::Level::Info);
+        self.send(&id.simple().to_string()).await?;
+        Ok(id)
+    }
+
+    /// Sends queued reports oldest first, until one fails to send.
+    async fn drain(&self) {
+        let pending = match self.reports.pending() {
+            Ok(pending) => pending,
+            Err(e) => {
+                log::error!("Failed to read crash reports: {}", e);
+                return;
+            }
+        };
+        for id in pending {
+            if !self.is_enabled() {
+                return;
+            }
+            match self.send(&id).await {
+                Ok(()) => log::info!("sent crash report {}", id),
+                // Most likely offline, the rest would fail the same way.
+                Err(ReportError::Send(e)) => {
+                    log::warn!("Failed to send crash report {}, will retry: {}", id, e);
+                    return;
+                }
+                Err(ReportError::Rejected(e)) => {
+                    log::error!("Dropped crash report {}, it can't be sent: {}", id, e)
+                }
+                Err(e) => log::error!("Skipping crash report {}: {}", id, e),
+            }
+        }
+    }
+}
+
+/// Creates the client before any setting can be read. A missing DSN disables
+/// crash reporting for the whole run.
+pub fn init(config: &TelemetryConfig, data_dir: &Path) -> Telemetry {
+    let reports = Arc::new(ReportQueue::new(data_dir.join(REPORTS_DIR)));
+    let wake = Arc::new(Notify::new());
+    let guard = config.sentry_dsn.clone().map(|dsn| {
+        let scrubber = Scrubber::from_env();
+        let (reports, wake) = (reports.clone(), wake.clone());
+        sentry::init(sentry::ClientOptions {
+            dsn: Some(dsn),
+            release: sentry::release_name!(),
+            send_default_pii: false,
+            before_send: Some(Arc::new(move |event| {
+                // Runs on the crashing thread, possibly while panicking, so
+                // this only writes the file and leaves sending to `start`.
+                if let Some(event) = scrubber.scrub(event) {
+                    match reports.push(&event) {
+                        Ok(_) => wake.notify_one(),
+                        Err(e) => log::error!("Failed to queue crash report: {}", e),
+                    }
+                }
+                None
+            })),
+            ..Default::default()
+        })
+    });
+    Telemetry {
+        consent: AtomicBool::new(false),
+        dsn: config.sentry_dsn.clone(),
+        reports,
+        wake,
+        _guard: guard,
+    }
+}
+
+/// Applies the stored consent, follows later changes to it and starts
+/// sending queued reports. Runs once the settings are loaded.
+pub fn start(app: &AppHandle) {
+    app.state::<Telemetry>()
+        .set_consent(settings::get_bool(app, SHARE_USAGE_DATA));
+
+    let app_handle = app.clone();
+    SettingChanged::listen(app, move |event| {
+        if event.payload.key == SHARE_USAGE_DATA {
+            let granted = event.payload.value.as_bool().unwrap_or(false);
+            app_handle.state::<Telemetry>().set_consent(granted);
+        }
+    });
+
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let telemetry = app_handle.state::<Telemetry>();
+        loop {
+            if telemetry.is_enabled() {
+                telemetry.drain().await;
+            }
+            let _ = tokio::time::timeout(RETRY_INTERVAL, telemetry.wake.notified()).await;
+        }
+    });
+}
+
+/// Queues a report for an edge runtime exit nobody asked for, with the last
+/// lines it logged.
+pub fn capture_runtime_crash(code: Option<i32>, signal: Option<i32>, log_tail: Vec<String>) {
+    let reason = match (code, signal) {
+        (_, Some(signal)) => format!("signal {}", signal),
+        (Some(code), None) => format!("exit code {}", code),
+        (None, None) => "no exit code".to_string(),
+    };
+    let mut event = sentry::protocol::Event {
+        message: Some(format!("Edge runtime exited unexpectedly ({})", reason)),
+        level: sentry::Level::Fatal,
+        ..Default::default()
+    };
+    event
+        .tags
+        .insert("component".to_string(), "edge-runtime".to_string());
+    event
+        .extra
+        .insert("runtime_log".to_string(), log_tail.into());
+    sentry::capture_event(event);
+}
+
+/// Calls `replace` on every string in `value`, keeping the original where it
+/// returns `None`.
+fn replace_strings(value: &mut JsonValue, replace: &impl Fn(&str) -> Option<String>) {
+    match value {
+        JsonValue::String(s) => {
+            if let Some(replaced) = replace(s) {
+                *s = replaced;
+            }
+        }
+        JsonValue::Array(values) => values.iter_mut().for_each(|v| replace_strings(v, replace)),
+        JsonValue::Object(map) => map.values_mut().for_each(|v| replace_strings(v, replace)),
+        _ => {}
+    }
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn list_crash_reports(
+    telemetry: tauri::State<'_, Telemetry>,
+) -> Result<Vec<CrashReport>, CommandError> {
+    Ok(telemetry.reports.list()?)
+}
+
+/// Replaces every occurrence of `text` in a pending report with `[redacted]`.
+#[tauri::command]
+#[specta::specta]
+pub fn redact_crash_report(
+    telemetry: tauri::State<'_, Telemetry>,
+    id: String,
+    text: String,
+) -> Result<CrashReport, CommandError> {
+    Ok(telemetry.reports.redact(&id, &text)?)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn send_crash_report(
+    telemetry: tauri::State<'_, Telemetry>,
+    id: String,
+) -> Result<(), CommandError> {
+    Ok(telemetry.send(&id).await?)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn delete_crash_report(
+    telemetry: tauri::State<'_, Telemetry>,
+    id: String,
+) -> Result<(), CommandError> {
+    Ok(telemetry.reports.delete(&id)?)
+}
+
+/// Replaces the home directory, and the user name where it names a user's
+/// directory, in every string of an event.
+struct Scrubber {
+    home: Option<String>,
+    /// Paths of user directories, e.g. `/Users/jane`, with their scrubbed
+    /// form. The name alone isn't replaced, it may well be an ordinary word.
+    user_dirs: Vec<(String, String)>,
+}
+
+impl Scrubber {
+    fn from_env() -> Self {
+        let home = dirs::home_dir().map(|home| home.to_string_lossy().into_owned());
+        let user = std::env::var("USER")
+            .or_else(|_| std::env::var("USERNAME"))
+            .ok();
+        Self::new(home, user)
+    }
+
+    fn new(home: Option<String>, user: Option<String>) -> Self {
+        let home = home.filter(|home| home.len() > 1);
+        // `home` only covers this system's layout, these catch the user's
+        // directory as spelled elsewhere, e.g. in a log from WSL.
+        let user_dirs = user
+            .filter(|user| !user.is_empty())
+            .map(|user| {
+                ["/Users/", "\\Users\\", "/home/"]
+                    .into_iter()
+                    .map(|parent| (format!("{}{}", parent, user), format!("{}<user>", parent)))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self { home, user_dirs }
+    }
+
+    fn scrub(
+        &self,
+        mut event: sentry::protocol::Event<'static>,
+    ) -> Option<sentry::protocol::Event<'static>> {
+        event.server_name = None;
+        event.user = None;
+        // Going through JSON reaches every string, including ones in
+        // breadcrumbs, contexts and stack frames added by future integrations.
+        // An event that doesn't survive the round trip is dropped rather than
+        // sent unscrubbed.
+        let mut value = serde_json::to_value(&event).ok()?;
+        replace_strings(&mut value, &|s| match self.scrub_str(s) {
+            Cow::Owned(scrubbed) => Some(scrubbed),
+            Cow::Borrowed(_) => None,
+        });
+        serde_json::from_value(value).ok()
+    }
+
+    fn scrub_str<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        let mut s = Cow::Borrowed(s);
+        if let Some(home) = &self.home {
+            if let Some(scrubbed) = replace_path(&s, home, "~") {
+                s = Cow::Owned(scrubbed);
+            }
+        }
+        for (dir, scrubbed_dir) in &self.user_dirs {
+            if let Some(scrubbed) = replace_path(&s, dir, scrubbed_dir) {
+                s = Cow::Owned(scrubbed);
+            }
+        }
+        s
+    }
+}
+
+/// Replaces `path` in `s` where it is a whole path or the start of one, so
+/// `/home/jane` is left alone in `/home/janet`.
+fn replace_path(s: &str, path: &str, replacement: &str) -> Option<String> {
+    let mut scrubbed = String::new();
+    let mut rest = s;
+    let mut replaced = false;
+    while let Some(at) = rest.find(path) {
+        let end = at + path.len();
+        let whole = rest[end..].chars().next().map_or(true, |c| {
+            !(c.is_alphanumeric() || matches!(c, '.' | '-' | '_'))
+        });
+        scrubbed.push_str(&rest[..at]);
+        scrubbed.push_str(if whole { replacement } else { path });
+        replaced |= whole;
+        rest = &rest[end..];
+    }
+    scrubbed.push_str(rest);
+    replaced.then_some(scrubbed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    use super::*;
+
+    fn scrubber() -> Scrubber {
+        Scrubber::new(Some("/home/jane".into()), Some("jane".into()))
+    }
+
+    #[test]
+    fn scrubs_home_and_user_directories() {
+        let scrubber = scrubber();
+        assert_eq!(
+            scrubber.scrub_str("/home/jane/src/main.rs"),
+            "~/src/main.rs"
+        );
+        assert_eq!(scrubber.scrub_str("at /home/jane"), "at ~");
+        assert_eq!(
+            scrubber.scrub_str("/Users/jane/Library and C:\\Users\\jane\\AppData"),
+            "/Users/<user>/Library and C:\\Users\\<user>\\AppData"
+        );
+    }
+
+    #[test]
+    fn keeps_the_user_name_outside_paths() {
+        let scrubber = scrubber();
+        for s in ["jane", "Janet's jane-doc", "/home/janet/x", "/srv/jane/x"] {
+            assert!(matches!(scrubber.scrub_str(s), Cow::Borrowed(_)), "{}", s);
+        }
+    }
+
+    /// Answers envelopes like Sentry's ingest endpoint with `status` and
+    /// passes on their bodies.
+    fn mock_ingest(status: &'static str) -> (Dsn, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                // Before answering, so the body is there once the send returns.
+                let _ = tx.send(String::from_utf8_lossy(&body).into_owned());
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        let dsn = format!("http://public@127.0.0.1:{}/1", port)
+            .parse()
+            .unwrap();
+        (dsn, rx)
+    }
+
+    /// Keeps its reports in the returned directory.
+    fn telemetry(dsn: Dsn) -> (Telemetry, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let telemetry = Telemetry {
+            consent: AtomicBool::new(false),
+            dsn: Some(dsn),
+            reports: Arc::new(ReportQueue::new(dir.path().to_path_buf())),
+            wake: Arc::new(Notify::new()),
+            _guard: None,
+        };
+        (telemetry, dir)
+    }
+
+    #[tokio::test]
+    async fn sends_scrubbed_reports_only_with_consent() {
+        let (dsn, ingest) = mock_ingest("200 OK");
+        let (telemetry, _dir) = telemetry(dsn);
+        let event = sentry::protocol::Event {
+            message: Some("jane failed to open /home/jane/.craftgen and /Users/jane".into()),
+            ..Default::default()
+        };
+        telemetry
+            .reports
+            .push(&scrubber().scrub(event).unwrap())
+            .unwrap();
+
+        telemetry.drain().await;
+        assert_eq!(telemetry.pending_reports(), 1);
+        assert!(ingest.try_recv().is_err());
+
+        telemetry.set_consent(true);
+        telemetry.drain().await;
+        assert_eq!(telemetry.pending_reports(), 0);
+        let body = ingest.try_recv().unwrap();
+        assert!(body.contains("jane failed to open ~/.craftgen and /Users/<user>"));
+        assert!(!body.contains("/home/jane") && !body.contains("/Users/jane"));
+    }
+
+    #[tokio::test]
+    async fn drops_rejected_reports_and_keeps_the_rest_for_later() {
+        for (status, kept) in [
+            ("400 Bad Request", 0),
+            ("429 Too Many Requests", 1),
+            ("503 Service Unavailable", 1),
+        ] {
+            let (dsn, ingest) = mock_ingest(status);
+            let (telemetry, _dir) = telemetry(dsn);
+            telemetry.set_consent(true);
+            for _ in 0..2 {
+                telemetry
+                    .reports
+                    .push(&sentry::protocol::Event::default())
+                    .unwrap();
+            }
+
+            telemetry.drain().await;
+            // Rejections don't stop the drain, other failures do.
+            assert_eq!(ingest.try_iter().count(), 2 - kept, "{}", status);
+            assert_eq!(telemetry.pending_reports(), 2 * kept, "{}", status);
+        }
+    }
+}
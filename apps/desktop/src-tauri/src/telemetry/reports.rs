@@ -0,0 +1,305 @@
+//! Crash reports waiting to be sent, one JSON file each in `crash-reports/`
+//! in the app data directory.
+//!
+//! Sentry's `before_send` writes every event here instead of sending it, so
+//! nothing is lost while offline or before the user has decided about
+//! consent. While "Share Usage Data" is on the queue is drained in the
+//! background, otherwise reports wait in "Pending reports" until the user
+//! sends or deletes them. Reports Sentry rejects are deleted, they would be
+//! rejected on every retry.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sentry::protocol::{Envelope, Event};
+use sentry::types::Dsn;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tauri_plugin_http::reqwest;
+
+use crate::cmd::CommandError;
+
+pub const REPORTS_DIR: &str = "crash-reports";
+
+/// Oldest reports are dropped beyond this, a crash loop shouldn't fill the
+/// disk.
+const MAX_REPORTS: usize = 50;
+const REDACTED: &str = "[redacted]";
+const USER_AGENT: &str = concat!("craftgen/", env!("CARGO_PKG_VERSION"));
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReportError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("invalid crash report: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("no crash report {0}")]
+    NotFound(String),
+    #[error("nothing to redact")]
+    EmptyRedaction,
+    #[error("crash reporting is disabled, no Sentry DSN is configured")]
+    NoDsn,
+    #[error("failed to send crash report: {0}")]
+    Send(String),
+    #[error("crash report was rejected and deleted: {0}")]
+    Rejected(String),
+}
+
+impl From<ReportError> for CommandError {
+    fn from(e: ReportError) -> Self {
+        match e {
+            ReportError::Io(e) => e.into(),
+            ReportError::NotFound(_) => CommandError::NotFound(e.to_string()),
+            ReportError::Json(_) | ReportError::EmptyRedaction | ReportError::NoDsn => {
+                CommandError::InvalidInput(e.to_string())
+            }
+            ReportError::Send(_) | ReportError::Rejected(_) => CommandError::Runtime(e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub id: String,
+    /// Seconds since the unix epoch.
+    pub created_at: u64,
+    /// The event's message or exception, for listing.
+    pub summary: String,
+    /// Failed attempts to send it so far.
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    /// The Sentry event exactly as it will be sent.
+    pub event: JsonValue,
+}
+
+impl CrashReport {
+    fn new(event: &Event<'static>) -> Result<Self, ReportError> {
+        let summary = event
+            .exception
+            .values
+            .last()
+            .map(|exception| match &exception.value {
+                Some(value) => format!("{}: {}", exception.ty, value),
+                None => exception.ty.clone(),
+            })
+            .or_else(|| event.message.clone())
+            .unwrap_or_else(|| "Unknown error".to_string());
+        Ok(Self {
+            id: event.event_id.simple().to_string(),
+            created_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            summary,
+            attempts: 0,
+            last_error: None,
+            event: serde_json::to_value(event)?,
+        })
+    }
+}
+
+pub struct ReportQueue {
+    dir: PathBuf,
+    /// Serializes read-modify-write of report files between the sender,
+    /// commands and `before_send`.
+    lock: Mutex<()>,
+}
+
+impl ReportQueue {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn push(&self, event: &Event<'static>) -> Result<CrashReport, ReportError> {
+        let report = CrashReport::new(event)?;
+        let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        fs::create_dir_all(&self.dir)?;
+        self.write(&report)?;
+        self.prune()?;
+        Ok(report)
+    }
+
+    /// Every pending report, newest first. Unreadable files are skipped.
+    pub fn list(&self) -> Result<Vec<CrashReport>, ReportError> {
+        let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut reports = Vec::new();
+        for path in self.files()? {
+            match read(&path) {
+                Ok(report) => reports.push(report),
+                Err(e) => log::warn!("Skipping crash report {}: {}", path.display(), e),
+            }
+        }
+        reports.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        Ok(reports)
+    }
+
+    /// Replaces every occurrence of `text` in the report with `[redacted]`.
+    pub fn redact(&self, id: &str, text: &str) -> Result<CrashReport, ReportError> {
+        if text.trim().is_empty() {
+            return Err(ReportError::EmptyRedaction);
+        }
+        let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut report = read(&self.existing(id)?)?;
+        super::replace_strings(&mut report.event, &|s| {
+            s.contains(text).then(|| s.replace(text, REDACTED))
+        });
+        report.summary = report.summary.replace(text, REDACTED);
+        self.write(&report)?;
+        Ok(report)
+    }
+
+    pub fn delete(&self, id: &str) -> Result<(), ReportError> {
+        let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        fs::remove_file(self.existing(id)?)?;
+        Ok(())
+    }
+
+    /// Sends one report and removes it, or records the failed attempt. A
+    /// report that can never be sent is removed as well.
+    pub async fn send(&self, dsn: &Dsn, id: &str) -> Result<(), ReportError> {
+        let report = {
+            let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+            read(&self.existing(id)?)?
+        };
+        let result = deliver(dsn, &report).await;
+
+        let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let path = self.path(id)?;
+        let remove = |path| match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            // Already gone if the user deleted it while it was being sent.
+            _ => Ok(()),
+        };
+        match result {
+            Ok(()) => Ok(remove(path)?),
+            Err(Failure::Permanent(e)) => {
+                remove(path)?;
+                Err(ReportError::Rejected(e))
+            }
+            Err(Failure::Transient(e)) => {
+                // Redactions made meanwhile are kept.
+                if let Ok(mut report) = read(&path) {
+                    report.attempts += 1;
+                    report.last_error = Some(e.clone());
+                    self.write(&report)?;
+                }
+                Err(ReportError::Send(e))
+            }
+        }
+    }
+
+    /// Ids of the pending reports, oldest first.
+    pub fn pending(&self) -> Result<Vec<String>, ReportError> {
+        let mut reports = self.list()?;
+        reports.reverse();
+        Ok(reports.into_iter().map(|report| report.id).collect())
+    }
+
+    fn files(&self) -> io::Result<Vec<PathBuf>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut files = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                files.push(path);
+            }
+        }
+        Ok(files)
+    }
+
+    fn prune(&self) -> Result<(), ReportError> {
+        let mut reports = self
+            .files()?
+            .into_iter()
+            .filter_map(|path| Some((read(&path).ok()?.created_at, path)))
+            .collect::<Vec<_>>();
+        if reports.len() <= MAX_REPORTS {
+            return Ok(());
+        }
+        reports.sort();
+        for (_, path) in &reports[..reports.len() - MAX_REPORTS] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// Ids are event ids, anything else can't name a report and could
+    /// escape the directory.
+    fn path(&self, id: &str) -> Result<PathBuf, ReportError> {
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(ReportError::NotFound(id.to_string()));
+        }
+        Ok(self.dir.join(format!("{}.json", id)))
+    }
+
+    fn existing(&self, id: &str) -> Result<PathBuf, ReportError> {
+        let path = self.path(id)?;
+        if !path.is_file() {
+            return Err(ReportError::NotFound(id.to_string()));
+        }
+        Ok(path)
+    }
+
+    fn write(&self, report: &CrashReport) -> Result<(), ReportError> {
+        let path = self.dir.join(format!("{}.json", report.id));
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(report)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+fn read(path: &Path) -> Result<CrashReport, ReportError> {
+    Ok(serde_json::from_slice(&fs::read(path)?)?)
+}
+
+/// Why a report didn't arrive.
+enum Failure {
+    /// Offline, rate limited or a server error, worth retrying later.
+    Transient(String),
+    /// The report itself is at fault and would fail the same way every time.
+    Permanent(String),
+}
+
+/// Posts the event to the DSN's envelope endpoint. Unlike Sentry's own
+/// transport this reports whether it arrived, so failures can be retried.
+async fn deliver(dsn: &Dsn, report: &CrashReport) -> Result<(), Failure> {
+    let event: Event<'static> = serde_json::from_value(report.event.clone())
+        .map_err(|e| Failure::Permanent(e.to_string()))?;
+    let mut body = Vec::new();
+    Envelope::from(event)
+        .to_writer(&mut body)
+        .map_err(|e| Failure::Permanent(e.to_string()))?;
+
+    let response = reqwest::Client::new()
+        .post(dsn.envelope_api_url().as_str())
+        .header("X-Sentry-Auth", dsn.to_auth(Some(USER_AGENT)).to_string())
+        .header("Content-Type", "application/x-sentry-envelope")
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| Failure::Transient(e.to_string()))?;
+    let status = response.status();
+    match response.error_for_status() {
+        Ok(_) => Ok(()),
+        Err(e)
+            if status.is_client_error()
+                && status != reqwest::StatusCode::REQUEST_TIMEOUT
+                && status != reqwest::StatusCode::TOO_MANY_REQUESTS =>
+        {
+            Err(Failure::Permanent(e.to_string()))
+        }
+        Err(e) => Err(Failure::Transient(e.to_string())),
+    }
+}
@@ -0,0 +1,28 @@
+//! Fixtures shared by the unit tests.
+
+use std::io::{Read, Write};
+
+/// An updater public key in the format `tauri signer` writes, made for the
+/// tests. Test data is signed with it.
+pub const PUBLIC_KEY: &str = concat!(
+    "dW50cnVzdGVkIGNvbW1lbnQ6IG1pbmlzaWduIHB1YmxpYyBrZXk6IDA4MDcwNjA1MDQwMzAyMDEK",
+    "UldRQkFnTUVCUVlIQ09wS2JHUGluRklLdnZWUWV4TXV4Zm1WUjNhdXZyNTdra0llNm1rVVJ0SXMK",
+);
+
+/// Appends a tar entry with a raw name, which `tar::Builder` would refuse to
+/// write for `..` or absolute paths.
+pub fn tar_entry<W: Write>(
+    builder: &mut tar::Builder<W>,
+    name: &str,
+    kind: tar::EntryType,
+    data: impl Read,
+    size: u64,
+) {
+    let mut header = tar::Header::new_gnu();
+    header.as_gnu_mut().unwrap().name[..name.len()].copy_from_slice(name.as_bytes());
+    header.set_entry_type(kind);
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_cksum();
+    builder.append(&header, data).unwrap();
+}
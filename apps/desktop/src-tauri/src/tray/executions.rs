@@ -0,0 +1,230 @@
+//! Executions in the edge runtime, polled from `/_internal/executions` to
+//! keep the tray's "Running" and "Recent" submenus current.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Deserialize;
+use tauri::menu::{MenuItemBuilder, Submenu, SubmenuBuilder};
+use tauri::{AppHandle, Manager, Wry};
+use tauri_plugin_http::reqwest;
+
+use crate::cmd;
+use crate::config::LoadedConfig;
+use crate::windows::{self, WindowTarget};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// How many finished executions the "Recent" submenu lists.
+const RECENT_LIMIT: usize = 10;
+
+const OPEN_PREFIX: &str = "execution:open:";
+const CANCEL_PREFIX: &str = "execution:cancel:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExecutionStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Execution {
+    pub id: String,
+    /// `<project>/<module>`
+    pub module: String,
+    pub version: Option<String>,
+    pub status: ExecutionStatus,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+}
+
+impl Execution {
+    fn label(&self) -> String {
+        match &self.version {
+            Some(version) => format!("{}@{}", self.module, version),
+            None => self.module.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Executions {
+    pub running: Vec<Execution>,
+    pub recent: Vec<Execution>,
+}
+
+/// The snapshot the tray menu was last built from.
+struct LatestExecutions(Mutex<Executions>);
+
+fn endpoint(app: &AppHandle) -> String {
+    let port = app.state::<LoadedConfig>().config.runtime.port;
+    format!("http://localhost:{}/_internal/executions", port)
+}
+
+async fn fetch(app: &AppHandle) -> Result<Executions, reqwest::Error> {
+    let mut executions: Executions = reqwest::get(endpoint(app))
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    executions.recent.truncate(RECENT_LIMIT);
+    Ok(executions)
+}
+
+async fn cancel(app: &AppHandle, id: &str) -> Result<(), reqwest::Error> {
+    reqwest::Client::new()
+        .delete(format!("{}/{}", endpoint(app), id))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Polls the runtime and rebuilds the tray menu whenever its executions
+/// change.
+pub fn spawn_poller(app: &AppHandle) {
+    app.manage(LatestExecutions(Mutex::default()));
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            // While the runtime is down or restarting the submenus are emptied
+            // rather than left showing stale runs.
+            let executions = fetch(&app).await.unwrap_or_else(|e| {
+                log::debug!("Failed to fetch executions: {}", e);
+                Executions::default()
+            });
+            let changed = {
+                let mut latest = app.state::<LatestExecutions>().0.lock().unwrap();
+                let changed = *latest != executions;
+                *latest = executions;
+                changed
+            };
+            if changed {
+                if let Err(e) = super::refresh(&app) {
+                    log::error!("Failed to rebuild tray menu: {}", e);
+                }
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    });
+}
+
+/// How many executions are running right now, as far as the runtime can
+/// tell. Falls back to the last poll if it doesn't answer.
+pub async fn running_count(app: &AppHandle) -> usize {
+    match fetch(app).await {
+        Ok(executions) => executions.running.len(),
+        Err(_) => latest(app).running.len(),
+    }
+}
+
+/// The executions the tray menu shows, empty until the first poll.
+pub fn latest(app: &AppHandle) -> Executions {
+    app.try_state::<LatestExecutions>()
+        .map(|latest| latest.0.lock().unwrap().clone())
+        .unwrap_or_default()
+}
+
+pub fn running_submenu(app: &AppHandle, executions: &Executions) -> tauri::Result<Submenu<Wry>> {
+    let title = match executions.running.len() {
+        0 => "Running".to_string(),
+        count => format!("Running ({})", count),
+    };
+    let mut menu = SubmenuBuilder::with_id(app, "running", title);
+    if executions.running.is_empty() {
+        menu = menu.item(
+            &MenuItemBuilder::new("No running executions")
+                .enabled(false)
+                .build(app)?,
+        );
+    }
+    for execution in &executions.running {
+        let item = SubmenuBuilder::new(app, execution.label())
+            .text(format!("{}{}", OPEN_PREFIX, execution.id), "Open")
+            .text(format!("{}{}", CANCEL_PREFIX, execution.id), "Cancel")
+            .build()?;
+        menu = menu.item(&item);
+    }
+    menu.build()
+}
+
+pub fn recent_submenu(app: &AppHandle, executions: &Executions) -> tauri::Result<Submenu<Wry>> {
+    let mut menu = SubmenuBuilder::with_id(app, "recent", "Recent");
+    if executions.recent.is_empty() {
+        menu = menu.item(
+            &MenuItemBuilder::new("No recent executions")
+                .enabled(false)
+                .build(app)?,
+        );
+    }
+    for execution in &executions.recent {
+        let icon = match execution.status {
+            ExecutionStatus::Running => "…",
+            ExecutionStatus::Succeeded => "✓",
+            ExecutionStatus::Failed => "✗",
+            ExecutionStatus::Cancelled => "⊘",
+        };
+        menu = menu.text(
+            format!("{}{}", OPEN_PREFIX, execution.id),
+            format!("{} {}", icon, execution.label()),
+        );
+    }
+    menu.build()
+}
+
+/// Handles the menu items added by the submenus above. Returns `false` for
+/// any other item.
+pub fn on_menu_event(app: &AppHandle, id: &str) -> bool {
+    if let Some(id) = id.strip_prefix(OPEN_PREFIX) {
+        if let Err(e) = open(app, id) {
+            log::error!("Failed to open execution {}: {}", id, e);
+        }
+    } else if let Some(id) = id.strip_prefix(CANCEL_PREFIX) {
+        let (app, id) = (app.clone(), id.to_string());
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = cancel(&app, &id).await {
+                log::error!("Failed to cancel execution {}: {}", id, e);
+            }
+        });
+    } else {
+        return false;
+    }
+    true
+}
+
+fn open(app: &AppHandle, id: &str) -> Result<(), cmd::CommandError> {
+    let execution = {
+        let latest = app.state::<LatestExecutions>().0.lock().unwrap();
+        latest
+            .running
+            .iter()
+            .chain(&latest.recent)
+            .find(|execution| execution.id == id)
+            .cloned()
+    };
+    let Some(execution) = execution else {
+        return Err(cmd::CommandError::NotFound(format!(
+            "execution {} is no longer listed",
+            id
+        )));
+    };
+    let Some((project_slug, module_name)) = execution.module.split_once('/') else {
+        return Err(cmd::CommandError::InvalidInput(format!(
+            "execution {} has no project in its module {:?}",
+            id, execution.module
+        )));
+    };
+
+    windows::open(
+        app,
+        WindowTarget::Execution {
+            id: execution.id,
+            project_slug: project_slug.to_string(),
+            module_name: module_name.to_string(),
+        },
+    )?;
+    Ok(())
+}
@@ -0,0 +1,167 @@
+//! The tray's "Troubleshooting" submenu, so debugging an install doesn't
+//! start with finding hidden folders.
+
+use std::path::{Path, PathBuf};
+
+use tauri::menu::{Submenu, SubmenuBuilder};
+use tauri::{AppHandle, Manager, Wry};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+use tauri_plugin_notification::NotificationExt;
+use tauri_plugin_shell::ShellExt;
+
+use crate::{cmd, functions, logs};
+use crate::config::LoadedConfig;
+use crate::runtime;
+use crate::telemetry::Telemetry;
+use crate::updater::{Channel, LastUpdateFailure};
+use crate::workspace::WorkspacePaths;
+
+const RESTART_RUNTIME: &str = "restart_runtime";
+/// The same as [`RESTART_RUNTIME`], shown at the top of the menu while the
+/// runtime is failing. Menu item ids have to be unique.
+pub const RESTART_RUNTIME_SHORTCUT: &str = "restart_runtime_shortcut";
+pub const OPEN_LOGS: &str = "open_logs";
+const LOG_VIEWER: &str = "log_viewer";
+/// The same as [`LOG_VIEWER`], next to [`RESTART_RUNTIME_SHORTCUT`].
+pub const LOG_VIEWER_SHORTCUT: &str = "log_viewer_shortcut";
+const OPEN_DATA: &str = "open_data";
+const OPEN_FUNCTIONS: &str = "open_functions";
+const COPY_DIAGNOSTICS: &str = "copy_diagnostics";
+const CRASH_REPORTS: &str = "crash_reports";
+
+pub fn submenu(app: &AppHandle) -> tauri::Result<Submenu<Wry>> {
+    SubmenuBuilder::with_id(app, "troubleshooting", "Troubleshooting")
+        .text(RESTART_RUNTIME, "Restart Runtime")
+        .separator()
+        .text(LOG_VIEWER, "Log Viewer…")
+        .text(OPEN_LOGS, "Open Logs Folder")
+        .text(OPEN_DATA, "Open Data Folder")
+        .text(OPEN_FUNCTIONS, "Open Functions Folder")
+        .separator()
+        .text(COPY_DIAGNOSTICS, "Copy Diagnostics")
+        .text(CRASH_REPORTS, "Pending Crash Reports…")
+        .build()
+}
+
+/// Handles the items above. Returns `false` for any other item.
+pub fn on_menu_event(app: &AppHandle, id: &str) -> bool {
+    match id {
+        RESTART_RUNTIME | RESTART_RUNTIME_SHORTCUT => runtime::restart(app),
+        LOG_VIEWER | LOG_VIEWER_SHORTCUT => {
+            if let Err(e) = logs::open_log_viewer(app) {
+                log::error!("Failed to open the log viewer: {}", e);
+            }
+        }
+        OPEN_LOGS => open_dir(app, app.path().app_log_dir()),
+        OPEN_DATA => open_dir(app, app.path().app_data_dir()),
+        OPEN_FUNCTIONS => open_dir(app, functions::dir(app)),
+        COPY_DIAGNOSTICS => copy_diagnostics(app),
+        CRASH_REPORTS => {
+            if let Err(e) = cmd::open_main_window_on(app, "/crash-reports") {
+                log::error!("Failed to open crash reports: {}", e);
+            }
+        }
+        _ => return false,
+    }
+    true
+}
+
+fn open_dir(app: &AppHandle, dir: tauri::Result<PathBuf>) {
+    let result = dir.map_err(|e| e.to_string()).and_then(|dir| {
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        open_path(app, &dir).map_err(|e| format!("{}: {}", dir.display(), e))
+    });
+    if let Err(e) = result {
+        log::error!("Failed to open folder: {}", e);
+    }
+}
+
+/// Opens a file or directory in the system file manager. The shell plugin's
+/// `open` is scoped to URLs, so this runs the platform's opener directly.
+fn open_path(app: &AppHandle, path: &Path) -> Result<(), tauri_plugin_shell::Error> {
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let program = "xdg-open";
+
+    app.shell().command(program).arg(path).spawn()?;
+    Ok(())
+}
+
+fn copy_diagnostics(app: &AppHandle) {
+    if let Err(e) = app.clipboard().write_text(diagnostics(app)) {
+        log::error!("Failed to copy diagnostics: {}", e);
+        return;
+    }
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title("Diagnostics copied")
+        .body("Paste them into your bug report or support message.")
+        .show()
+    {
+        log::warn!("Failed to show notification: {}", e);
+    }
+}
+
+/// A plain-text summary of versions, runtime state and paths to paste into
+/// bug reports.
+pub fn diagnostics(app: &AppHandle) -> String {
+    let package = app.package_info();
+    let config = app.state::<LoadedConfig>();
+    let telemetry = app.state::<Telemetry>();
+    let webview = tauri::webview_version().unwrap_or_else(|e| format!("unknown ({})", e));
+
+    let mut lines = vec![
+        format!(
+            "Craftgen {} (commit {})",
+            package.version,
+            env!("COMMIT_HASH").trim()
+        ),
+        format!("Tauri {}, webview {}", tauri::VERSION, webview),
+        format!("OS: {} {}", std::env::consts::OS, std::env::consts::ARCH),
+        format!(
+            "Runtime: {} on port {}",
+            runtime::status(app),
+            config.config.runtime.port
+        ),
+        format!("Update channel: {}", Channel::current(app)),
+        format!(
+            "Functions: {}",
+            functions::overlay_version(app).unwrap_or_else(|| "bundled".to_string())
+        ),
+        format!(
+            "Crash reporting: {}, {} pending reports",
+            if telemetry.is_enabled() { "on" } else { "off" },
+            telemetry.pending_reports()
+        ),
+        format!(
+            "Config: {} ({})",
+            config.path.display(),
+            if config.file_found { "loaded" } else { "not found" }
+        ),
+    ];
+    if let Some(error) = &config.error {
+        lines.push(format!("Config error: {}", error));
+    }
+    if let Some(failure) = app.state::<LastUpdateFailure>().describe() {
+        lines.push(format!("Last update failure: {}", failure));
+    }
+
+    let modules = WorkspacePaths::resolve(app).map(|paths| paths.modules);
+    for (label, path) in [
+        ("Data", app.path().app_data_dir()),
+        ("Modules", modules),
+        ("Logs", app.path().app_log_dir()),
+        ("Functions", functions::dir(app)),
+    ] {
+        let path = path.map_or_else(
+            |e| format!("unavailable ({})", e),
+            |path| path.display().to_string(),
+        );
+        lines.push(format!("{}: {}", label, path));
+    }
+    lines.join("\n")
+}
@@ -1,74 +1,55 @@
-mod updater;
+mod executions;
+mod maintenance;
 
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
 use tauri_plugin_shell::ShellExt;
 use tauri::{
     image::Image,
     menu::{
-        AboutMetadataBuilder, CheckMenuItemBuilder, MenuBuilder, MenuItemBuilder,
-        PredefinedMenuItem,
+        AboutMetadataBuilder, CheckMenuItemBuilder, Menu, MenuBuilder, MenuItem,
+        MenuItemBuilder, PredefinedMenuItem, Submenu, SubmenuBuilder,
     },
     tray::{ MouseButton, TrayIconBuilder, TrayIconEvent},
-    AppHandle
+    AppHandle, Manager, Wry
 };
-use tauri_plugin_store::StoreBuilder;
 
-pub use updater::check_for_update;
+use crate::{cmd, palette, settings, updater};
+use crate::runtime::{self, RuntimeStatus};
+use crate::settings::{Setting, SettingKind, SettingScope};
 
-use crate::cmd;
+/// Prefix of the check items generated for tray-scoped settings. Items for
+/// an option are `setting:<key>=<value>`.
+const SETTING_PREFIX: &str = "setting:";
 
-pub fn build(app: &AppHandle) {
-  
-    let about_metadata = AboutMetadataBuilder::new()
-        .short_version("Beta".into())
-        .icon(Image::from_bytes(include_bytes!("../../icons/128.png")).ok())
-        .copyright("©2024 Nokta PTE LTD".into())
-        .website("https://www.craftgen.ai".into())
-        .website_label("Visit Website".into())
-        .license("AGPL-3.0".into())
-        .version(app.package_info().version.to_string().into())
-        .build();
+/// Set once the user quits, so closing windows no longer keeps the app
+/// running in the background.
+pub static EXIT_FLAG: AtomicBool = AtomicBool::new(false);
 
-    let tray_menu = MenuBuilder::new(app)
-        .items(&[
-            // &MenuItemBuilder::with_id("record", "Start Recording")
-            //     .accelerator("CommandOrControl+Shift+2")
-            //     .build(app)
-            //     .expect(""),
-            &PredefinedMenuItem::separator(app).expect(""),
-            // &CheckMenuItemBuilder::with_id("record_cursor", "Record Mouse Cursor")
-            //     .checked(get_tray_setting(app, "record_cursor".to_string()))
-            //     .build(app)
-            //     .expect(""),
-            &CheckMenuItemBuilder::with_id("share_usage_data", "Share Usage Data")
-                .checked(get_tray_setting(app, "share_usage_data".to_string()))
-                .build(app)
-                .expect(""),
-            &PredefinedMenuItem::separator(app).expect(""),
-            &MenuItemBuilder::with_id("website", "Visit Website")
-                .build(app)
-                .expect(""),
-            &MenuItemBuilder::with_id("feedback", "Give Feedback")
-                .build(app)
-                .expect(""),
-            &PredefinedMenuItem::separator(app).expect(""),
-            &MenuItemBuilder::with_id("updates", "Check for Updates")
-                .build(app)
-                .expect(""),
-            &PredefinedMenuItem::about(app, "About Craftgen".into(), Some(about_metadata))
-                .expect(""),
-            &PredefinedMenuItem::quit(app, Some("Quit")).expect(""),
-        ])
-        .build()
-        .expect("Failed to build tray menu");
+pub fn build(app: &AppHandle) {
+    executions::spawn_poller(app);
+    let status = runtime::status(app);
+    let tray_menu = menu(app).expect("Failed to build tray menu");
 
     let mut tray = TrayIconBuilder::with_id("tray")
         .menu(&tray_menu)
-        .icon(Image::from_bytes(include_bytes!("../../icons/128.png")).expect(""))
-        .tooltip("Craftgen")
+        .icon(status_icon(status).expect(""))
+        .tooltip(tooltip(status))
         .on_menu_event(move |app, event| match event.id().as_ref() {
             // "record" => {
             //     toggle_cropper(app);
             // }
+            "open" => {
+                if let Err(e) = cmd::open_main_window(app) {
+                    log::error!("Failed to open main window: {}", e);
+                }
+            }
+            "palette" => {
+                if let Err(e) = palette::open_palette(app) {
+                    log::error!("Failed to open palette: {}", e);
+                }
+            }
             "feedback" => {
                 app.shell().open( "https://www.craftgen.ai/discord", None).expect("Failed to open feedback link");
             }
@@ -76,18 +57,33 @@ pub fn build(app: &AppHandle) {
                 app.shell().open("https://www.craftgen.ai", None).expect("failed to open about link");
             }
             "updates" => {
-                check_for_update(app.clone(), false).expect("Failed to check for updates");
+                updater::check_for_update(app.clone(), false).expect("Failed to check for updates");
+            }
+            "install_update" => updater::restart_to_install(app),
+            "install_update_file" => updater::pick_update_file(app),
+            "quit" => quit(app),
+            id => {
+                if let Some(key) = id.strip_prefix(SETTING_PREFIX) {
+                    let result = match key.split_once('=') {
+                        Some((key, value)) => settings::set(app, key, value.into()),
+                        None => settings::toggle(app, key),
+                    };
+                    if let Err(e) = result {
+                        log::error!("Failed to update {}: {}", key, e);
+                    }
+                } else if !maintenance::on_menu_event(app, id) {
+                    executions::on_menu_event(app, id);
+                }
             }
-            "record_cursor" => update_tray_setting(app, "record_cursor".to_string()),
-            "share_usage_data" => update_tray_setting(app, "share_usage_data".to_string()),
-            _ => (),
         })
         .on_tray_icon_event(|tray, event| {
             match event {
               TrayIconEvent::Click { button, .. } => {
                 if button == MouseButton::Left {
                     let app = tray.app_handle();
-                    cmd::open_main_window(&app).unwrap();
+                    if let Err(e) = cmd::open_main_window(app) {
+                        log::error!("Failed to open main window: {}", e);
+                    }
                 }
             }
                 _ => (),
@@ -98,53 +94,215 @@ pub fn build(app: &AppHandle) {
     {
         tray = tray
             .menu_on_left_click(false)
-            .icon(
-                Image::from_bytes(include_bytes!("../../icons/128.png"))
-                    .expect("Couldn't find icon"),
-            )
+            .icon(status_icon(status).expect("Couldn't find icon"))
             .icon_as_template(true)
     }
 
     tray.build(app).expect("Failed to build tray");
 }
 
-pub fn get_tray_setting(app: &AppHandle, key: String) -> bool {
-    let mut store = StoreBuilder::new("app_data.bin").build(app.clone());
-    store.load().unwrap_or_default();
+/// Quits the app and stops the runtime, after asking if executions are
+/// still running.
+fn quit(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let running = executions::running_count(&app).await;
+        if running == 0 {
+            exit(&app);
+            return;
+        }
+        let app_handle = app.clone();
+        app.dialog()
+            .message(match running {
+                1 => "An execution is still running. Quitting stops it.".to_string(),
+                n => format!("{} executions are still running. Quitting stops them.", n),
+            })
+            .title("Quit Craftgen?")
+            .kind(MessageDialogKind::Warning)
+            .ok_button_label("Quit")
+            .cancel_button_label("Cancel")
+            .show(move |quit| {
+                if quit {
+                    exit(&app_handle);
+                }
+            });
+    });
+}
+
+pub fn exit(app: &AppHandle) {
+    EXIT_FLAG.store(true, Ordering::Relaxed);
+    app.exit(0);
+}
+
+/// Replaces the tray menu, e.g. when the runtime's executions change.
+pub fn refresh(app: &AppHandle) -> tauri::Result<()> {
+    if let Some(tray) = app.tray_by_id("tray") {
+        tray.set_menu(Some(menu(app)?))?;
+    }
+    Ok(())
+}
+
+/// Shows the runtime status in the tray icon, tooltip and menu.
+pub fn set_status(app: &AppHandle, status: RuntimeStatus) {
+    let Some(tray) = app.tray_by_id("tray") else {
+        return;
+    };
+    let result = status_icon(status)
+        .and_then(|icon| tray.set_icon(Some(icon)))
+        .and_then(|_| {
+            #[cfg(target_os = "macos")]
+            tray.set_icon_as_template(true)?;
+            tray.set_tooltip(Some(tooltip(status)))
+        })
+        .and_then(|_| refresh(app));
+    if let Err(e) = result {
+        log::error!("Failed to show runtime status in the tray: {}", e);
+    }
+}
 
-    let setting_value = store
-        .get(key.clone())
-        .unwrap_or(&serde_json::Value::Bool(true))
-        .as_bool()
-        .unwrap();
+/// Shows how far an update download is in the tooltip, until the next
+/// [`set_status`].
+pub fn show_update_progress(app: &AppHandle, progress: &updater::UpdateProgress) {
+    let Some(tray) = app.tray_by_id("tray") else {
+        return;
+    };
+    let tooltip = format!(
+        "Craftgen: downloading {}, {}",
+        progress.version,
+        progress.describe()
+    );
+    if let Err(e) = tray.set_tooltip(Some(tooltip)) {
+        log::error!("Failed to show update progress in the tray: {}", e);
+    }
+}
 
-    setting_value
+/// Variants of the template icon with a badge for anything but healthy.
+fn status_icon(status: RuntimeStatus) -> tauri::Result<Image<'static>> {
+    let bytes: &'static [u8] = match status {
+        RuntimeStatus::Healthy => include_bytes!("../../icons/128.png"),
+        RuntimeStatus::Starting => include_bytes!("../../icons/tray/starting.png"),
+        RuntimeStatus::Degraded => include_bytes!("../../icons/tray/degraded.png"),
+        RuntimeStatus::Crashed => include_bytes!("../../icons/tray/crashed.png"),
+        RuntimeStatus::Updating => include_bytes!("../../icons/tray/updating.png"),
+    };
+    Image::from_bytes(bytes)
 }
 
-fn update_tray_setting(app: &AppHandle, key: String) {
-    let mut store = StoreBuilder::new("app_data.bin").build(app.clone());
-    store.load().unwrap_or_default();
+fn tooltip(status: RuntimeStatus) -> &'static str {
+    match status {
+        RuntimeStatus::Healthy => "Craftgen",
+        RuntimeStatus::Starting => "Craftgen: runtime starting",
+        RuntimeStatus::Degraded => "Craftgen: runtime not responding",
+        RuntimeStatus::Crashed => "Craftgen: runtime crashed",
+        RuntimeStatus::Updating => "Craftgen: updating",
+    }
+}
 
-    // Get current value or true if not found
-    let setting_value = get_tray_setting(app, key.clone());
+fn menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let about_metadata = AboutMetadataBuilder::new()
+        .short_version("Beta".into())
+        .icon(Image::from_bytes(include_bytes!("../../icons/128.png")).ok())
+        .copyright("©2024 Nokta PTE LTD".into())
+        .website("https://www.craftgen.ai".into())
+        .website_label("Visit Website".into())
+        .license("AGPL-3.0".into())
+        .version(app.package_info().version.to_string().into())
+        .build();
 
-    match setting_value {
-        true => {
-            store.insert(key.clone(), false.into()).unwrap();
+    let executions = executions::latest(app);
+    let status = runtime::status(app);
+    let mut builder = MenuBuilder::new(app);
+    if status.is_failure() {
+        let label = match status {
+            RuntimeStatus::Crashed => "Runtime crashed",
+            _ => "Runtime not responding",
+        };
+        builder = builder
+            .item(&MenuItemBuilder::new(label).enabled(false).build(app)?)
+            .text(maintenance::RESTART_RUNTIME_SHORTCUT, "Restart Runtime")
+            .text(maintenance::LOG_VIEWER_SHORTCUT, "View Logs")
+            .separator();
+    }
+
+    builder = builder
+        .items(&[
+            // Linux trays don't report clicks, and the window may be hidden.
+            &MenuItemBuilder::with_id("open", "Open Craftgen").build(app)?,
+            &MenuItemBuilder::with_id("palette", "Quick Launch…").build(app)?,
+            &PredefinedMenuItem::separator(app)?,
+            &executions::running_submenu(app, &executions)?,
+            &executions::recent_submenu(app, &executions)?,
+            // &MenuItemBuilder::with_id("record", "Start Recording")
+            //     .accelerator("CommandOrControl+Shift+2")
+            //     .build(app)
+            //     .expect(""),
+            &PredefinedMenuItem::separator(app)?,
+        ]);
+    for setting in settings::SETTINGS {
+        if setting.scope != SettingScope::Tray {
+            continue;
         }
-        false => {
-            store.insert(key.clone(), true.into()).unwrap();
+        if !setting.options.is_empty() {
+            builder = builder.item(&options_submenu(app, setting)?);
+        } else if setting.kind() == SettingKind::Boolean {
+            builder = builder.item(
+                &CheckMenuItemBuilder::with_id(
+                    format!("{}{}", SETTING_PREFIX, setting.key),
+                    setting.label,
+                )
+                .checked(settings::get_bool(app, setting.key))
+                .build(app)?,
+            );
         }
     }
-    store.save().expect("Failed to save store");
 
-    // log updated value
-    let updated_value = get_tray_setting(app, key.clone());
+    builder
+        .items(&[
+            &PredefinedMenuItem::separator(app)?,
+            &MenuItemBuilder::with_id("website", "Visit Website").build(app)?,
+            &MenuItemBuilder::with_id("feedback", "Give Feedback").build(app)?,
+            &PredefinedMenuItem::separator(app)?,
+            &maintenance::submenu(app)?,
+            &updates_item(app)?,
+            &MenuItemBuilder::with_id("install_update_file", "Install Update from File…").build(app)?,
+            &PredefinedMenuItem::about(app, "About Craftgen".into(), Some(about_metadata))?,
+            &MenuItemBuilder::with_id("quit", "Quit").build(app)?,
+        ])
+        .build()
+}
+
+/// "Check for Updates", or a way to restart into an update that was already
+/// downloaded in the background.
+fn updates_item(app: &AppHandle) -> tauri::Result<MenuItem<Wry>> {
+    match app.state::<updater::PendingUpdate>().version() {
+        Some(version) => MenuItemBuilder::with_id(
+            "install_update",
+            format!("Restart to Install {}", version),
+        )
+        .build(app),
+        None => MenuItemBuilder::with_id("updates", "Check for Updates").build(app),
+    }
+}
 
-    println!("{}: {}", key, updated_value);
+/// A submenu with one check item per option, the current one checked.
+fn options_submenu(app: &AppHandle, setting: &Setting) -> tauri::Result<Submenu<Wry>> {
+    let current = settings::get_string(app, setting.key);
+    let mut submenu = SubmenuBuilder::new(app, setting.label);
+    for option in setting.options {
+        submenu = submenu.item(
+            &CheckMenuItemBuilder::with_id(
+                format!("{}{}={}", SETTING_PREFIX, setting.key, option.value),
+                option.label,
+            )
+            .checked(current == option.value)
+            .build(app)?,
+        );
+    }
+    submenu.build()
 }
 
 #[tauri::command]
+#[specta::specta]
 pub async fn is_ok_sharing_usage_data(app: AppHandle) -> bool {
-    get_tray_setting(&app, "share_usage_data".to_string())
-}
\ No newline at end of file
+    settings::get_bool(&app, settings::SHARE_USAGE_DATA)
+}
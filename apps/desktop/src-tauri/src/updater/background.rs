@@ -0,0 +1,180 @@
+//! Silent update checks on an interval. A release found in the background is
+//! downloaded right away but only installed when the app quits or restarts,
+//! so running workflows are never interrupted. Functions bundles are checked
+//! on the same schedule, see [`crate::functions`].
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+use tauri_plugin_notification::NotificationExt;
+use tauri_plugin_updater::Update;
+
+use super::progress::{LastUpdateFailure, Progress, UpdateError};
+use super::{find_update, Channel};
+use crate::config::LoadedConfig;
+use crate::functions::FunctionsError;
+use crate::{functions, runtime, tray};
+
+/// Leaves startup to the runtime before the first check.
+const FIRST_CHECK_DELAY: Duration = Duration::from_secs(60);
+
+struct Downloaded {
+    update: Update,
+    bytes: Vec<u8>,
+}
+
+/// An update downloaded in the background, waiting for the app to quit.
+#[derive(Default)]
+pub struct PendingUpdate(Mutex<Option<Downloaded>>);
+
+impl PendingUpdate {
+    pub fn version(&self) -> Option<String> {
+        let pending = self.0.lock().unwrap();
+        pending
+            .as_ref()
+            .map(|pending| pending.update.version.clone())
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().take();
+    }
+
+    fn set(&self, update: Update, bytes: Vec<u8>) {
+        *self.0.lock().unwrap() = Some(Downloaded { update, bytes });
+    }
+
+    fn take(&self) -> Option<Downloaded> {
+        self.0.lock().unwrap().take()
+    }
+}
+
+pub fn spawn_scheduler(app: &AppHandle) {
+    let Some(interval) = app.state::<LoadedConfig>().config.updater.check_interval else {
+        log::info!("background update checks are disabled");
+        return;
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(FIRST_CHECK_DELAY).await;
+        loop {
+            check_and_download(&app).await;
+            match functions::update(&app, true).await {
+                Ok(Some(version)) => log::info!("switched to functions {}", version),
+                Ok(None) => {}
+                // Likely offline, tried again on the next check.
+                Err(e @ FunctionsError::Download { .. }) => {
+                    log::warn!("functions update check failed: {}", e)
+                }
+                Err(e) => functions::notify_failure(&app, &e),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+async fn check_and_download(app: &AppHandle) {
+    if app.state::<PendingUpdate>().version().is_some() {
+        return;
+    }
+    let channel = Channel::current(app);
+    let update = match find_update(app, channel, true).await {
+        Ok(Some(update)) => update,
+        Ok(None) => {
+            log::debug!("no update on the {} channel", channel);
+            return;
+        }
+        Err(e) => {
+            log::warn!("background update check failed: {}", e);
+            return;
+        }
+    };
+
+    download_for_quit(app, update, channel).await;
+}
+
+/// Downloads `update` and keeps it for [`install_pending`], unless the user
+/// switches away from `channel` in the meantime.
+pub(super) async fn download_for_quit(app: &AppHandle, update: Update, channel: Channel) {
+    log::info!("downloading update {} in the background", update.version);
+    let mut progress = Progress::new(app, &update.version);
+    let result = update
+        .download(|len, total| progress.chunk(len, total), || {})
+        .await;
+    progress.finish();
+    let bytes = match result {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            // Tried again on the next check.
+            app.state::<LastUpdateFailure>()
+                .record(app, &update.version, &UpdateError::from(e));
+            return;
+        }
+    };
+    // The user may have switched channels while it was downloading.
+    if Channel::current(app) != channel {
+        return;
+    }
+    // Now rather than when quitting, which shouldn't wait for a copy of the
+    // whole installation.
+    super::backup(app).await;
+
+    let version = update.version.clone();
+    app.state::<PendingUpdate>().set(update, bytes);
+    if let Err(e) = tray::refresh(app) {
+        log::error!("Failed to rebuild tray menu: {}", e);
+    }
+    if let Err(e) = app
+        .notification()
+        .builder()
+        .title("Update ready")
+        .body(format!(
+            "Craftgen {} will be installed the next time you quit or restart.",
+            version
+        ))
+        .show()
+    {
+        log::warn!("Failed to show notification: {}", e);
+    }
+}
+
+/// Installs the update downloaded in the background, if there is one. On
+/// Windows the installer takes over and this doesn't return.
+pub fn install_pending(app: &AppHandle) {
+    let Some(Downloaded { update, bytes }) = app.state::<PendingUpdate>().take() else {
+        return;
+    };
+    log::info!("installing update {}", update.version);
+    super::expect_update(app, &update.version);
+    if let Err(e) = update.install(bytes) {
+        app.state::<LastUpdateFailure>()
+            .record(app, &update.version, &UpdateError::from(e));
+    }
+}
+
+/// Stops the runtime, installs the downloaded update and relaunches.
+pub fn restart_to_install(app: &AppHandle) {
+    runtime::set_updating(app, true);
+    runtime::kill_sidecar_process(app);
+    install_pending(app);
+    app.restart();
+}
+
+/// Offers to restart into an update that is already downloaded.
+pub fn prompt_restart(app: &AppHandle, version: &str) {
+    let app_handle = app.clone();
+    app.dialog()
+        .message(format!(
+            "Craftgen {} is downloaded and will be installed when you quit.",
+            version
+        ))
+        .title("Update ready")
+        .ok_button_label("Restart Now")
+        .cancel_button_label("Later")
+        .show(move |restart| {
+            if restart {
+                restart_to_install(&app_handle);
+            }
+        });
+}
@@ -0,0 +1,52 @@
+use std::fmt;
+use std::str::FromStr;
+
+use tauri::AppHandle;
+
+use crate::settings::{self, UPDATE_CHANNEL};
+
+/// Which releases the updater follows, picked with the `update_channel`
+/// setting. Each channel has its own manifest endpoint in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Stable,
+    Beta,
+    Nightly,
+}
+
+impl Channel {
+    pub fn current(app: &AppHandle) -> Self {
+        let value = settings::get_string(app, UPDATE_CHANNEL);
+        value.parse().unwrap_or_else(|_| {
+            log::warn!("unknown update channel {:?}, using stable", value);
+            Channel::Stable
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Channel::Stable => "stable",
+            Channel::Beta => "beta",
+            Channel::Nightly => "nightly",
+        }
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Channel {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "stable" => Ok(Channel::Stable),
+            "beta" => Ok(Channel::Beta),
+            "nightly" => Ok(Channel::Nightly),
+            _ => Err(()),
+        }
+    }
+}
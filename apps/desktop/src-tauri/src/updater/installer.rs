@@ -0,0 +1,286 @@
+//! Installs a verified update bundle over the running installation, the way
+//! the Tauri updater installs the bundles it downloads, for bundles that
+//! don't come through it, see [`super::offline`].
+//!
+//! Accepts what the bundler publishes for updates: an AppImage or a
+//! `.AppImage.tar.gz` on Linux, a `.app.tar.gz` on macOS, and an NSIS or MSI
+//! installer, zipped or not, on Windows.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+use std::path::PathBuf;
+
+use tauri::AppHandle;
+
+use super::rollback;
+use super::UpdateError;
+
+/// Replaces the installation with the bundle in `bytes`. On Windows the
+/// installer takes over and the app exits.
+pub fn install(app: &AppHandle, bytes: &[u8]) -> Result<(), UpdateError> {
+    let path = rollback::install_path().ok_or_else(|| {
+        UpdateError::Install(
+            "the installation can't be located, only packaged builds can be updated".to_string(),
+        )
+    })?;
+    install_at(app, &path, bytes).map_err(|e| match e.kind() {
+        io::ErrorKind::InvalidData => UpdateError::Install(e.to_string()),
+        _ => UpdateError::Disk(e.to_string()),
+    })
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn is_gzip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&[0x1f, 0x8b])
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// A fresh directory for unpacking the bundle, outside of the installation.
+#[cfg(any(target_os = "macos", target_os = "windows"))]
+fn staging_dir() -> io::Result<PathBuf> {
+    let dir = std::env::temp_dir().join(format!("craftgen-update-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+#[cfg(not(target_os = "windows"))]
+fn install_at(_app: &AppHandle, path: &Path, bytes: &[u8]) -> io::Result<()> {
+    replace(path, bytes)
+}
+
+/// Writes the new AppImage next to the running one and renames it over it,
+/// which doesn't disturb the running process.
+#[cfg(target_os = "linux")]
+fn replace(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    use std::io::Read;
+
+    let appimage = match is_gzip(bytes) {
+        true => {
+            let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(bytes));
+            let mut appimage = None;
+            for entry in archive.entries()? {
+                let mut entry = entry?;
+                if entry
+                    .path()?
+                    .extension()
+                    .is_some_and(|ext| ext == "AppImage")
+                {
+                    let mut contents = Vec::new();
+                    entry.read_to_end(&mut contents)?;
+                    appimage = Some(contents);
+                    break;
+                }
+            }
+            appimage.ok_or_else(|| invalid("the bundle has no AppImage"))?
+        }
+        false => bytes.to_vec(),
+    };
+
+    let mut name = path.file_name().unwrap_or_default().to_owned();
+    name.push(".update");
+    let new = path.with_file_name(name);
+    let written = fs::write(&new, appimage)
+        .and_then(|()| fs::set_permissions(&new, fs::metadata(path)?.permissions()))
+        .and_then(|()| fs::rename(&new, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&new);
+    }
+    written
+}
+
+/// Unpacks the `.app` and copies it over the running one file by file, like
+/// a rollback restores the backup.
+#[cfg(target_os = "macos")]
+fn replace(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    if !is_gzip(bytes) {
+        return Err(invalid("the bundle is not a .app.tar.gz"));
+    }
+    let staging = staging_dir()?;
+    let installed = (|| {
+        // `unpack` refuses entries that would end up outside of `staging`.
+        tar::Archive::new(flate2::read::GzDecoder::new(bytes)).unpack(&staging)?;
+        let bundle = fs::read_dir(&staging)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .find(|path| path.extension().is_some_and(|ext| ext == "app"))
+            .ok_or_else(|| invalid("the bundle has no .app"))?;
+        rollback::restore(&bundle, path)
+    })();
+    if let Err(e) = fs::remove_dir_all(&staging) {
+        log::warn!("Failed to remove {}: {}", staging.display(), e);
+    }
+    installed
+}
+
+/// Starts the installer, which replaces the installation once the app has
+/// exited and relaunches it.
+#[cfg(target_os = "windows")]
+fn install_at(app: &AppHandle, _path: &Path, bytes: &[u8]) -> io::Result<()> {
+    const MSI_MAGIC: &[u8] = &[0xd0, 0xcf, 0x11, 0xe0, 0xa1, 0xb1, 0x1a, 0xe1];
+
+    let staging = staging_dir()?;
+    let installer = if bytes.starts_with(b"PK\x03\x04") {
+        zip::ZipArchive::new(io::Cursor::new(bytes))
+            .and_then(|mut archive| archive.extract(&staging))
+            .map_err(|e| invalid(&e.to_string()))?;
+        fs::read_dir(&staging)?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .find(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == "exe" || ext == "msi")
+            })
+            .ok_or_else(|| invalid("the bundle has no installer"))?
+    } else if bytes.starts_with(b"MZ") {
+        let installer = staging.join("Craftgen-setup.exe");
+        fs::write(&installer, bytes)?;
+        installer
+    } else if bytes.starts_with(MSI_MAGIC) {
+        let installer = staging.join("Craftgen.msi");
+        fs::write(&installer, bytes)?;
+        installer
+    } else {
+        return Err(invalid("the bundle is not an NSIS or MSI installer"));
+    };
+
+    let quoted = format!("\"{}\"", installer.display());
+    let (file, parameters) = match installer.extension().is_some_and(|ext| ext == "msi") {
+        true => (
+            PathBuf::from("msiexec.exe"),
+            format!("/i {} /passive /promptrestart AUTOLAUNCHAPP=True", quoted),
+        ),
+        false => (installer, "/P /R /UPDATE".to_string()),
+    };
+    shell_execute(&file, &parameters)?;
+    // The installer can't replace files that are in use.
+    app.exit(0);
+    Ok(())
+}
+
+/// Runs `file` like Explorer would, so the installer can ask for elevation.
+#[cfg(target_os = "windows")]
+fn shell_execute(file: &Path, parameters: &str) -> io::Result<()> {
+    use std::ffi::OsStr;
+    use std::os::windows::ffi::OsStrExt;
+
+    use windows_sys::Win32::UI::Shell::ShellExecuteW;
+    use windows_sys::Win32::UI::WindowsAndMessaging::SW_SHOW;
+
+    let wide = |s: &OsStr| s.encode_wide().chain([0]).collect::<Vec<u16>>();
+    let (operation, file, parameters) = (
+        wide(OsStr::new("open")),
+        wide(file.as_os_str()),
+        wide(OsStr::new(parameters)),
+    );
+    // SAFETY: every pointer is a NUL-terminated string that outlives the call.
+    let result = unsafe {
+        ShellExecuteW(
+            0,
+            operation.as_ptr(),
+            file.as_ptr(),
+            parameters.as_ptr(),
+            std::ptr::null(),
+            SW_SHOW,
+        )
+    };
+    // Anything up to 32 is an error code.
+    match result > 32 {
+        true => Ok(()),
+        false => Err(io::Error::last_os_error()),
+    }
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "macos")))]
+mod tests {
+    use super::*;
+
+    fn tar_gz(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::fast(),
+        ));
+        for (name, data) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o755);
+            builder.append_data(&mut header, name, *data).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn replaces_the_appimage_keeping_its_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Craftgen.AppImage");
+        fs::write(&path, "old").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        replace(&path, b"raw").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"raw");
+
+        let bundle = tar_gz(&[("Craftgen_0.2.0_amd64.AppImage", b"packed")]);
+        replace(&path, &bundle).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"packed");
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn refuses_a_bundle_without_an_appimage() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Craftgen.AppImage");
+        fs::write(&path, "old").unwrap();
+
+        let error = replace(&path, &tar_gz(&[("README", b"hi")])).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(fs::read(&path).unwrap(), b"old");
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn replaces_the_app_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Craftgen.app");
+        fs::create_dir_all(path.join("Contents/MacOS")).unwrap();
+        fs::write(path.join("Contents/MacOS/craftgen"), "old").unwrap();
+        fs::write(path.join("Contents/Removed"), "old").unwrap();
+
+        let bundle = tar_gz(&[
+            ("Craftgen.app/Contents/MacOS/craftgen", b"new"),
+            ("Craftgen.app/Contents/Info.plist", b"plist"),
+        ]);
+        replace(&path, &bundle).unwrap();
+        assert_eq!(
+            fs::read(path.join("Contents/MacOS/craftgen")).unwrap(),
+            b"new"
+        );
+        assert_eq!(
+            fs::read(path.join("Contents/Info.plist")).unwrap(),
+            b"plist"
+        );
+        assert!(!path.join("Contents/Removed").exists());
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn refuses_a_bundle_without_an_app() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Craftgen.app");
+        fs::create_dir_all(&path).unwrap();
+
+        let error = replace(&path, b"not gzip").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = replace(&path, &tar_gz(&[("README", b"hi")])).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,260 @@
+//! App updates from the manifest of the selected release channel.
+//!
+//! The user can check at any time from the tray, and a release that is found
+//! is shown with its notes in the update window, see [`prompt`]. Besides that
+//! the app checks silently in the background, downloads what it finds and
+//! installs it when the app quits, see [`background`]. Either way the replaced installation
+//! is kept and restored if the new version fails its first start, see
+//! [`rollback`]. Machines without access to the update server can install
+//! a downloaded manifest and bundle instead, see [`offline`].
+
+mod background;
+mod channel;
+mod installer;
+mod offline;
+mod progress;
+mod prompt;
+mod rollback;
+mod signature;
+
+use tauri::{AppHandle, Manager, Result};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+use tauri_plugin_updater::{Update, UpdaterExt};
+
+pub use background::{install_pending, restart_to_install, PendingUpdate};
+pub use channel::Channel;
+pub use offline::{install_from_file, pick_update_file, OfflineError};
+pub use progress::{LastUpdateFailure, UpdateError, UpdateFailed, UpdateProgress};
+pub use prompt::{
+    get_update_offer, respond_to_update, OfferedUpdate, UpdateAction, UpdateOffer,
+};
+pub use rollback::spawn_health_check;
+pub use signature::{public_key, verify as verify_signature, SignatureError};
+
+use progress::Progress;
+
+use crate::cmd::CommandError;
+use crate::config::LoadedConfig;
+use crate::runtime;
+use crate::settings::{SettingChanged, UPDATE_CHANNEL};
+
+/// Starts the background checks, and checks the new channel as soon as the
+/// user switches to it.
+pub fn start(app: &AppHandle) {
+    background::spawn_scheduler(app);
+
+    let app_handle = app.clone();
+    SettingChanged::listen(app, move |event| {
+        if event.payload.key == UPDATE_CHANNEL {
+            log::info!("update channel changed to {}", event.payload.value);
+            // A download from the previous channel is no longer wanted.
+            app_handle.state::<PendingUpdate>().clear();
+            if let Err(e) = check_for_update(app_handle.clone(), false) {
+                log::error!("Failed to check for updates: {}", e);
+            }
+        }
+    });
+}
+
+/// Looks for a release on the current channel and offers it in the update
+/// window, see [`prompt`].
+///
+/// A check the user asked for also offers a release older than the running
+/// version, e.g. after moving from beta back to stable, which the window
+/// presents as a downgrade. Silent checks only ever upgrade, and pass over
+/// skipped versions.
+pub fn check_for_update(app_handle: AppHandle, silent_if_none: bool) -> Result<()> {
+    if !silent_if_none {
+        if let Some(version) = app_handle.state::<PendingUpdate>().version() {
+            background::prompt_restart(&app_handle, &version);
+            return Ok(());
+        }
+    }
+    tauri::async_runtime::spawn(async move {
+        let channel = Channel::current(&app_handle);
+        let response = find_update(&app_handle, channel, silent_if_none).await;
+        match response {
+            Ok(update_option) => {
+                if let Some(update) = update_option {
+                    log::info!("update {} available at {}", update.version, update.download_url);
+                    if let Err(e) = prompt::offer(&app_handle, update, channel) {
+                        log::error!("Failed to show the update window: {}", e);
+                    }
+                } else {
+                    match silent_if_none {
+                        true => {}
+                        _ => {
+                            app_handle
+                                .dialog()
+                                .message(format!(
+                                    "You're on the latest {} version!",
+                                    channel
+                                ))
+                                .ok_button_label("Okay")
+                                .show(|_| {});
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!("Failed to check for updates: {}", e);
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Lets the frontend check, silently once the main window has loaded.
+#[tauri::command]
+#[specta::specta]
+pub fn check_for_updates(
+    app_handle: AppHandle,
+    silent: bool,
+) -> std::result::Result<(), CommandError> {
+    Ok(check_for_update(app_handle, silent)?)
+}
+
+/// Downloads and installs `update` while the user waits, then offers to
+/// relaunch. A failure is recorded and can be retried.
+fn install_now(app_handle: AppHandle, update: Update) {
+    tauri::async_runtime::spawn(async move {
+        let update_result = install(&app_handle, &update).await;
+        let version = update.version.clone();
+        report_install(app_handle, &version, update_result, move |app_handle| {
+            install_now(app_handle, update)
+        });
+    });
+}
+
+/// Downloads `update` with progress and installs it, see [`install_verified`].
+async fn install(app: &AppHandle, update: &Update) -> std::result::Result<(), UpdateError> {
+    runtime::set_updating(app, true);
+    let mut progress = Progress::new(app, &update.version);
+    let download = update
+        .download(
+            |len, total| progress.chunk(len, total),
+            || log::info!("update {} downloaded, installing", update.version),
+        )
+        .await;
+    progress.finish();
+    let bytes = download?;
+    install_verified(app, &update.version, || Ok(update.install(bytes)?)).await
+}
+
+/// The path every update takes once its bundle is verified, downloaded or
+/// from a file: keep the current installation for [`rollback`], `install`.
+async fn install_verified(
+    app: &AppHandle,
+    version: &str,
+    install: impl FnOnce() -> std::result::Result<(), UpdateError>,
+) -> std::result::Result<(), UpdateError> {
+    backup(app).await;
+    expect_update(app, version);
+    install()
+}
+
+/// Offers to relaunch into the installed update, or records the failure and
+/// offers to `retry`.
+fn report_install(
+    app_handle: AppHandle,
+    version: &str,
+    result: std::result::Result<(), UpdateError>,
+    retry: impl FnOnce(AppHandle) + Send + 'static,
+) {
+    match result {
+        Ok(()) => {
+            app_handle
+                .dialog()
+                .message("Update installed successfully!")
+                .ok_button_label("Relaunch")
+                .show(move |_| {
+                    app_handle.restart();
+                });
+        }
+        Err(error) => {
+            runtime::set_updating(&app_handle, false);
+            app_handle
+                .state::<LastUpdateFailure>()
+                .record(&app_handle, version, &error);
+            app_handle
+                .dialog()
+                .message(format!(
+                    "Craftgen {} could not be installed.\n\n{}",
+                    version, error
+                ))
+                .title("Update failed")
+                .kind(MessageDialogKind::Error)
+                .ok_button_label("Retry")
+                .cancel_button_label("Cancel")
+                .show(move |retry_now| {
+                    if retry_now {
+                        retry(app_handle);
+                    }
+                });
+        }
+    }
+}
+
+/// Keeps the current installation for [`rollback`]. Updating goes ahead
+/// without it if that fails, like it always did.
+async fn backup(app: &AppHandle) {
+    let app_handle = app.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || rollback::backup(&app_handle)).await;
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => log::warn!("Failed to back up the installation: {}", e),
+        Err(e) => log::warn!("Failed to back up the installation: {}", e),
+    }
+}
+
+/// Has the first launch of `version` checked against the backup, right
+/// before installing it.
+fn expect_update(app: &AppHandle, version: &str) {
+    if let Err(e) = rollback::expect_update(app, version) {
+        log::warn!("Failed to prepare checking {}: {}", version, e);
+    }
+}
+
+/// Asks the channel's endpoint for a release other than the running one,
+/// only a newer one with `upgrade_only`, which also skips a version that was
+/// rolled back or that the user skipped.
+async fn find_update(
+    app: &AppHandle,
+    channel: Channel,
+    upgrade_only: bool,
+) -> std::result::Result<Option<Update>, tauri_plugin_updater::Error> {
+    let endpoint = app
+        .state::<LoadedConfig>()
+        .config
+        .updater
+        .endpoint(channel)
+        .clone();
+    let rolled_back = rollback::rolled_back_version(app);
+    let app_handle = app.clone();
+    app.updater_builder()
+        .endpoints(vec![endpoint])?
+        .version_comparator(move |current, release| match upgrade_only {
+            true => {
+                let version = release.version.to_string();
+                release.version > current
+                    && rolled_back.as_deref() != Some(version.as_str())
+                    && !prompt::is_skipped(&app_handle, &version)
+            }
+            false => release.version != current,
+        })
+        .build()?
+        .check()
+        .await
+}
+
+/// Whether installing `version` would move back from `current`. Versions
+/// that don't parse are never treated as a downgrade.
+fn is_downgrade(current: &str, version: &str) -> bool {
+    match (
+        semver::Version::parse(current),
+        semver::Version::parse(version),
+    ) {
+        (Ok(current), Ok(version)) => version < current,
+        _ => false,
+    }
+}
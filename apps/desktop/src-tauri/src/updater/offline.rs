@@ -0,0 +1,336 @@
+//! Updates from a manifest and bundle on disk, for machines that can't reach
+//! the update server.
+//!
+//! The bundle is checked like the Tauri updater checks a download, against
+//! the signature the manifest lists for this platform and the updater's
+//! public key, and only a newer version is accepted. It's then installed the
+//! same way as one from the network, see [`super::install_verified`].
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use tauri::{AppHandle, Url};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+use super::signature::{self, SignatureError};
+use super::{install_verified, installer, report_install, UpdateError};
+use crate::cmd::CommandError;
+use crate::runtime;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OfflineError {
+    #[error("failed to read {}: {error}", path.display())]
+    Read { path: PathBuf, error: io::Error },
+    #[error("invalid update manifest: {0}")]
+    Manifest(String),
+    #[error("the update manifest has no bundle for {0}")]
+    MissingPlatform(String),
+    #[error("no update bundle next to the manifest, expected {0}")]
+    MissingBundle(String),
+    #[error("update {version} isn't newer than the installed {current}")]
+    NotNewer { version: String, current: String },
+    #[error("the update bundle isn't signed for Craftgen: {0}")]
+    Signature(#[from] SignatureError),
+    #[error("Craftgen {version} could not be installed: {error}")]
+    Install { version: String, error: UpdateError },
+}
+
+impl From<OfflineError> for CommandError {
+    fn from(e: OfflineError) -> Self {
+        match e {
+            OfflineError::Read { error, .. } => error.into(),
+            OfflineError::Manifest(_)
+            | OfflineError::MissingPlatform(_)
+            | OfflineError::MissingBundle(_)
+            | OfflineError::NotNewer { .. }
+            | OfflineError::Signature(_) => CommandError::InvalidInput(e.to_string()),
+            OfflineError::Install { .. } => CommandError::Runtime(e.to_string()),
+        }
+    }
+}
+
+/// A `latest.json` as published for online updates, either with a single
+/// `url` and `signature` or with one per platform.
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    version: String,
+    url: Option<Url>,
+    signature: Option<String>,
+    #[serde(default)]
+    platforms: HashMap<String, Release>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Release {
+    url: Url,
+    signature: String,
+}
+
+impl Manifest {
+    fn parse(bytes: &[u8]) -> Result<Self, OfflineError> {
+        serde_json::from_slice(bytes).map_err(|e| OfflineError::Manifest(e.to_string()))
+    }
+
+    /// The release for `target`, like `darwin-x86_64`, falling back to the
+    /// top-level one.
+    fn release(&self, target: &str) -> Result<Release, OfflineError> {
+        if let Some(release) = self.platforms.get(target) {
+            return Ok(release.clone());
+        }
+        match (&self.url, &self.signature) {
+            (Some(url), Some(signature)) => Ok(Release {
+                url: url.clone(),
+                signature: signature.clone(),
+            }),
+            _ => Err(OfflineError::MissingPlatform(target.to_string())),
+        }
+    }
+}
+
+/// The platform key the Tauri updater looks up in `platforms`.
+fn target() -> String {
+    let os = match std::env::consts::OS {
+        "macos" => "darwin",
+        os => os,
+    };
+    let arch = match std::env::consts::ARCH {
+        "x86" => "i686",
+        "arm" => "armv7",
+        arch => arch,
+    };
+    format!("{}-{}", os, arch)
+}
+
+/// Accepts `bundle` for `release` of `manifest` if it's newer than `current`
+/// and signed with `public_key`. Returns the version to install.
+fn verify(
+    manifest: &Manifest,
+    release: &Release,
+    current: &semver::Version,
+    bundle: &[u8],
+    public_key: &str,
+) -> Result<semver::Version, OfflineError> {
+    let version = semver::Version::parse(manifest.version.trim_start_matches('v'))
+        .map_err(|e| OfflineError::Manifest(format!("invalid `version`: {}", e)))?;
+    if version <= *current {
+        return Err(OfflineError::NotNewer {
+            version: version.to_string(),
+            current: current.to_string(),
+        });
+    }
+    signature::verify(public_key, bundle, &release.signature)?;
+    Ok(version)
+}
+
+/// Installs the update described by `manifest`. Without `bundle` the file
+/// its URL for this platform names is looked up next to the manifest.
+/// Returns the installed version.
+pub async fn install_from_file(
+    app: &AppHandle,
+    manifest_path: &Path,
+    bundle: Option<&Path>,
+) -> Result<String, OfflineError> {
+    let manifest = Manifest::parse(&read(manifest_path)?)?;
+    let release = manifest.release(&target())?;
+    let bundle = match bundle {
+        Some(bundle) => bundle.to_path_buf(),
+        None => find_bundle(manifest_path, &release)?,
+    };
+    let bytes = read(&bundle)?;
+    let version = verify(
+        &manifest,
+        &release,
+        &app.package_info().version,
+        &bytes,
+        &signature::public_key(app)?,
+    )?
+    .to_string();
+
+    log::info!("installing update {} from {}", version, bundle.display());
+    runtime::set_updating(app, true);
+    install_verified(app, &version, || installer::install(app, &bytes))
+        .await
+        .map_err(|error| OfflineError::Install {
+            version: version.clone(),
+            error,
+        })?;
+    Ok(version)
+}
+
+/// "Install Update from File…": asks for the manifest, and for the bundle
+/// too if it isn't next to it.
+pub fn pick_update_file(app: &AppHandle) {
+    let app_handle = app.clone();
+    app.dialog()
+        .file()
+        .set_title("Choose the update manifest (latest.json)")
+        .add_filter("Update manifest", &["json"])
+        .pick_file(move |manifest| {
+            if let Some(manifest) = manifest.and_then(|path| path.into_path().ok()) {
+                install_interactively(app_handle, manifest, None);
+            }
+        });
+}
+
+fn install_interactively(app_handle: AppHandle, manifest: PathBuf, bundle: Option<PathBuf>) {
+    tauri::async_runtime::spawn(async move {
+        let result = install_from_file(&app_handle, &manifest, bundle.as_deref()).await;
+        let (version, result) = match result {
+            Ok(version) => (version, Ok(())),
+            Err(OfflineError::Install { version, error }) => (version, Err(error)),
+            Err(OfflineError::MissingBundle(_)) => {
+                let app = app_handle.clone();
+                app_handle
+                    .dialog()
+                    .file()
+                    .set_title("Choose the update bundle")
+                    .pick_file(move |bundle| {
+                        if let Some(bundle) = bundle.and_then(|path| path.into_path().ok()) {
+                            install_interactively(app, manifest, Some(bundle));
+                        }
+                    });
+                return;
+            }
+            Err(e) => {
+                log::error!("Failed to install update from file: {}", e);
+                app_handle
+                    .dialog()
+                    .message(e.to_string())
+                    .title("Can't install this update")
+                    .kind(MessageDialogKind::Error)
+                    .show(|_| {});
+                return;
+            }
+        };
+        report_install(app_handle, &version, result, move |app_handle| {
+            install_interactively(app_handle, manifest, bundle)
+        });
+    });
+}
+
+/// The file `release` downloads from, next to the manifest.
+fn find_bundle(manifest: &Path, release: &Release) -> Result<PathBuf, OfflineError> {
+    let name = release
+        .url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| OfflineError::MissingBundle(release.url.to_string()))?;
+    let path = manifest.parent().unwrap_or(Path::new(".")).join(name);
+    match path.is_file() {
+        true => Ok(path),
+        false => Err(OfflineError::MissingBundle(name.to_string())),
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, OfflineError> {
+    std::fs::read(path).map_err(|error| OfflineError::Read {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::PUBLIC_KEY;
+
+    const BUNDLE: &[u8] = include_bytes!("../testdata/functions.tar.gz");
+    const BUNDLE_SIGNATURE: &str = include_str!("../testdata/functions.tar.gz.sig");
+
+    fn manifest(version: &str) -> Manifest {
+        Manifest::parse(
+            serde_json::json!({
+                "version": version,
+                "platforms": {
+                    target(): {
+                        "url": "https://example.com/v0.2.0/Craftgen.app.tar.gz",
+                        "signature": BUNDLE_SIGNATURE,
+                    },
+                    "other-arch": {
+                        "url": "https://example.com/v0.2.0/Other.tar.gz",
+                        "signature": "",
+                    },
+                },
+            })
+            .to_string()
+            .as_bytes(),
+        )
+        .unwrap()
+    }
+
+    fn current() -> semver::Version {
+        semver::Version::new(0, 1, 0)
+    }
+
+    #[test]
+    fn accepts_a_newer_signed_bundle() {
+        let manifest = manifest("v0.2.0");
+        let release = manifest.release(&target()).unwrap();
+        let version = verify(&manifest, &release, &current(), BUNDLE, PUBLIC_KEY).unwrap();
+        assert_eq!(version, semver::Version::new(0, 2, 0));
+    }
+
+    #[test]
+    fn refuses_a_tampered_bundle() {
+        let manifest = manifest("0.2.0");
+        let release = manifest.release(&target()).unwrap();
+        let mut bundle = BUNDLE.to_vec();
+        *bundle.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            verify(&manifest, &release, &current(), &bundle, PUBLIC_KEY),
+            Err(OfflineError::Signature(SignatureError::Mismatch(_)))
+        ));
+    }
+
+    #[test]
+    fn refuses_a_version_that_isnt_newer() {
+        let same = manifest("0.1.0");
+        let release = same.release(&target()).unwrap();
+        assert!(matches!(
+            verify(&same, &release, &current(), BUNDLE, PUBLIC_KEY),
+            Err(OfflineError::NotNewer { .. })
+        ));
+
+        let invalid = manifest("latest");
+        assert!(matches!(
+            verify(&invalid, &release, &current(), BUNDLE, PUBLIC_KEY),
+            Err(OfflineError::Manifest(_))
+        ));
+    }
+
+    #[test]
+    fn picks_the_release_for_this_platform() {
+        let release = manifest("0.2.0").release(&target()).unwrap();
+        assert_eq!(release.signature, BUNDLE_SIGNATURE);
+        assert!(matches!(
+            manifest("0.2.0").release("plan9-mips"),
+            Err(OfflineError::MissingPlatform(target)) if target == "plan9-mips"
+        ));
+
+        let simple = Manifest::parse(
+            br#"{"version": "0.2.0", "url": "https://example.com/a.tar.gz", "signature": "sig"}"#,
+        )
+        .unwrap();
+        assert_eq!(simple.release("plan9-mips").unwrap().signature, "sig");
+    }
+
+    #[test]
+    fn finds_the_bundle_next_to_the_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_path = dir.path().join("latest.json");
+        let release = manifest("0.2.0").release(&target()).unwrap();
+        assert!(matches!(
+            find_bundle(&manifest_path, &release),
+            Err(OfflineError::MissingBundle(name)) if name == "Craftgen.app.tar.gz"
+        ));
+
+        std::fs::write(dir.path().join("Craftgen.app.tar.gz"), BUNDLE).unwrap();
+        assert_eq!(
+            find_bundle(&manifest_path, &release).unwrap(),
+            dir.path().join("Craftgen.app.tar.gz")
+        );
+    }
+}
@@ -0,0 +1,176 @@
+//! Download progress and failures of app updates, reported to the webview
+//! as events and to the user through the tray tooltip.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri_specta::Event;
+
+use crate::{runtime, tray};
+
+/// Chunks arrive far more often than anyone can read a percentage.
+const EMIT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Why downloading or installing an update failed.
+///
+/// Serialized as `{ kind, message }` like [`crate::cmd::CommandError`].
+#[derive(Debug, Clone, thiserror::Error, Serialize, Deserialize, specta::Type)]
+#[serde(tag = "kind", content = "message", rename_all = "camelCase")]
+pub enum UpdateError {
+    /// The update server or the download couldn't be reached.
+    #[error("network error: {0}")]
+    Network(String),
+    /// The download doesn't match the signature published with it.
+    #[error("signature mismatch: {0}")]
+    Signature(String),
+    /// The update couldn't be written or unpacked.
+    #[error("disk error: {0}")]
+    Disk(String),
+    #[error("install failed: {0}")]
+    Install(String),
+}
+
+impl From<tauri_plugin_updater::Error> for UpdateError {
+    fn from(e: tauri_plugin_updater::Error) -> Self {
+        use tauri_plugin_updater::Error;
+
+        match e {
+            Error::Reqwest(_) => UpdateError::Network(e.to_string()),
+            Error::Minisign(_) | Error::Base64(_) | Error::SignatureUtf8(_) => {
+                UpdateError::Signature(e.to_string())
+            }
+            Error::Io(_) => UpdateError::Disk(e.to_string()),
+            _ => UpdateError::Install(e.to_string()),
+        }
+    }
+}
+
+/// Emitted while an update downloads, at most every 250ms.
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type, tauri_specta::Event)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateProgress {
+    pub version: String,
+    pub downloaded: u64,
+    /// Unknown when the server doesn't send a content length.
+    pub total: Option<u64>,
+    pub bytes_per_second: u64,
+}
+
+impl UpdateProgress {
+    /// Like "45% at 1.2 MB/s", for the tray tooltip.
+    pub fn describe(&self) -> String {
+        let rate = format!("{:.1} MB/s", self.bytes_per_second as f64 / 1_000_000.0);
+        match self.total {
+            Some(total) if total > 0 => {
+                format!("{}% at {}", self.downloaded * 100 / total, rate)
+            }
+            _ => format!("{:.1} MB at {}", self.downloaded as f64 / 1_000_000.0, rate),
+        }
+    }
+}
+
+/// Emitted when downloading or installing an update failed.
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type, tauri_specta::Event)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateFailed {
+    pub version: String,
+    pub error: UpdateError,
+}
+
+/// Turns the updater's chunk callbacks into [`UpdateProgress`] events.
+pub struct Progress {
+    app: AppHandle,
+    version: String,
+    started: Instant,
+    downloaded: u64,
+    last_emit: Option<Instant>,
+}
+
+impl Progress {
+    pub fn new(app: &AppHandle, version: &str) -> Self {
+        Self {
+            app: app.clone(),
+            version: version.to_string(),
+            started: Instant::now(),
+            downloaded: 0,
+            last_emit: None,
+        }
+    }
+
+    pub fn chunk(&mut self, len: usize, total: Option<u64>) {
+        self.downloaded += len as u64;
+        let done = total.is_some_and(|total| self.downloaded >= total);
+        if !done
+            && self
+                .last_emit
+                .is_some_and(|last| last.elapsed() < EMIT_INTERVAL)
+        {
+            return;
+        }
+        self.last_emit = Some(Instant::now());
+
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let progress = UpdateProgress {
+            version: self.version.clone(),
+            downloaded: self.downloaded,
+            total,
+            bytes_per_second: if elapsed > 0.0 {
+                (self.downloaded as f64 / elapsed) as u64
+            } else {
+                0
+            },
+        };
+        tray::show_update_progress(&self.app, &progress);
+        if let Err(e) = progress.emit(&self.app) {
+            log::warn!("Failed to emit update progress: {}", e);
+        }
+    }
+
+    /// Puts the runtime status back in the tray tooltip.
+    pub fn finish(self) {
+        tray::set_status(&self.app, runtime::status(&self.app));
+    }
+}
+
+/// The last update that failed, kept for the diagnostics.
+#[derive(Default)]
+pub struct LastUpdateFailure(Mutex<Option<RecordedFailure>>);
+
+struct RecordedFailure {
+    version: String,
+    error: UpdateError,
+    /// Seconds since the unix epoch.
+    at: u64,
+}
+
+impl LastUpdateFailure {
+    /// Logs the failure, remembers it and tells the webview.
+    pub fn record(&self, app: &AppHandle, version: &str, error: &UpdateError) {
+        log::error!("Failed to update to {}: {}", version, error);
+        *self.0.lock().unwrap() = Some(RecordedFailure {
+            version: version.to_string(),
+            error: error.clone(),
+            at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        });
+        let event = UpdateFailed {
+            version: version.to_string(),
+            error: error.clone(),
+        };
+        if let Err(e) = event.emit(app) {
+            log::warn!("Failed to emit update failure: {}", e);
+        }
+    }
+
+    /// Like "1.2.0 at 1729300000: network error: …".
+    pub fn describe(&self) -> Option<String> {
+        let failure = self.0.lock().unwrap();
+        failure
+            .as_ref()
+            .map(|failure| format!("{} at {}: {}", failure.version, failure.at, failure.error))
+    }
+}
@@ -0,0 +1,162 @@
+//! The update window: shows a release with its notes and lets the user
+//! install it now, when the app quits, or skip it.
+//!
+//! Skipped versions are kept in the settings store. Silent checks, including
+//! the background downloads, pass over them, a check the user asked for
+//! still offers them.
+
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder, WindowEvent};
+use tauri_plugin_updater::Update;
+use tauri_specta::Event;
+
+use super::{background, install_now, is_downgrade, Channel};
+use crate::cmd::CommandError;
+use crate::settings::Settings;
+
+pub const LABEL: &str = "update";
+
+/// Store key of the list of skipped versions.
+const SKIPPED_VERSIONS: &str = "skipped_update_versions";
+
+/// The release the update window shows, as sent to it.
+#[derive(Debug, Clone, Serialize, Deserialize, specta::Type, tauri_specta::Event)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateOffer {
+    pub version: String,
+    pub current_version: String,
+    pub channel: String,
+    /// The release notes from the manifest, usually markdown.
+    pub notes: Option<String>,
+    /// The publication date, `YYYY-MM-DD`.
+    pub date: Option<String>,
+    /// Installing it replaces this version with an older one.
+    pub downgrade: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub enum UpdateAction {
+    InstallNow,
+    InstallOnQuit,
+    Skip,
+}
+
+struct Offered {
+    update: Update,
+    channel: Channel,
+    offer: UpdateOffer,
+}
+
+/// The release shown in the update window, if it is open.
+#[derive(Default)]
+pub struct OfferedUpdate(Mutex<Option<Offered>>);
+
+/// Shows `update` in the update window, replacing whatever it showed.
+pub fn offer(app: &AppHandle, update: Update, channel: Channel) -> Result<(), CommandError> {
+    let offer = UpdateOffer {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        channel: channel.to_string(),
+        notes: update.body.clone().filter(|notes| !notes.trim().is_empty()),
+        date: update.date.map(|date| date.date().to_string()),
+        downgrade: is_downgrade(&update.current_version, &update.version),
+    };
+    *app.state::<OfferedUpdate>().0.lock().unwrap() = Some(Offered {
+        update,
+        channel,
+        offer: offer.clone(),
+    });
+
+    let window = match app.get_webview_window(LABEL) {
+        Some(window) => {
+            // Already loaded, it won't ask for the offer again.
+            offer.emit(app)?;
+            window
+        }
+        None => {
+            let url = WebviewUrl::App("update.html".into());
+            let window = WebviewWindowBuilder::new(app, LABEL, url)
+                .title("Software Update")
+                .inner_size(520.0, 460.0)
+                .min_inner_size(420.0, 320.0)
+                .visible(false)
+                .build()?;
+            let app_handle = app.clone();
+            window.on_window_event(move |event| {
+                // Closing the window decides nothing, the next check offers
+                // the release again.
+                if let WindowEvent::Destroyed = event {
+                    app_handle.state::<OfferedUpdate>().0.lock().unwrap().take();
+                }
+            });
+            window
+        }
+    };
+    window.center()?;
+    window.show()?;
+    window.set_focus()?;
+    Ok(())
+}
+
+/// Whether the user chose to skip `version`.
+pub fn is_skipped(app: &AppHandle, version: &str) -> bool {
+    skipped_versions(app)
+        .iter()
+        .any(|skipped| skipped == version)
+}
+
+fn skipped_versions(app: &AppHandle) -> Vec<String> {
+    app.state::<Settings>()
+        .get_state(SKIPPED_VERSIONS)
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+fn skip(app: &AppHandle, version: &str) -> Result<(), CommandError> {
+    let mut skipped = skipped_versions(app);
+    if !skipped.iter().any(|skipped| skipped == version) {
+        skipped.push(version.to_string());
+    }
+    app.state::<Settings>()
+        .set_state(SKIPPED_VERSIONS, JsonValue::from(skipped))?;
+    log::info!("skipping update {}", version);
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn get_update_offer(offered: tauri::State<'_, OfferedUpdate>) -> Option<UpdateOffer> {
+    let offered = offered.0.lock().unwrap();
+    offered.as_ref().map(|offered| offered.offer.clone())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn respond_to_update(app_handle: AppHandle, action: UpdateAction) -> Result<(), CommandError> {
+    let Some(Offered {
+        update, channel, ..
+    }) = app_handle.state::<OfferedUpdate>().0.lock().unwrap().take()
+    else {
+        return Err(CommandError::NotFound(
+            "no update is being offered".to_string(),
+        ));
+    };
+    if let Some(window) = app_handle.get_webview_window(LABEL) {
+        window.close()?;
+    }
+
+    match action {
+        UpdateAction::InstallNow => install_now(app_handle, update),
+        UpdateAction::InstallOnQuit => {
+            tauri::async_runtime::spawn(async move {
+                background::download_for_quit(&app_handle, update, channel).await;
+            });
+        }
+        UpdateAction::Skip => skip(&app_handle, &update.version)?,
+    }
+    Ok(())
+}
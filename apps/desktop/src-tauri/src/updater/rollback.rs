@@ -0,0 +1,384 @@
+//! Keeps the installation an update replaces, and puts it back if the new
+//! version can't get its runtime healthy on first launch.
+//!
+//! Everything lives in `updates/` in the app data directory:
+//! - `previous/` is a copy of the installation from before the last update,
+//!   described by `previous.json`.
+//! - `pending-check.json` exists between installing an update and the first
+//!   launch of the new version.
+//! - `rolled-back.json` names the last version that was rolled back, so
+//!   background checks don't install it again.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+
+use crate::json_file;
+use crate::runtime;
+
+const UPDATES_DIR: &str = "updates";
+const PREVIOUS_DIR: &str = "previous";
+const PREVIOUS_FILE: &str = "previous.json";
+const PENDING_CHECK_FILE: &str = "pending-check.json";
+const ROLLED_BACK_FILE: &str = "rolled-back.json";
+
+/// How long the new version gets to report a healthy runtime, including the
+/// runtime's own startup grace period.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Suffix of files moved aside while restoring, they may still be in use.
+const REPLACED_SUFFIX: &str = "rollback-old";
+
+/// The backed up installation.
+#[derive(Debug, Serialize, Deserialize)]
+struct Previous {
+    version: String,
+    /// Where it was installed, and is restored to.
+    path: PathBuf,
+}
+
+/// An update that was installed but hasn't been launched yet.
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingCheck {
+    from: String,
+    to: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RolledBack {
+    version: String,
+}
+
+/// Copies the running installation aside so an update can be rolled back.
+/// This takes a while for a large installation, so updates installed on quit
+/// are backed up as soon as they are downloaded. Builds that can't be
+/// located, like dev builds, aren't backed up.
+pub fn backup(app: &AppHandle) -> io::Result<()> {
+    let Some(install_path) = install_path() else {
+        log::warn!("not backing up the installation, its location is unknown");
+        return Ok(());
+    };
+    let dir = updates_dir(app)?;
+    let previous = dir.join(PREVIOUS_DIR);
+    remove_all(&previous)?;
+    fs::create_dir_all(&dir)?;
+    copy_all(&install_path, &previous)?;
+
+    let version = app.package_info().version.to_string();
+    json_file::write(
+        &dir.join(PREVIOUS_FILE),
+        &Previous {
+            version: version.clone(),
+            path: install_path,
+        },
+    )?;
+    log::info!("backed up the installation of {}", version);
+    Ok(())
+}
+
+/// Has the first launch of `to` checked, right before it is installed. Only
+/// a backup of the running version can be restored, without one the update
+/// is installed unchecked.
+pub fn expect_update(app: &AppHandle, to: &str) -> io::Result<()> {
+    let dir = updates_dir(app)?;
+    let from = app.package_info().version.to_string();
+    match json_file::read::<Previous>(&dir.join(PREVIOUS_FILE)) {
+        Ok(previous) if previous.version == from => {}
+        Ok(_) => return Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    }
+    json_file::write(
+        &dir.join(PENDING_CHECK_FILE),
+        &PendingCheck {
+            from,
+            to: to.to_string(),
+        },
+    )
+}
+
+/// The last version that was rolled back, background checks skip it.
+pub fn rolled_back_version(app: &AppHandle) -> Option<String> {
+    updates_dir(app)
+        .and_then(|dir| json_file::read::<RolledBack>(&dir.join(ROLLED_BACK_FILE)))
+        .map(|rolled_back| rolled_back.version)
+        .ok()
+}
+
+/// On the first launch after an update, waits for the runtime to become
+/// healthy and rolls back if it doesn't.
+pub fn spawn_health_check(app: &AppHandle) {
+    let Ok(dir) = updates_dir(app) else {
+        return;
+    };
+    let marker = dir.join(PENDING_CHECK_FILE);
+    let Ok(check) = json_file::read::<PendingCheck>(&marker) else {
+        return;
+    };
+    // The update didn't take, or this is the restored version starting.
+    let current = app.package_info().version.to_string();
+    if check.to != current {
+        if let Err(e) = fs::remove_file(&marker) {
+            log::warn!("Failed to remove {}: {}", marker.display(), e);
+        }
+        return;
+    }
+
+    log::info!(
+        "first launch after updating from {}, checking the runtime",
+        check.from
+    );
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if runtime::wait_until_healthy(&app, HEALTH_CHECK_TIMEOUT).await {
+            log::info!("update to {} is healthy", check.to);
+            if let Err(e) = fs::remove_file(&marker) {
+                log::warn!("Failed to remove {}: {}", marker.display(), e);
+            }
+            return;
+        }
+        log::error!(
+            "runtime is {} after updating to {}, rolling back",
+            runtime::status(&app),
+            check.to
+        );
+        roll_back(&app, &dir, &check);
+    });
+}
+
+fn roll_back(app: &AppHandle, dir: &Path, check: &PendingCheck) {
+    let result = json_file::read::<Previous>(&dir.join(PREVIOUS_FILE)).and_then(|previous| {
+        runtime::kill_sidecar_process(app);
+        restore(&dir.join(PREVIOUS_DIR), &previous.path)?;
+        Ok(previous)
+    });
+    // Even if restoring failed, don't try again on every launch.
+    let _ = fs::remove_file(dir.join(PENDING_CHECK_FILE));
+    if let Err(e) = json_file::write(
+        &dir.join(ROLLED_BACK_FILE),
+        &RolledBack {
+            version: check.to.clone(),
+        },
+    ) {
+        log::warn!("Failed to remember the rolled back version: {}", e);
+    }
+
+    match result {
+        Ok(previous) => {
+            log::info!("restored {}", previous.version);
+            let app_handle = app.clone();
+            app.dialog()
+                .message(format!(
+                    "Craftgen {} couldn't start its runtime, so {} was restored. Craftgen will now restart.",
+                    check.to, previous.version
+                ))
+                .title("Update rolled back")
+                .kind(MessageDialogKind::Warning)
+                .show(move |_| {
+                    app_handle.restart();
+                });
+        }
+        Err(e) => {
+            log::error!("Failed to roll back to {}: {}", check.from, e);
+            app.dialog()
+                .message(format!(
+                    "Craftgen {} couldn't start its runtime, and restoring {} failed: {}\n\nPlease reinstall Craftgen {} from craftgen.ai.",
+                    check.to, check.from, e, check.from
+                ))
+                .title("Update failed")
+                .kind(MessageDialogKind::Error)
+                .show(|_| {});
+        }
+    }
+}
+
+fn updates_dir(app: &AppHandle) -> io::Result<PathBuf> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join(UPDATES_DIR))
+        .map_err(|e| io::Error::new(io::ErrorKind::NotFound, e.to_string()))
+}
+
+/// What the updater replaces: the `.app` bundle on macOS, the AppImage on
+/// Linux and the install directory on Windows.
+pub(super) fn install_path() -> Option<PathBuf> {
+    if cfg!(debug_assertions) {
+        return None;
+    }
+    #[cfg(target_os = "linux")]
+    {
+        std::env::var_os("APPIMAGE").map(PathBuf::from)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let exe = std::env::current_exe().ok()?;
+        exe.ancestors()
+            .find(|path| path.extension().is_some_and(|ext| ext == "app"))
+            .map(Path::to_path_buf)
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let exe = std::env::current_exe().ok()?;
+        exe.parent().map(Path::to_path_buf)
+    }
+}
+
+/// Copies a file or directory tree, keeping symlinks as symlinks.
+fn copy_all(from: &Path, to: &Path) -> io::Result<()> {
+    let file_type = fs::symlink_metadata(from)?.file_type();
+    if file_type.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_all(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else if file_type.is_symlink() {
+        copy_symlink(from, to)?;
+    } else {
+        fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+/// Copies the backup, or an unpacked update, over the installation file by
+/// file, and removes what isn't in it. Files that are in use, like the
+/// running executable, can't be overwritten on Windows but can be renamed, so
+/// every existing file is moved aside first.
+pub(super) fn restore(from: &Path, to: &Path) -> io::Result<()> {
+    let file_type = fs::symlink_metadata(from)?.file_type();
+    if file_type.is_dir() {
+        if fs::symlink_metadata(to).is_ok_and(|metadata| !metadata.is_dir()) {
+            move_aside(to)?;
+        }
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            restore(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        for entry in fs::read_dir(to)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if fs::symlink_metadata(from.join(&name)).is_ok() {
+                continue;
+            }
+            match name.to_string_lossy().ends_with(REPLACED_SUFFIX) {
+                true => discard(&entry.path()),
+                false => discard(&move_aside(&entry.path())?),
+            }
+        }
+        return Ok(());
+    }
+
+    let replaced = match fs::symlink_metadata(to) {
+        Ok(_) => Some(move_aside(to)?),
+        Err(_) => None,
+    };
+    if file_type.is_symlink() {
+        copy_symlink(from, to)?;
+    } else {
+        fs::copy(from, to)?;
+    }
+    if let Some(replaced) = replaced {
+        discard(&replaced);
+    }
+    Ok(())
+}
+
+/// Renames `path` out of the way, replacing what an earlier restore left.
+fn move_aside(path: &Path) -> io::Result<PathBuf> {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(REPLACED_SUFFIX);
+    let replaced = PathBuf::from(name);
+    remove_all(&replaced)?;
+    fs::rename(path, &replaced)?;
+    Ok(replaced)
+}
+
+/// Removes a file moved aside. Fails for files still in use, they are only
+/// wasted space.
+fn discard(replaced: &Path) {
+    let _ = remove_all(replaced);
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(from: &Path, to: &Path) -> io::Result<()> {
+    fs::copy(from, to).map(|_| ())
+}
+
+fn remove_all(path: &Path) -> io::Result<()> {
+    let result = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) => Err(e),
+    };
+    match result {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(root: &Path) -> Vec<(String, String)> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(root).unwrap() {
+            let path = entry.unwrap().path();
+            let name = path
+                .strip_prefix(root)
+                .unwrap()
+                .to_string_lossy()
+                .into_owned();
+            if path.is_dir() {
+                files.extend(
+                    tree(&path)
+                        .into_iter()
+                        .map(|(file, contents)| (format!("{}/{}", name, file), contents)),
+                );
+            } else {
+                files.push((name, fs::read_to_string(&path).unwrap()));
+            }
+        }
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn restores_the_backup_exactly() {
+        let root = tempfile::tempdir().unwrap();
+        let (backup, install) = (root.path().join("previous"), root.path().join("Craftgen"));
+        for (dir, files) in [
+            (&backup, &[("app", "1.0"), ("lib/a", "1.0")][..]),
+            (
+                &install,
+                &[
+                    ("app", "1.1"),
+                    ("lib/a", "1.1"),
+                    ("lib/b", "1.1"),
+                    ("plugins/c", "1.1"),
+                ][..],
+            ),
+        ] {
+            for (file, contents) in files {
+                let path = dir.join(file);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, contents).unwrap();
+            }
+        }
+
+        restore(&backup, &install).unwrap();
+        assert_eq!(tree(&install), tree(&backup));
+    }
+}
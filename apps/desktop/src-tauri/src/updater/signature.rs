@@ -0,0 +1,110 @@
+//! Minisign signatures made with the updater's key, for downloads the Tauri
+//! updater doesn't handle itself.
+
+use base64::Engine;
+use minisign_verify::{PublicKey, Signature};
+use tauri::AppHandle;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SignatureError {
+    #[error("no updater public key is configured")]
+    NoPublicKey,
+    #[error("invalid public key: {0}")]
+    PublicKey(String),
+    #[error("invalid signature: {0}")]
+    Malformed(String),
+    #[error("signature doesn't match: {0}")]
+    Mismatch(minisign_verify::Error),
+}
+
+/// `plugins.updater.pubkey` from `tauri.conf.json`.
+pub fn public_key(app: &AppHandle) -> Result<String, SignatureError> {
+    app.config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|updater| updater.get("pubkey"))
+        .and_then(|pubkey| pubkey.as_str())
+        .map(str::to_string)
+        .ok_or(SignatureError::NoPublicKey)
+}
+
+/// Checks `data` against a signature as written by `tauri signer sign`. Both
+/// the key and the signature are the base64 encoded minisign files.
+pub fn verify(public_key: &str, data: &[u8], signature: &str) -> Result<(), SignatureError> {
+    let public_key = decode(public_key)
+        .and_then(|key| PublicKey::decode(&key).map_err(|e| e.to_string()))
+        .map_err(SignatureError::PublicKey)?;
+    let signature = decode(signature)
+        .and_then(|signature| Signature::decode(&signature).map_err(|e| e.to_string()))
+        .map_err(SignatureError::Malformed)?;
+    public_key
+        .verify(data, &signature, true)
+        .map_err(SignatureError::Mismatch)
+}
+
+fn decode(base64: &str) -> Result<String, String> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(base64.trim())
+        .map_err(|e| e.to_string())?;
+    String::from_utf8(bytes).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::PUBLIC_KEY;
+
+    /// Signatures in the format `tauri signer` writes, made with the test key.
+    const SIGNATURE: &str = concat!(
+        "dW50cnVzdGVkIGNvbW1lbnQ6IHNpZ25hdHVyZSBmcm9tIHRhdXJpIHNlY3JldCBrZXkKUlVRQkFn",
+        "TUVCUVlIQ0xqWTBIK0xoWis1SkllMU1XQzZFUUtnZm1sMnBUZS9ycVlrR1N0SjhUZGlFZFc0N3JV",
+        "cmcrS0pwM1Vxdmp1c3htckdmcVhIRmJjbFQ5NzhSZ0EyeUFvPQp0cnVzdGVkIGNvbW1lbnQ6IHRp",
+        "bWVzdGFtcDoxNzAwMDAwMDAwCWZpbGU6ZnVuY3Rpb25zLnRhci5negowckFlbVhvRmsyaVVIZW1Y",
+        "S3NValF0RFpvV2J0bHNHUkxiUnhFM3U2MzgvZFdmWVpnN0Y3d3A3UWJ1Tkp3Nk1ERU4wcEc3NXNj",
+        "ZE1qZEJ3MVpKNzlCUT09Cg==",
+    );
+    /// Made with another key that has the same key id.
+    const OTHER_SIGNATURE: &str = concat!(
+        "dW50cnVzdGVkIGNvbW1lbnQ6IHNpZ25hdHVyZSBmcm9tIHRhdXJpIHNlY3JldCBrZXkKUlVRQkFn",
+        "TUVCUVlIQ0E1WmhmaVd0d0hqOWlleVZjN2xiTlgyemhwbmpWcWIvb1JFamFaRmE5MWREdXEvSXdJ",
+        "aWdQcm9WNytQMFFCblg0QXFkTWMzMkJsVmFLY0Q4TXh6NkFBPQp0cnVzdGVkIGNvbW1lbnQ6IHRp",
+        "bWVzdGFtcDoxNzAwMDAwMDAwCWZpbGU6ZnVuY3Rpb25zLnRhci5negpjbHBHeXJEMXRyVnlsQTNy",
+        "QWVZZlU3SytubHluUEEwRHBPS05obmlzNDFxZjJwN1J1bWZzQWlNTnpKS3FWaFJycDFxVW1MbUFi",
+        "UThCU0xCbWt5NGFCZz09Cg==",
+    );
+    const DATA: &[u8] = b"craftgen functions";
+
+    #[test]
+    fn accepts_a_valid_signature() {
+        verify(PUBLIC_KEY, DATA, SIGNATURE).unwrap();
+    }
+
+    #[test]
+    fn rejects_a_signature_by_another_key() {
+        assert!(matches!(
+            verify(PUBLIC_KEY, DATA, OTHER_SIGNATURE),
+            Err(SignatureError::Mismatch(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_tampered_data() {
+        assert!(matches!(
+            verify(PUBLIC_KEY, b"craftgen functionz", SIGNATURE),
+            Err(SignatureError::Mismatch(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(matches!(
+            verify(PUBLIC_KEY, DATA, "not a signature"),
+            Err(SignatureError::Malformed(_))
+        ));
+        assert!(matches!(
+            verify(SIGNATURE, DATA, SIGNATURE),
+            Err(SignatureError::PublicKey(_))
+        ));
+    }
+}
@@ -0,0 +1,405 @@
+//! Remembers each window's size, position, maximized state and monitor by
+//! label, or by a key that windows of one kind share, in `window-state.json`
+//! in the app data directory.
+//!
+//! Moves and resizes are written shortly after they settle, and everything
+//! once more when the app exits. A restored window is fitted onto the
+//! monitors that are connected now, so it never opens off-screen after a
+//! display was unplugged or rearranged.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, Manager, Monitor, PhysicalPosition, PhysicalSize, WebviewWindow, WindowEvent,
+};
+
+use crate::json_file;
+
+const STATE_FILE: &str = "window-state.json";
+
+/// How long moves and resizes have to settle before they are written.
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+/// How much of a window's title bar has to be on a monitor to grab it.
+const MIN_VISIBLE_WIDTH: i32 = 100;
+const MIN_VISIBLE_HEIGHT: i32 = 30;
+
+/// A rectangle in physical pixels, in the desktop's coordinate space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Bounds {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+impl Bounds {
+    fn of_monitor(monitor: &Monitor) -> Self {
+        Self {
+            x: monitor.position().x,
+            y: monitor.position().y,
+            width: monitor.size().width,
+            height: monitor.size().height,
+        }
+    }
+
+    fn right(&self) -> i32 {
+        self.x + self.width as i32
+    }
+
+    fn bottom(&self) -> i32 {
+        self.y + self.height as i32
+    }
+
+    /// Whether enough of the title bar is on `monitor` to move the window.
+    fn grabbable_on(&self, monitor: &Bounds) -> bool {
+        let title_bar_bottom = self.y + MIN_VISIBLE_HEIGHT.min(self.height as i32);
+        let width = self.right().min(monitor.right()) - self.x.max(monitor.x);
+        let height = title_bar_bottom.min(monitor.bottom()) - self.y.max(monitor.y);
+        width >= MIN_VISIBLE_WIDTH.min(self.width as i32) && height > 0
+    }
+
+    /// Shrinks to fit on `monitor` and moves inside it.
+    fn fit_into(&self, monitor: &Bounds) -> Self {
+        let width = self.width.min(monitor.width);
+        let height = self.height.min(monitor.height);
+        Self {
+            x: self.x.clamp(monitor.x, monitor.right() - width as i32),
+            y: self.y.clamp(monitor.y, monitor.bottom() - height as i32),
+            width,
+            height,
+        }
+    }
+
+    /// Shrinks to fit on `monitor` and centers on it.
+    fn center_on(&self, monitor: &Bounds) -> Self {
+        let width = self.width.min(monitor.width);
+        let height = self.height.min(monitor.height);
+        Self {
+            x: monitor.x + (monitor.width - width) as i32 / 2,
+            y: monitor.y + (monitor.height - height) as i32 / 2,
+            width,
+            height,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WindowState {
+    /// The bounds when not maximized, kept while maximized so that
+    /// unmaximizing after a restart goes back to them.
+    bounds: Bounds,
+    maximized: bool,
+    monitor: Option<String>,
+}
+
+/// Where `bounds` should open with the monitors connected now: on the monitor
+/// it was saved on or any other it is still reachable on, otherwise centered
+/// on `fallback`.
+fn place(
+    bounds: Bounds,
+    saved_monitor: Option<&str>,
+    monitors: &[(Option<String>, Bounds)],
+    fallback: Option<Bounds>,
+) -> Option<Bounds> {
+    let saved = monitors
+        .iter()
+        .filter(|(name, _)| name.is_some() && name.as_deref() == saved_monitor);
+    if let Some((_, monitor)) = saved
+        .chain(monitors.iter())
+        .find(|(_, monitor)| bounds.grabbable_on(monitor))
+    {
+        return Some(bounds.fit_into(monitor));
+    }
+    let fallback = fallback.or_else(|| monitors.first().map(|(_, monitor)| *monitor))?;
+    Some(bounds.center_on(&fallback))
+}
+
+/// The saved state of every window by label, written back to disk shortly
+/// after each change.
+pub struct WindowStates {
+    path: PathBuf,
+    states: Mutex<HashMap<String, WindowState>>,
+    /// Bumped on every change, a pending write only goes ahead if nothing
+    /// changed after it was scheduled.
+    generation: AtomicU64,
+}
+
+impl WindowStates {
+    pub fn load(app: &AppHandle) -> tauri::Result<Self> {
+        let path = app.path().app_data_dir()?.join(STATE_FILE);
+        let states = match json_file::read(&path) {
+            Ok(states) => states,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                log::warn!("Failed to read {}: {}", path.display(), e);
+                HashMap::new()
+            }
+        };
+        Ok(Self {
+            path,
+            states: Mutex::new(states),
+            generation: AtomicU64::new(0),
+        })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        json_file::write(&self.path, &*self.states.lock().unwrap())
+    }
+
+    fn get(&self, label: &str) -> Option<WindowState> {
+        self.states.lock().unwrap().get(label).cloned()
+    }
+
+    /// Records the window's current state under `key` and schedules a write.
+    fn update(&self, window: &WebviewWindow, key: &str) {
+        // Minimized windows report bogus positions on Windows, and the
+        // window is restored to where it was before anyway.
+        if window.is_minimized().unwrap_or(false) {
+            return;
+        }
+        let maximized = window.is_maximized().unwrap_or(false);
+        let monitor = window
+            .current_monitor()
+            .ok()
+            .flatten()
+            .and_then(|monitor| monitor.name().cloned());
+        let bounds = match (window.outer_position(), window.inner_size()) {
+            (Ok(position), Ok(size)) if size.width > 0 && size.height > 0 => Bounds {
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+            },
+            _ => return,
+        };
+
+        {
+            let mut states = self.states.lock().unwrap();
+            let previous = states.get(key).map(|state| state.bounds);
+            states.insert(
+                key.to_string(),
+                WindowState {
+                    bounds: match (maximized, previous) {
+                        (true, Some(previous)) => previous,
+                        _ => bounds,
+                    },
+                    maximized,
+                    monitor,
+                },
+            );
+        }
+
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let app = window.app_handle().clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(SAVE_DELAY).await;
+            let states = app.state::<WindowStates>();
+            if states.generation.load(Ordering::SeqCst) == generation {
+                if let Err(e) = states.save() {
+                    log::warn!("Failed to save window state: {}", e);
+                }
+            }
+        });
+    }
+}
+
+/// Restores the window's saved state and keeps it up to date from now on.
+/// Call it right after building the window, before showing it.
+pub fn track(window: &WebviewWindow) {
+    track_as(window, window.label());
+}
+
+/// Like [`track`], but saves the state under `key` instead of the label, for
+/// windows whose labels don't repeat, so the file doesn't grow with each one.
+pub fn track_as(window: &WebviewWindow, key: &str) {
+    if let Err(e) = restore(window, key) {
+        log::warn!("Failed to restore window {}: {}", window.label(), e);
+    }
+    let handle = window.clone();
+    let key = key.to_string();
+    window.on_window_event(move |event| match event {
+        WindowEvent::Moved(_) | WindowEvent::Resized(_) | WindowEvent::CloseRequested { .. } => {
+            handle.state::<WindowStates>().update(&handle, &key)
+        }
+        _ => {}
+    });
+}
+
+fn restore(window: &WebviewWindow, key: &str) -> tauri::Result<()> {
+    let Some(state) = window.state::<WindowStates>().get(key) else {
+        return Ok(());
+    };
+    let monitors: Vec<_> = window
+        .available_monitors()?
+        .iter()
+        .map(|monitor| (monitor.name().cloned(), Bounds::of_monitor(monitor)))
+        .collect();
+    let primary = window
+        .primary_monitor()?
+        .map(|monitor| Bounds::of_monitor(&monitor));
+    let Some(bounds) = place(state.bounds, state.monitor.as_deref(), &monitors, primary) else {
+        return Ok(());
+    };
+    if bounds != state.bounds {
+        log::info!(
+            "window {} didn't fit the connected monitors, moved to {:?}",
+            window.label(),
+            bounds
+        );
+    }
+
+    window.set_size(PhysicalSize::new(bounds.width, bounds.height))?;
+    window.set_position(PhysicalPosition::new(bounds.x, bounds.y))?;
+    if state.maximized {
+        window.maximize()?;
+    }
+    Ok(())
+}
+
+/// Writes every window's state, on exit.
+pub fn save(app: &AppHandle) {
+    if let Some(states) = app.try_state::<WindowStates>() {
+        if let Err(e) = states.save() {
+            log::warn!("Failed to save window state: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(x: i32, y: i32, width: u32, height: u32) -> Bounds {
+        Bounds {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn monitor(name: &str, bounds: Bounds) -> (Option<String>, Bounds) {
+        (Some(name.to_string()), bounds)
+    }
+
+    const PRIMARY: Bounds = Bounds {
+        x: 0,
+        y: 0,
+        width: 1920,
+        height: 1080,
+    };
+    /// To the left of and above the primary monitor.
+    const SECONDARY: Bounds = Bounds {
+        x: -2560,
+        y: -360,
+        width: 2560,
+        height: 1440,
+    };
+
+    #[test]
+    fn grabbable_only_by_enough_of_the_title_bar() {
+        assert!(bounds(100, 100, 800, 600).grabbable_on(&PRIMARY));
+        // Everything but the title bar off-screen.
+        assert!(bounds(100, 1060, 800, 600).grabbable_on(&PRIMARY));
+        assert!(bounds(1820, 100, 800, 600).grabbable_on(&PRIMARY));
+        // Only the title bar off-screen.
+        assert!(!bounds(100, -40, 800, 600).grabbable_on(&PRIMARY));
+        assert!(!bounds(100, 1080, 800, 600).grabbable_on(&PRIMARY));
+        assert!(!bounds(1840, 100, 800, 600).grabbable_on(&PRIMARY));
+        assert!(bounds(-1000, -300, 800, 600).grabbable_on(&SECONDARY));
+        assert!(!bounds(-1000, -300, 800, 600).grabbable_on(&PRIMARY));
+    }
+
+    #[test]
+    fn fits_into_a_monitor() {
+        assert_eq!(
+            bounds(100, 100, 800, 600).fit_into(&PRIMARY),
+            bounds(100, 100, 800, 600)
+        );
+        assert_eq!(
+            bounds(1500, -20, 800, 600).fit_into(&PRIMARY),
+            bounds(1120, 0, 800, 600)
+        );
+        assert_eq!(
+            bounds(-2600, -400, 800, 600).fit_into(&SECONDARY),
+            bounds(-2560, -360, 800, 600)
+        );
+        // Larger than the monitor.
+        assert_eq!(bounds(-100, 50, 3000, 2000).fit_into(&PRIMARY), PRIMARY);
+    }
+
+    #[test]
+    fn centers_on_a_monitor() {
+        assert_eq!(
+            bounds(5000, 5000, 800, 600).center_on(&PRIMARY),
+            bounds(560, 240, 800, 600)
+        );
+        assert_eq!(
+            bounds(0, 0, 800, 600).center_on(&SECONDARY),
+            bounds(-1680, 60, 800, 600)
+        );
+        assert_eq!(bounds(0, 0, 3000, 2000).center_on(&PRIMARY), PRIMARY);
+    }
+
+    #[test]
+    fn places_on_the_saved_monitor_first() {
+        // Overlaps both monitors, the saved one wins.
+        let window = bounds(-400, 100, 800, 600);
+        let monitors = [monitor("primary", PRIMARY), monitor("secondary", SECONDARY)];
+        assert_eq!(
+            place(window, Some("secondary"), &monitors, Some(PRIMARY)),
+            Some(bounds(-800, 100, 800, 600))
+        );
+        assert_eq!(
+            place(window, Some("primary"), &monitors, Some(PRIMARY)),
+            Some(bounds(0, 100, 800, 600))
+        );
+        // Unnamed monitors never match the saved one.
+        let unnamed = [(None, PRIMARY), (None, SECONDARY)];
+        assert_eq!(
+            place(window, None, &unnamed, Some(PRIMARY)),
+            Some(bounds(0, 100, 800, 600))
+        );
+    }
+
+    #[test]
+    fn places_a_window_from_an_unplugged_monitor_on_the_fallback() {
+        let window = bounds(-1000, -300, 800, 600);
+        let monitors = [monitor("primary", PRIMARY)];
+        assert_eq!(
+            place(window, Some("secondary"), &monitors, Some(PRIMARY)),
+            Some(bounds(560, 240, 800, 600))
+        );
+        assert_eq!(
+            place(window, Some("secondary"), &monitors, None),
+            Some(bounds(560, 240, 800, 600))
+        );
+        assert_eq!(place(window, Some("secondary"), &[], None), None);
+    }
+
+    #[test]
+    fn places_a_window_with_only_its_title_bar_off_screen() {
+        let window = bounds(100, -40, 800, 600);
+        assert_eq!(
+            place(
+                window,
+                Some("primary"),
+                &[monitor("primary", PRIMARY)],
+                Some(PRIMARY)
+            ),
+            Some(bounds(560, 240, 800, 600))
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! Secondary windows, one per workflow or execution, next to the main window.
+//!
+//! Each window's label is derived from what it shows,
+//! `workflow:<project>/<slug>` or `execution:<id>`, so opening the same
+//! target twice focuses the window that is already open. Open windows are
+//! tracked in [`OpenWindows`] until they are destroyed. They only get the
+//! permissions in `capabilities/secondary.json`, which matches their label
+//! prefixes.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindowBuilder, WindowEvent};
+
+use crate::cmd::CommandError;
+use crate::window_state;
+
+const WORKFLOW_PREFIX: &str = "workflow:";
+const EXECUTION_PREFIX: &str = "execution:";
+/// Every execution gets a new window, they all open where the last one was.
+const EXECUTION_STATE_KEY: &str = "execution";
+
+/// What a secondary window shows.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, specta::Type)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum WindowTarget {
+    #[serde(rename_all = "camelCase")]
+    Workflow {
+        project_slug: String,
+        workflow_slug: String,
+    },
+    /// An execution, shown on the route of the module it ran with its id in
+    /// the `executionId` search param.
+    #[serde(rename_all = "camelCase")]
+    Execution {
+        id: String,
+        project_slug: String,
+        module_name: String,
+    },
+}
+
+impl WindowTarget {
+    fn label(&self) -> String {
+        match self {
+            WindowTarget::Workflow {
+                project_slug,
+                workflow_slug,
+            } => format!("{}{}/{}", WORKFLOW_PREFIX, project_slug, workflow_slug),
+            WindowTarget::Execution { id, .. } => format!("{}{}", EXECUTION_PREFIX, id),
+        }
+    }
+
+    fn path(&self) -> String {
+        match self {
+            WindowTarget::Workflow {
+                project_slug,
+                workflow_slug,
+            } => format!("/{}/{}", project_slug, workflow_slug),
+            WindowTarget::Execution {
+                id,
+                project_slug,
+                module_name,
+            } => format!("/{}/{}?executionId={}", project_slug, module_name, id),
+        }
+    }
+
+    fn title(&self) -> String {
+        match self {
+            WindowTarget::Workflow {
+                project_slug,
+                workflow_slug,
+            } => format!("{}/{} — Craftgen", project_slug, workflow_slug),
+            WindowTarget::Execution {
+                id,
+                project_slug,
+                module_name,
+            } => format!(
+                "{}/{} execution {} — Craftgen",
+                project_slug, module_name, id
+            ),
+        }
+    }
+
+    /// Labels may only contain `a-zA-Z0-9-/:_`, and the parts end up in the
+    /// route as well.
+    fn validate(&self) -> Result<(), CommandError> {
+        let parts = match self {
+            WindowTarget::Workflow {
+                project_slug,
+                workflow_slug,
+            } => vec![project_slug, workflow_slug],
+            WindowTarget::Execution {
+                id,
+                project_slug,
+                module_name,
+            } => vec![id, project_slug, module_name],
+        };
+        let valid = |part: &&String| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        };
+        match parts.iter().find(|part| !valid(part)) {
+            Some(part) => Err(CommandError::InvalidInput(format!(
+                "{:?} may only contain letters, digits, `-` and `_`",
+                part
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// An open secondary window, as listed to the frontend.
+#[derive(Debug, Clone, Serialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowInfo {
+    pub label: String,
+    pub target: WindowTarget,
+    pub title: String,
+}
+
+/// The open secondary windows by label.
+#[derive(Default)]
+pub struct OpenWindows(Mutex<BTreeMap<String, WindowTarget>>);
+
+impl OpenWindows {
+    pub fn list(&self) -> Vec<WindowInfo> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(label, target)| WindowInfo {
+                label: label.clone(),
+                target: target.clone(),
+                title: target.title(),
+            })
+            .collect()
+    }
+}
+
+/// Shows the window for `target`, focusing it if it is already open.
+pub fn open(app: &AppHandle, target: WindowTarget) -> Result<WindowInfo, CommandError> {
+    target.validate()?;
+    let label = target.label();
+    let info = WindowInfo {
+        label: label.clone(),
+        target: target.clone(),
+        title: target.title(),
+    };
+
+    #[cfg(target_os = "macos")]
+    {
+        crate::dock::set_dock_visible(true);
+    }
+
+    if let Some(window) = app.get_webview_window(&label) {
+        window.unminimize()?;
+        window.show()?;
+        window.set_focus()?;
+        return Ok(info);
+    }
+
+    let url = WebviewUrl::App(target.path().into());
+    let window = WebviewWindowBuilder::new(app, &label, url)
+        .title(&info.title)
+        .inner_size(1000.0, 700.0)
+        .min_inner_size(600.0, 400.0)
+        .visible(false)
+        .build()?;
+    match target {
+        WindowTarget::Workflow { .. } => window_state::track(&window),
+        WindowTarget::Execution { .. } => window_state::track_as(&window, EXECUTION_STATE_KEY),
+    }
+    app.state::<OpenWindows>()
+        .0
+        .lock()
+        .unwrap()
+        .insert(label.clone(), target);
+    let app_handle = app.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Destroyed = event {
+            app_handle
+                .state::<OpenWindows>()
+                .0
+                .lock()
+                .unwrap()
+                .remove(&label);
+        }
+    });
+    window.show()?;
+    window.set_focus()?;
+    log::info!("opened window {}", info.label);
+    Ok(info)
+}
+
+/// Closes a secondary window. The main window isn't managed here.
+pub fn close(app: &AppHandle, label: &str) -> Result<(), CommandError> {
+    if !app
+        .state::<OpenWindows>()
+        .0
+        .lock()
+        .unwrap()
+        .contains_key(label)
+    {
+        return Err(CommandError::NotFound(format!("no window {}", label)));
+    }
+    if let Some(window) = app.get_webview_window(label) {
+        window.close()?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn open_window(
+    app_handle: AppHandle,
+    target: WindowTarget,
+) -> Result<WindowInfo, CommandError> {
+    open(&app_handle, target)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn close_window(app_handle: AppHandle, label: String) -> Result<(), CommandError> {
+    close(&app_handle, &label)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub fn list_windows(windows: tauri::State<'_, OpenWindows>) -> Vec<WindowInfo> {
+    windows.list()
+}
@@ -0,0 +1,623 @@
+//! Export and import of the local workspace as a portable archive.
+//!
+//! An archive is a gzipped tarball that starts with `manifest.json`, followed
+//! by the installed modules, the runtime databases and the settings store.
+
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager, Runtime};
+
+use crate::cmd::CommandError;
+use crate::runtime;
+use crate::settings::{self, Settings};
+
+/// Bump when the archive layout changes and add a step to [`migrate`].
+pub const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_NAME: &str = "manifest.json";
+
+#[derive(Debug, thiserror::Error)]
+pub enum WorkspaceError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+    #[error("invalid manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+    #[error("archive has no {MANIFEST_NAME}")]
+    MissingManifest,
+    #[error("unsupported archive format version {0}, this build supports up to {FORMAT_VERSION}")]
+    UnsupportedVersion(u32),
+    #[error("refusing to unpack `{0}` outside of the workspace")]
+    UnsafePath(String),
+    #[error("checksum mismatch for `{0}`")]
+    Checksum(String),
+    #[error("`{0}` is listed in the manifest but missing from the archive")]
+    MissingFile(String),
+    #[error("`{0}` is in the archive but not listed in the manifest")]
+    UnlistedFile(String),
+    #[error("the current workspace is not empty, confirm to overwrite it")]
+    WouldOverwrite,
+}
+
+/// Where the parts of the workspace live on disk.
+#[derive(Debug, Clone)]
+pub struct WorkspacePaths {
+    data_dir: PathBuf,
+    /// Installed modules, exposed to the runtime as `CRAFTGEN_MODULES_DIR`. The
+    /// runtime falls back to the bundled functions in `SERVICE_BASE_DIR`.
+    pub modules: PathBuf,
+    /// KV and libsql tenant databases, exposed to the runtime as `DB_LOCATION`.
+    pub db: PathBuf,
+    /// The settings store.
+    pub settings: PathBuf,
+}
+
+impl WorkspacePaths {
+    pub fn resolve<R: Runtime>(app: &impl Manager<R>) -> tauri::Result<Self> {
+        let data_dir = app.path().app_data_dir()?;
+        Ok(Self {
+            modules: data_dir.join("modules"),
+            db: data_dir.join("db"),
+            settings: data_dir.join(settings::STORE_FILE),
+            data_dir,
+        })
+    }
+
+    pub fn ensure(&self) -> io::Result<()> {
+        fs::create_dir_all(&self.modules)?;
+        fs::create_dir_all(&self.db)
+    }
+
+    /// Archive path of each part together with its location on disk.
+    fn parts(&self) -> [(&'static str, &Path); 3] {
+        [
+            ("modules", &self.modules),
+            ("db", &self.db),
+            ("settings/app_data.bin", &self.settings),
+        ]
+    }
+
+    fn is_empty(&self) -> io::Result<bool> {
+        for (_, path) in self.parts() {
+            if path.is_file() || (path.is_dir() && fs::read_dir(path)?.next().is_some()) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format_version: u32,
+    pub app_version: String,
+    /// Seconds since the unix epoch.
+    pub created_at: u64,
+    pub files: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSummary {
+    pub path: PathBuf,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportWorkspaceRequest {
+    pub path: PathBuf,
+    /// Replace a non-empty workspace. Without it the import fails with
+    /// `confirmationRequired`.
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Clone, Serialize, specta::Type)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub files: usize,
+    pub format_version: u32,
+    pub app_version: String,
+    /// Where the replaced workspace was moved, if there was one.
+    pub backup: Option<PathBuf>,
+}
+
+pub fn export(
+    paths: &WorkspacePaths,
+    app_version: &str,
+    dest: &Path,
+) -> Result<ExportSummary, WorkspaceError> {
+    let mut files = Vec::new();
+    for (name, path) in paths.parts() {
+        collect_files(path, name.to_string(), &mut files)?;
+    }
+
+    let entries = files
+        .iter()
+        .map(|(name, path)| {
+            Ok(ManifestEntry {
+                path: name.clone(),
+                size: fs::metadata(path)?.len(),
+                sha256: sha256_file(path)?,
+            })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    let manifest = Manifest {
+        format_version: FORMAT_VERSION,
+        app_version: app_version.to_string(),
+        created_at: now(),
+        files: entries,
+    };
+
+    // Write next to the destination first so a failed export never leaves a
+    // truncated archive behind under the requested name.
+    let partial = dest.with_extension("partial");
+    let write = || -> Result<(), WorkspaceError> {
+        let encoder = GzEncoder::new(File::create(&partial)?, Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+
+        let manifest_bytes = serde_json::to_vec_pretty(&manifest)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(manifest_bytes.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(manifest.created_at);
+        header.set_cksum();
+        builder.append_data(&mut header, MANIFEST_NAME, manifest_bytes.as_slice())?;
+
+        for (name, path) in &files {
+            builder.append_path_with_name(path, name)?;
+        }
+        builder.into_inner()?.finish()?;
+        Ok(())
+    };
+    if let Err(e) = write() {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    fs::rename(&partial, dest)?;
+
+    Ok(ExportSummary {
+        path: dest.to_path_buf(),
+        files: manifest.files.len(),
+        bytes: fs::metadata(dest)?.len(),
+    })
+}
+
+pub fn import(
+    paths: &WorkspacePaths,
+    archive: &Path,
+    overwrite: bool,
+) -> Result<ImportSummary, WorkspaceError> {
+    if !overwrite && !paths.is_empty()? {
+        return Err(WorkspaceError::WouldOverwrite);
+    }
+
+    let stamp = now();
+    let staging = paths.data_dir.join(format!(".import-{}", stamp));
+    let staged = || -> Result<Manifest, WorkspaceError> {
+        unpack(archive, &staging)?;
+        let manifest_path = staging.join(MANIFEST_NAME);
+        if !manifest_path.is_file() {
+            return Err(WorkspaceError::MissingManifest);
+        }
+        let mut manifest: Manifest = serde_json::from_slice(&fs::read(&manifest_path)?)?;
+        if manifest.format_version == 0 || manifest.format_version > FORMAT_VERSION {
+            return Err(WorkspaceError::UnsupportedVersion(manifest.format_version));
+        }
+        verify(&manifest, &staging)?;
+        migrate(&mut manifest, &staging)?;
+        Ok(manifest)
+    };
+    let manifest = match staged() {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            let _ = fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+    };
+
+    let backup_dir = paths.data_dir.join(format!(".workspace-backup-{}", stamp));
+    let backup = swap_in(paths, &staging, &backup_dir);
+    // The import is done or undone either way, a leftover only takes space.
+    if let Err(e) = fs::remove_dir_all(&staging) {
+        log::warn!("Failed to remove {}: {}", staging.display(), e);
+    }
+
+    Ok(ImportSummary {
+        files: manifest.files.len(),
+        format_version: manifest.format_version,
+        app_version: manifest.app_version,
+        backup: backup?,
+    })
+}
+
+/// Moves every part of the workspace into `backup_dir` and the staged part in
+/// its place. If any move fails the ones already made are undone, so the
+/// workspace is never left half replaced. Returns the backup if anything was
+/// moved into it.
+fn swap_in(
+    paths: &WorkspacePaths,
+    staging: &Path,
+    backup_dir: &Path,
+) -> Result<Option<PathBuf>, WorkspaceError> {
+    let mut moved: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut backup = None;
+    let mut swap = || -> io::Result<()> {
+        for (name, path) in paths.parts() {
+            if path.exists() {
+                let target = backup_dir.join(name);
+                fs::create_dir_all(target.parent().unwrap_or(backup_dir))?;
+                fs::rename(path, &target)?;
+                moved.push((path.to_path_buf(), target));
+                backup = Some(backup_dir.to_path_buf());
+            }
+            let source = staging.join(name);
+            if source.exists() {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::rename(&source, path)?;
+                moved.push((source, path.to_path_buf()));
+            }
+        }
+        paths.ensure()
+    };
+    if let Err(e) = swap() {
+        for (from, to) in moved.iter().rev() {
+            if let Err(e) = fs::rename(to, from) {
+                log::error!(
+                    "Failed to move {} back to {}: {}",
+                    to.display(),
+                    from.display(),
+                    e
+                );
+            }
+        }
+        return Err(e.into());
+    }
+    Ok(backup)
+}
+
+/// Upgrade steps, where `MIGRATIONS[n]` turns a version `n + 1` archive into
+/// a version `n + 2` one.
+type Migration = fn(&mut Manifest, &Path) -> Result<(), WorkspaceError>;
+const MIGRATIONS: &[Migration] = &[];
+const _: () = assert!(MIGRATIONS.len() as u32 == FORMAT_VERSION - 1);
+
+/// Brings a verified archive written by an older build up to [`FORMAT_VERSION`].
+fn migrate(manifest: &mut Manifest, staging: &Path) -> Result<(), WorkspaceError> {
+    for step in &MIGRATIONS[(manifest.format_version - 1) as usize..] {
+        step(manifest, staging)?;
+        manifest.format_version += 1;
+    }
+    Ok(())
+}
+
+fn unpack(archive: &Path, dest: &Path) -> Result<(), WorkspaceError> {
+    fs::create_dir_all(dest)?;
+    let mut tar = tar::Archive::new(GzDecoder::new(File::open(archive)?));
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        let name = path.to_string_lossy().to_string();
+        let safe = path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        let supported = matches!(
+            entry.header().entry_type(),
+            tar::EntryType::Regular | tar::EntryType::Directory
+        );
+        if !safe || !supported || !entry.unpack_in(dest)? {
+            return Err(WorkspaceError::UnsafePath(name));
+        }
+    }
+    Ok(())
+}
+
+fn verify(manifest: &Manifest, staging: &Path) -> Result<(), WorkspaceError> {
+    for entry in &manifest.files {
+        if !entry
+            .path
+            .split('/')
+            .all(|part| !part.is_empty() && part != "." && part != "..")
+        {
+            return Err(WorkspaceError::UnsafePath(entry.path.clone()));
+        }
+        let path = staging.join(&entry.path);
+        if !path.is_file() {
+            return Err(WorkspaceError::MissingFile(entry.path.clone()));
+        }
+        if fs::metadata(&path)?.len() != entry.size || sha256_file(&path)? != entry.sha256 {
+            return Err(WorkspaceError::Checksum(entry.path.clone()));
+        }
+    }
+
+    let mut unpacked = Vec::new();
+    for child in fs::read_dir(staging)? {
+        let child = child?;
+        let name = child.file_name().to_string_lossy().to_string();
+        collect_files(&child.path(), name, &mut unpacked)?;
+    }
+    for (name, _) in unpacked {
+        if name != MANIFEST_NAME && !manifest.files.iter().any(|entry| entry.path == name) {
+            return Err(WorkspaceError::UnlistedFile(name));
+        }
+    }
+    Ok(())
+}
+
+/// Recursively lists regular files under `path`, keyed by their archive path.
+fn collect_files(path: &Path, name: String, files: &mut Vec<(String, PathBuf)>) -> io::Result<()> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    if metadata.is_file() {
+        files.push((name, path.to_path_buf()));
+    } else if metadata.is_dir() {
+        let mut children = fs::read_dir(path)?.collect::<io::Result<Vec<_>>>()?;
+        children.sort_by_key(|child| child.file_name());
+        for child in children {
+            let child_name = format!("{}/{}", name, child.file_name().to_string_lossy());
+            collect_files(&child.path(), child_name, files)?;
+        }
+    }
+    Ok(())
+}
+
+fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn export_workspace(
+    app_handle: AppHandle,
+    path: PathBuf,
+) -> Result<ExportSummary, CommandError> {
+    let paths = WorkspacePaths::resolve(&app_handle)?;
+    let app_version = app_handle.package_info().version.to_string();
+    let summary =
+        runtime::with_sidecar_stopped(&app_handle, move || export(&paths, &app_version, &path))
+            .await??;
+    Ok(summary)
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn import_workspace(
+    app_handle: AppHandle,
+    request: ImportWorkspaceRequest,
+) -> Result<ImportSummary, CommandError> {
+    let paths = WorkspacePaths::resolve(&app_handle)?;
+    let summary = runtime::with_sidecar_stopped(&app_handle, move || {
+        import(&paths, &request.path, request.overwrite)
+    })
+    .await??;
+    // The shared store still holds the replaced settings.
+    app_handle.state::<Settings>().reload(&app_handle)?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::tar_entry;
+
+    use super::*;
+
+    fn paths(root: &Path) -> WorkspacePaths {
+        WorkspacePaths {
+            data_dir: root.to_path_buf(),
+            modules: root.join("modules"),
+            db: root.join("db"),
+            settings: root.join(settings::STORE_FILE),
+        }
+    }
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    /// A workspace with a module, a database and settings, all containing
+    /// `contents`.
+    fn workspace(root: &Path, contents: &str) -> WorkspacePaths {
+        let paths = paths(root);
+        write(&paths.modules.join("scraper/index.ts"), contents);
+        write(&paths.db.join("kv.db"), contents);
+        write(&paths.settings, contents);
+        paths
+    }
+
+    /// An archive of `entries` as given, after a manifest listing `listed`.
+    fn archive(
+        path: &Path,
+        listed: &[(&str, &[u8])],
+        entries: &[(&str, tar::EntryType, &[u8])],
+    ) -> PathBuf {
+        let manifest = Manifest {
+            format_version: FORMAT_VERSION,
+            app_version: "0.0.10".to_string(),
+            created_at: 0,
+            files: listed
+                .iter()
+                .map(|(name, contents)| ManifestEntry {
+                    path: name.to_string(),
+                    size: contents.len() as u64,
+                    sha256: format!("{:x}", Sha256::digest(contents)),
+                })
+                .collect(),
+        };
+        let manifest = serde_json::to_vec(&manifest).unwrap();
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        tar_entry(
+            &mut builder,
+            MANIFEST_NAME,
+            tar::EntryType::Regular,
+            manifest.as_slice(),
+            manifest.len() as u64,
+        );
+        for (name, kind, contents) in entries {
+            tar_entry(&mut builder, name, *kind, *contents, contents.len() as u64);
+        }
+        fs::write(path, builder.into_inner().unwrap().finish().unwrap()).unwrap();
+        path.to_path_buf()
+    }
+
+    #[test]
+    fn imports_what_was_exported() {
+        let tmp = tempfile::tempdir().unwrap();
+        let source = workspace(&tmp.path().join("source"), "exported");
+        let archive = tmp.path().join("workspace.tar.gz");
+        let exported = export(&source, "0.0.10", &archive).unwrap();
+        assert_eq!(exported.files, 3);
+
+        let dest = paths(&tmp.path().join("dest"));
+        let imported = import(&dest, &archive, false).unwrap();
+        assert_eq!(imported.files, 3);
+        assert_eq!(imported.app_version, "0.0.10");
+        assert_eq!(imported.backup, None);
+        assert_eq!(read(&dest.modules.join("scraper/index.ts")), "exported");
+        assert_eq!(read(&dest.db.join("kv.db")), "exported");
+        assert_eq!(read(&dest.settings), "exported");
+    }
+
+    #[test]
+    fn asks_before_replacing_a_workspace_and_backs_it_up() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = tmp.path().join("workspace.tar.gz");
+        export(
+            &workspace(&tmp.path().join("source"), "imported"),
+            "0.0.10",
+            &archive,
+        )
+        .unwrap();
+        let dest = workspace(&tmp.path().join("dest"), "replaced");
+
+        assert!(matches!(
+            import(&dest, &archive, false),
+            Err(WorkspaceError::WouldOverwrite)
+        ));
+        assert_eq!(read(&dest.db.join("kv.db")), "replaced");
+
+        let backup = import(&dest, &archive, true).unwrap().backup.unwrap();
+        assert_eq!(read(&dest.db.join("kv.db")), "imported");
+        assert_eq!(read(&backup.join("db/kv.db")), "replaced");
+        assert_eq!(read(&backup.join("modules/scraper/index.ts")), "replaced");
+        assert_eq!(read(&backup.join("settings/app_data.bin")), "replaced");
+    }
+
+    #[test]
+    fn rejects_a_tampered_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = archive(
+            &tmp.path().join("workspace.tar.gz"),
+            &[("db/kv.db", b"original")],
+            &[("db/kv.db", tar::EntryType::Regular, b"tampered")],
+        );
+        let dest = paths(&tmp.path().join("dest"));
+        assert!(matches!(
+            import(&dest, &archive, false),
+            Err(WorkspaceError::Checksum(path)) if path == "db/kv.db"
+        ));
+        assert!(!dest.db.exists());
+    }
+
+    #[test]
+    fn rejects_entries_outside_the_workspace_and_links() {
+        let tmp = tempfile::tempdir().unwrap();
+        for (name, kind) in [
+            ("../evil", tar::EntryType::Regular),
+            ("db/../../evil", tar::EntryType::Regular),
+            ("/tmp/evil", tar::EntryType::Regular),
+            ("db/kv.db", tar::EntryType::Symlink),
+            ("db/kv.db", tar::EntryType::Link),
+        ] {
+            let archive = archive(
+                &tmp.path().join("workspace.tar.gz"),
+                &[],
+                &[(name, kind, b"")],
+            );
+            let dest = paths(&tmp.path().join("dest"));
+            let imported = import(&dest, &archive, false);
+            assert!(
+                matches!(imported, Err(WorkspaceError::UnsafePath(_))),
+                "{} {:?}",
+                name,
+                kind
+            );
+            assert!(!tmp.path().join("evil").exists());
+            assert!(!dest.db.exists());
+        }
+    }
+
+    #[test]
+    fn puts_the_workspace_back_when_replacing_it_fails() {
+        let tmp = tempfile::tempdir().unwrap();
+        let archive = tmp.path().join("workspace.tar.gz");
+        export(
+            &workspace(&tmp.path().join("source"), "imported"),
+            "0.0.10",
+            &archive,
+        )
+        .unwrap();
+        let dest = workspace(&tmp.path().join("dest"), "kept");
+        // Settings are moved last, and can't be moved into a file.
+        let blocked = WorkspacePaths {
+            settings: dest.db.join("kv.db").join(settings::STORE_FILE),
+            ..dest.clone()
+        };
+
+        assert!(matches!(
+            import(&blocked, &archive, true),
+            Err(WorkspaceError::Io(_))
+        ));
+        assert_eq!(read(&dest.modules.join("scraper/index.ts")), "kept");
+        assert_eq!(read(&dest.db.join("kv.db")), "kept");
+        let leftovers: Vec<_> = fs::read_dir(&dest.data_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.starts_with(".import-"))
+            .collect();
+        assert!(leftovers.is_empty(), "{:?}", leftovers);
+    }
+}
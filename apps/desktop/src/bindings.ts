@@ -0,0 +1,617 @@
+/* eslint-disable */
+// This file was generated by [tauri-specta](https://github.com/oscartbeaumont/tauri-specta). Do not edit this file manually.
+
+/** user-defined commands **/
+
+
+export const commands = {
+async showMainWindow() : Promise<Result<null, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("show_main_window") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getEffectiveConfig() : Promise<EffectiveConfig> {
+    return await TAURI_INVOKE("get_effective_config");
+},
+async showLogViewer() : Promise<Result<null, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("show_log_viewer") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * The buffered entries, only those after the entry with id `after` if given.
+ */
+async getLogs(after: number | null) : Promise<LogEntry[]> {
+    return await TAURI_INVOKE("get_logs", { after });
+},
+/**
+ * Returns how many entries were written.
+ */
+async exportLogs(request: ExportLogsRequest) : Promise<Result<number | null, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("export_logs", { request }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async installModule(request: InstallModuleRequest) : Promise<Result<InstalledModule, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("install_module", { request }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Installs a module from an archive, a directory or a local git repository.
+ */
+async installModuleFrom(source: ModuleSource) : Promise<Result<InstalledModule, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("install_module_from", { source }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Removes `version` of the module, or every version when it is omitted.
+ */
+async uninstallModule(name: string, version: string | null) : Promise<Result<null, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("uninstall_module", { name, version }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async listModules() : Promise<Result<InstalledModule[], CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("list_modules") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async setModuleVersion(name: string, version: string) : Promise<Result<InstalledModule, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_module_version", { name, version }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async rollbackModule(name: string) : Promise<Result<InstalledModule, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("rollback_module", { name }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Resolves `<project>/<module>[@<version>]` the same way `/run` does.
+ */
+async resolveModule(spec: string) : Promise<Result<ResolvedModule, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("resolve_module", { spec }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async showPalette() : Promise<Result<null, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("show_palette") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async hidePalette() : Promise<Result<null, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("hide_palette") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Runs a module through the runtime, on the port from the loaded config.
+ */
+async runModule(request: RunModuleRequest) : Promise<Result<RunResult, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("run_module", { request }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * The most recently created jobs matching `filter`.
+ */
+async listJobs(filter: JobFilter) : Promise<Result<Job[], CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("list_jobs", { filter }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getJob(id: string) : Promise<Result<Job, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_job", { id }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async listQueues() : Promise<Result<QueueStats[], CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("list_queues") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Puts a dead job back on its queue with fresh attempts.
+ */
+async retryJob(id: string) : Promise<Result<Job, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("retry_job", { id }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async deleteJob(id: string) : Promise<Result<null, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("delete_job", { id }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async listSettings() : Promise<SettingEntry[]> {
+    return await TAURI_INVOKE("list_settings");
+},
+async getSetting(key: string) : Promise<Result<JsonValue, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("get_setting", { key }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async setSetting(key: string, value: JsonValue) : Promise<Result<null, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("set_setting", { key, value }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async listCrashReports() : Promise<Result<CrashReport[], CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("list_crash_reports") };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+/**
+ * Replaces every occurrence of `text` in a pending report with `[redacted]`.
+ */
+async redactCrashReport(id: string, text: string) : Promise<Result<CrashReport, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("redact_crash_report", { id, text }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async sendCrashReport(id: string) : Promise<Result<null, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("send_crash_report", { id }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async deleteCrashReport(id: string) : Promise<Result<null, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("delete_crash_report", { id }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async isOkSharingUsageData() : Promise<boolean> {
+    return await TAURI_INVOKE("is_ok_sharing_usage_data");
+},
+/**
+ * Lets the frontend check, silently once the main window has loaded.
+ */
+async checkForUpdates(silent: boolean) : Promise<Result<null, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("check_for_updates", { silent }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async getUpdateOffer() : Promise<UpdateOffer | null> {
+    return await TAURI_INVOKE("get_update_offer");
+},
+async respondToUpdate(action: UpdateAction) : Promise<Result<null, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("respond_to_update", { action }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async openWindow(target: WindowTarget) : Promise<Result<WindowInfo, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("open_window", { target }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async closeWindow(label: string) : Promise<Result<null, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("close_window", { label }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async listWindows() : Promise<WindowInfo[]> {
+    return await TAURI_INVOKE("list_windows");
+},
+async exportWorkspace(path: string) : Promise<Result<ExportSummary, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("export_workspace", { path }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+},
+async importWorkspace(request: ImportWorkspaceRequest) : Promise<Result<ImportSummary, CommandError>> {
+    try {
+    return { status: "ok", data: await TAURI_INVOKE("import_workspace", { request }) };
+} catch (e) {
+    if(e instanceof Error) throw e;
+    else return { status: "error", error: e  as any };
+}
+}
+}
+
+/** user-defined events **/
+
+
+export const events = __makeEvents__<{
+logsAppended: LogsAppended,
+navigate: Navigate,
+settingChanged: SettingChanged,
+updateFailed: UpdateFailed,
+updateOffer: UpdateOffer,
+updateProgress: UpdateProgress
+}>({
+logsAppended: "logs-appended",
+navigate: "navigate",
+settingChanged: "setting-changed",
+updateFailed: "update-failed",
+updateOffer: "update-offer",
+updateProgress: "update-progress"
+})
+
+/** user-defined constants **/
+
+
+
+/** user-defined types **/
+
+/**
+ * Error returned by every command.
+ * 
+ * Serialized as `{ kind, message }` so the frontend can branch on `kind` and
+ * show `message` to the user.
+ */
+export type CommandError = { kind: "invalidInput"; message: string } | { kind: "confirmationRequired"; message: string } | { kind: "notFound"; message: string } | { kind: "io"; message: string } | { kind: "runtime"; message: string }
+export type ConfigEntry = { key: string; value: JsonValue; source: ConfigSource; 
+/**
+ * Why a value for this key was ignored.
+ */
+error: string | null }
+export type ConfigSource = "default" | "file" | "env" | "cli"
+export type CrashReport = { id: string; 
+/**
+ * Seconds since the unix epoch.
+ */
+createdAt: number; 
+/**
+ * The event's message or exception, for listing.
+ */
+summary: string; 
+/**
+ * Failed attempts to send it so far.
+ */
+attempts: number; lastError: string | null; 
+/**
+ * The Sentry event exactly as it will be sent.
+ */
+event: JsonValue }
+export type EffectiveConfig = { path: string; fileFound: boolean; error: string | null; entries: ConfigEntry[] }
+export type ExportLogsRequest = { 
+/**
+ * Earliest entry to export, in milliseconds since the unix epoch.
+ */
+from: number | null; 
+/**
+ * Latest entry to export, in milliseconds since the unix epoch.
+ */
+to: number | null }
+export type ExportSummary = { path: string; files: number; bytes: number }
+export type ImportSummary = { files: number; formatVersion: number; appVersion: string; 
+/**
+ * Where the replaced workspace was moved, if there was one.
+ */
+backup: string | null }
+export type ImportWorkspaceRequest = { path: string; 
+/**
+ * Replace a non-empty workspace. Without it the import fails with
+ * `confirmationRequired`.
+ */
+overwrite: boolean }
+export type InstallModuleRequest = { manifest: ModuleManifest; files: ModuleFile[] }
+export type InstalledModule = { 
+/**
+ * Manifest of the current version.
+ */
+manifest: ModuleManifest; 
+/**
+ * Directory of the current version.
+ */
+path: string; 
+/**
+ * Every installed version, newest first.
+ */
+versions: string[]; 
+/**
+ * The version a rollback would switch back to.
+ */
+previous: string | null }
+/**
+ * Times are milliseconds since the unix epoch.
+ */
+export type Job = { id: string; queue: string; payload: JsonValue; state: JobState; 
+/**
+ * Leases so far, including the current one.
+ */
+attempts: number; maxAttempts: number; 
+/**
+ * When the job is due, or was last due.
+ */
+runAt: number; leasedUntil: number | null; 
+/**
+ * From the last nack or expired lease.
+ */
+lastError: string | null; createdAt: number; updatedAt: number }
+export type JobFilter = { queue: string | null; state: JobState | null; 
+/**
+ * Defaults to 100, at most 1000.
+ */
+limit: number | null }
+export type JobState = "pending" | "leased" | "succeeded" | "dead"
+export type JsonValue = null | boolean | number | string | JsonValue[] | { [key in string]: JsonValue }
+export type LogEntry = { 
+/**
+ * Increases by one per entry, also across entries that were dropped.
+ */
+id: number; 
+/**
+ * Milliseconds since the unix epoch.
+ */
+timestamp: number; level: LogLevel; source: LogSource; 
+/**
+ * The module that logged it.
+ */
+target: string; message: string }
+export type LogLevel = "error" | "warn" | "info" | "debug" | "trace"
+/**
+ * Where an entry comes from.
+ */
+export type LogSource = "app" | "runtime"
+/**
+ * Emitted to the log viewer with the entries logged since the last batch.
+ */
+export type LogsAppended = { entries: LogEntry[] }
+export type ModuleFile = { 
+/**
+ * Path relative to the module root.
+ */
+path: string; contents: string }
+/**
+ * Describes an installed module, stored as `module.json` next to its code.
+ */
+export type ModuleManifest = { 
+/**
+ * `<project>/<module>`, e.g. `my-org/scraper`.
+ */
+name: string; 
+/**
+ * Semver version of the module.
+ */
+version: string; 
+/**
+ * Path of the file the runtime starts, relative to the module root.
+ */
+entrypoint: string; permissions: Permission[]; 
+/**
+ * Other modules this one needs, as `name -> semver requirement`.
+ */
+dependencies: { [key in string]: string } }
+/**
+ * Where to install a module from.
+ */
+export type ModuleSource = { kind: "archive"; path: string } | { kind: "directory"; path: string } | { kind: "git"; repository: string; reference: string | null }
+/**
+ * Asks the main window to show a frontend route. Secondary windows, see
+ * [`crate::windows`], don't get it.
+ */
+export type Navigate = { path: string }
+/**
+ * Capabilities a module asks the runtime for.
+ */
+export type Permission = "net" | "env"
+export type QueueStats = { queue: string; pending: number; leased: number; succeeded: number; dead: number; 
+/**
+ * When the earliest pending job is due.
+ */
+nextRunAt: number | null }
+/**
+ * A module version picked by [`ModuleStore::resolve`].
+ */
+export type ResolvedModule = { manifest: ModuleManifest; path: string }
+export type RunModuleRequest = { 
+/**
+ * `<project>/<module>[@<version>]`, as `/run` takes it.
+ */
+spec: string; 
+/**
+ * Sent as JSON if it parses as JSON, as plain text otherwise.
+ */
+input: string }
+/**
+ * The runtime's response to a run.
+ */
+export type RunResult = { status: number; ok: boolean; body: string; executionId: string | null }
+/**
+ * Emitted after a setting was written, from the app or the tray.
+ */
+export type SettingChanged = { key: string; value: JsonValue }
+/**
+ * A setting together with its current value, as listed to the frontend.
+ */
+export type SettingEntry = { key: string; label: string; description: string; scope: SettingScope; kind: SettingKind; default: JsonValue; value: JsonValue; options: SettingOption[] }
+export type SettingKind = "boolean" | "integer" | "string"
+/**
+ * One of the values a setting is restricted to.
+ */
+export type SettingOption = { value: string; label: string }
+/**
+ * Where a setting is offered to the user.
+ */
+export type SettingScope = "app" | "tray"
+export type UpdateAction = "installNow" | "installOnQuit" | "skip"
+/**
+ * Why downloading or installing an update failed.
+ * 
+ * Serialized as `{ kind, message }` like [`crate::cmd::CommandError`].
+ */
+export type UpdateError = { kind: "network"; message: string } | { kind: "signature"; message: string } | { kind: "disk"; message: string } | { kind: "install"; message: string }
+/**
+ * Emitted when downloading or installing an update failed.
+ */
+export type UpdateFailed = { version: string; error: UpdateError }
+/**
+ * The release the update window shows, as sent to it.
+ */
+export type UpdateOffer = { version: string; currentVersion: string; channel: string; 
+/**
+ * The release notes from the manifest, usually markdown.
+ */
+notes: string | null; 
+/**
+ * The publication date, `YYYY-MM-DD`.
+ */
+date: string | null; 
+/**
+ * Installing it replaces this version with an older one.
+ */
+downgrade: boolean }
+/**
+ * Emitted while an update downloads, at most every 250ms.
+ */
+export type UpdateProgress = { version: string; downloaded: number; 
+/**
+ * Unknown when the server doesn't send a content length.
+ */
+total: number | null; bytesPerSecond: number }
+/**
+ * An open secondary window, as listed to the frontend.
+ */
+export type WindowInfo = { label: string; target: WindowTarget; title: string }
+/**
+ * What a secondary window shows.
+ */
+export type WindowTarget = { kind: "workflow"; projectSlug: string; workflowSlug: string } | { kind: "execution"; id: string; projectSlug: string; moduleName: string }
+
+/** tauri-specta globals **/
+
+import {
+	invoke as TAURI_INVOKE,
+	Channel as TAURI_CHANNEL,
+} from "@tauri-apps/api/core";
+import * as TAURI_API_EVENT from "@tauri-apps/api/event";
+import { type WebviewWindow as __WebviewWindow__ } from "@tauri-apps/api/webviewWindow";
+
+type __EventObj__<T> = {
+	listen: (
+		cb: TAURI_API_EVENT.EventCallback<T>,
+	) => ReturnType<typeof TAURI_API_EVENT.listen<T>>;
+	once: (
+		cb: TAURI_API_EVENT.EventCallback<T>,
+	) => ReturnType<typeof TAURI_API_EVENT.once<T>>;
+	emit: null extends T
+		? (payload?: T) => ReturnType<typeof TAURI_API_EVENT.emit>
+		: (payload: T) => ReturnType<typeof TAURI_API_EVENT.emit>;
+};
+
+export type Result<T, E> =
+	| { status: "ok"; data: T }
+	| { status: "error"; error: E };
+
+function __makeEvents__<T extends Record<string, any>>(
+	mappings: Record<keyof T, string>,
+) {
+	return new Proxy(
+		{} as unknown as {
+			[K in keyof T]: __EventObj__<T[K]> & {
+				(handle: __WebviewWindow__): __EventObj__<T[K]>;
+			};
+		},
+		{
+			get: (_, event) => {
+				const name = mappings[event as keyof T];
+
+				return new Proxy((() => {}) as any, {
+					apply: (_, __, [window]: [__WebviewWindow__]) => ({
+						listen: (arg: any) => window.listen(name, arg),
+						once: (arg: any) => window.once(name, arg),
+						emit: (arg: any) => window.emit(name, arg),
+					}),
+					get: (_, command: keyof __EventObj__<any>) => {
+						switch (command) {
+							case "listen":
+								return (arg: any) => TAURI_API_EVENT.listen(name, arg);
+							case "once":
+								return (arg: any) => TAURI_API_EVENT.once(name, arg);
+							case "emit":
+								return (arg: any) => TAURI_API_EVENT.emit(name, arg);
+						}
+					},
+				});
+			},
+		},
+	);
+}
@@ -1,12 +1,25 @@
 import { useEffect, useState } from "react";
 
+import { commands } from "../bindings";
+
+const DEFAULT_RUNTIME_PORT = 24321;
+
+export const getRuntimePort = async () => {
+  const config = await commands.getEffectiveConfig();
+  const port = config.entries.find((entry) => entry.key === "runtime.port");
+  return typeof port?.value === "number" ? port.value : DEFAULT_RUNTIME_PORT;
+};
+
 const useHealthStatus = (interval = 30000) => {
   const [isHealthy, setIsHealthy] = useState<boolean>(false);
 
   useEffect(() => {
     const checkHealth = async () => {
       try {
-        const response = await fetch("http://localhost:24321/_internal/health");
+        const port = await getRuntimePort();
+        const response = await fetch(
+          `http://localhost:${port}/_internal/health`,
+        );
         const data = await response.json();
         console.log("Health check result:", data);
         setIsHealthy(data.message === "ok");
@@ -0,0 +1,21 @@
+import { useEffect } from "react";
+import { useRouter } from "@tanstack/react-router";
+
+import { events } from "../bindings";
+
+/** Navigates to routes requested by the backend, e.g. from the tray menu. */
+const useNavigateEvent = () => {
+  const router = useRouter();
+
+  useEffect(() => {
+    const unlisten = events.navigate.listen(({ payload }) => {
+      router.history.push(payload.path);
+    });
+
+    return () => {
+      unlisten.then((unlisten) => unlisten());
+    };
+  }, [router]);
+};
+
+export default useNavigateEvent;
@@ -0,0 +1,195 @@
+import { useEffect, useMemo, useRef, useState } from "react";
+
+import { Button } from "@craftgen/ui/components/button";
+import { Input } from "@craftgen/ui/components/input";
+import { Toggle } from "@craftgen/ui/components/toggle";
+
+import { commands, events, LogEntry, LogLevel, LogSource } from "../bindings";
+
+const LEVELS: LogLevel[] = ["error", "warn", "info", "debug", "trace"];
+const SOURCES: LogSource[] = ["app", "runtime"];
+
+/** Same as the backend's buffer, older entries are dropped. */
+const MAX_ENTRIES = 10_000;
+
+const LEVEL_CLASSES: Record<LogLevel, string> = {
+  error: "text-red-600",
+  warn: "text-yellow-600",
+  info: "text-green-600",
+  debug: "text-muted-foreground",
+  trace: "text-cyan-600",
+};
+
+const useLogEntries = () => {
+  const [entries, setEntries] = useState<LogEntry[]>([]);
+
+  useEffect(() => {
+    const append = (incoming: LogEntry[]) =>
+      setEntries((entries) => {
+        // Batches can overlap with what `getLogs` returned.
+        const last = entries.at(-1)?.id ?? -1;
+        const fresh = incoming.filter((entry) => entry.id > last);
+        if (fresh.length === 0) return entries;
+        return [...entries, ...fresh].slice(-MAX_ENTRIES);
+      });
+    const unlisten = events.logsAppended.listen(({ payload }) => {
+      append(payload.entries);
+    });
+    commands.getLogs(null).then(append);
+    return () => {
+      unlisten.then((unlisten) => unlisten());
+    };
+  }, []);
+
+  return entries;
+};
+
+const formatTime = (timestamp: number) => {
+  const date = new Date(timestamp);
+  return `${date.toLocaleTimeString(undefined, { hour12: false })}.${String(
+    date.getMilliseconds(),
+  ).padStart(3, "0")}`;
+};
+
+/** `datetime-local` input values are in local time, empty means unbounded. */
+const parseLocalTime = (value: string) =>
+  value ? new Date(value).getTime() : null;
+
+export const LogViewer = () => {
+  const entries = useLogEntries();
+  const [levels, setLevels] = useState<LogLevel[]>(["error", "warn", "info"]);
+  const [sources, setSources] = useState<LogSource[]>(SOURCES);
+  const [search, setSearch] = useState("");
+  // The entries shown while paused, new ones keep arriving underneath.
+  const [frozen, setFrozen] = useState<LogEntry[] | null>(null);
+  const [from, setFrom] = useState("");
+  const [to, setTo] = useState("");
+  const [status, setStatus] = useState<string | null>(null);
+  const bottomRef = useRef<HTMLDivElement>(null);
+
+  const shown = useMemo(() => {
+    const query = search.toLowerCase();
+    return (frozen ?? entries).filter(
+      (entry) =>
+        levels.includes(entry.level) &&
+        sources.includes(entry.source) &&
+        (!query ||
+          entry.message.toLowerCase().includes(query) ||
+          entry.target.toLowerCase().includes(query)),
+    );
+  }, [entries, frozen, levels, sources, search]);
+
+  useEffect(() => {
+    if (!frozen) bottomRef.current?.scrollIntoView();
+  }, [shown, frozen]);
+
+  const toggle = <T,>(list: T[], item: T, pressed: boolean) =>
+    pressed ? [...list, item] : list.filter((other) => other !== item);
+
+  const exportLogs = async () => {
+    const result = await commands.exportLogs({
+      from: parseLocalTime(from),
+      to: parseLocalTime(to),
+    });
+    if (result.status === "ok" && result.data === null) return;
+    setStatus(
+      result.status === "ok"
+        ? `Exported ${result.data} entries.`
+        : result.error.message,
+    );
+  };
+
+  const pausedAt = frozen?.at(-1)?.id ?? -1;
+  const newSincePause = frozen
+    ? entries.filter((entry) => entry.id > pausedAt).length
+    : 0;
+
+  return (
+    <div className="flex h-screen flex-col bg-background">
+      <div className="flex flex-wrap items-center gap-1 border-b p-2">
+        {LEVELS.map((level) => (
+          <Toggle
+            key={level}
+            size="sm"
+            pressed={levels.includes(level)}
+            onPressedChange={(pressed) =>
+              setLevels((levels) => toggle(levels, level, pressed))
+            }
+          >
+            {level}
+          </Toggle>
+        ))}
+        <div className="mx-1 h-5 border-l" />
+        {SOURCES.map((source) => (
+          <Toggle
+            key={source}
+            size="sm"
+            pressed={sources.includes(source)}
+            onPressedChange={(pressed) =>
+              setSources((sources) => toggle(sources, source, pressed))
+            }
+          >
+            {source}
+          </Toggle>
+        ))}
+        <Input
+          className="ml-auto h-8 w-56"
+          placeholder="Search"
+          value={search}
+          onChange={(event) => setSearch(event.target.value)}
+        />
+        <Button
+          size="sm"
+          variant="outline"
+          onClick={() => setFrozen(frozen ? null : entries)}
+        >
+          {frozen
+            ? `Resume${newSincePause > 0 ? ` (${newSincePause} new)` : ""}`
+            : "Pause"}
+        </Button>
+      </div>
+      <div className="min-h-0 flex-1 overflow-auto p-2 font-mono text-xs">
+        {shown.map((entry) => (
+          <div key={entry.id} className="flex gap-2 whitespace-pre-wrap">
+            <span className="shrink-0 text-muted-foreground">
+              {formatTime(entry.timestamp)}
+            </span>
+            <span className={`w-12 shrink-0 ${LEVEL_CLASSES[entry.level]}`}>
+              {entry.level.toUpperCase()}
+            </span>
+            <span className="shrink-0 text-muted-foreground">
+              {entry.target}
+            </span>
+            <span className="break-all">{entry.message}</span>
+          </div>
+        ))}
+        {shown.length === 0 && (
+          <p className="text-muted-foreground">No matching log entries.</p>
+        )}
+        <div ref={bottomRef} />
+      </div>
+      <div className="flex items-center gap-2 border-t p-2 text-sm">
+        <span className="text-muted-foreground">Export from</span>
+        <Input
+          type="datetime-local"
+          step="1"
+          className="h-8 w-52"
+          value={from}
+          onChange={(event) => setFrom(event.target.value)}
+        />
+        <span className="text-muted-foreground">to</span>
+        <Input
+          type="datetime-local"
+          step="1"
+          className="h-8 w-52"
+          value={to}
+          onChange={(event) => setTo(event.target.value)}
+        />
+        <Button size="sm" onClick={exportLogs}>
+          Export…
+        </Button>
+        {status && <span className="text-muted-foreground">{status}</span>}
+      </div>
+    </div>
+  );
+};
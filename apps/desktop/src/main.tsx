@@ -5,7 +5,6 @@ import ReactDOM from "react-dom/client";
 import "./styles.css";
 
 import { AuthSession } from "@supabase/supabase-js";
-import { attachConsole } from "@tauri-apps/plugin-log";
 
 import { api } from "@craftgen/ui/lib/api";
 
@@ -53,11 +52,7 @@ const InnerApp = () => {
   }, []);
 
   useEffect(() => {
-    (async () => {
-      await checkForAppUpdates();
-      const detach = await attachConsole();
-      return detach;
-    })();
+    checkForAppUpdates();
   }, []);
 
   const isHealthy = useHealthStatus(5000);
@@ -0,0 +1,20 @@
+import { StrictMode } from "react";
+import ReactDOM from "react-dom/client";
+
+import "../styles.css";
+
+import { ThemeProvider } from "@craftgen/ui/providers/theme-provider";
+
+import { Palette } from "./palette";
+
+const rootElement = document.getElementById("root")!;
+if (!rootElement.innerHTML) {
+  const root = ReactDOM.createRoot(rootElement);
+  root.render(
+    <StrictMode>
+      <ThemeProvider attribute="class" defaultTheme="system" enableSystem>
+        <Palette />
+      </ThemeProvider>
+    </StrictMode>,
+  );
+}
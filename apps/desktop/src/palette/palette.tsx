@@ -0,0 +1,261 @@
+import { useCallback, useEffect, useMemo, useState } from "react";
+
+import {
+  Command,
+  CommandEmpty,
+  CommandGroup,
+  CommandInput,
+  CommandItem,
+  CommandList,
+} from "@craftgen/ui/components/command";
+import { Textarea } from "@craftgen/ui/components/textarea";
+
+import { commands, InstalledModule, RunResult } from "../bindings";
+import { client } from "../trpc/react";
+
+/** A workflow of one of the user's projects, from the backend. */
+type Workflow = { projectSlug: string; slug: string; name: string };
+
+const formatBody = (body: string) => {
+  try {
+    return JSON.stringify(JSON.parse(body), null, 2);
+  } catch {
+    return body;
+  }
+};
+
+const useInstalledModules = () => {
+  const [modules, setModules] = useState<InstalledModule[]>([]);
+  const [error, setError] = useState<string | null>(null);
+
+  const refresh = useCallback(async () => {
+    const result = await commands.listModules();
+    if (result.status === "ok") {
+      setModules(result.data);
+      setError(null);
+    } else {
+      setError(result.error.message);
+    }
+  }, []);
+
+  // The window is hidden rather than closed, so the list is reloaded every
+  // time it is shown again.
+  useEffect(() => {
+    refresh();
+    window.addEventListener("focus", refresh);
+    return () => window.removeEventListener("focus", refresh);
+  }, [refresh]);
+
+  return { modules, error };
+};
+
+/**
+ * Workflows of the signed in user's projects. Empty when signed out or
+ * offline, the installed modules still work then.
+ */
+const useWorkflows = () => {
+  const [workflows, setWorkflows] = useState<Workflow[]>([]);
+
+  const refresh = useCallback(async () => {
+    try {
+      const projects = await client.project.userProjects.query();
+      const lists = await Promise.all(
+        projects.map(({ project }) =>
+          client.craft.module.list.query({ projectSlug: project.slug }),
+        ),
+      );
+      setWorkflows(
+        lists.flat().map((workflow) => ({
+          projectSlug: workflow.project.slug,
+          slug: workflow.slug,
+          name: workflow.name,
+        })),
+      );
+    } catch (error) {
+      console.warn("Failed to load workflows:", error);
+      setWorkflows([]);
+    }
+  }, []);
+
+  useEffect(() => {
+    refresh();
+    window.addEventListener("focus", refresh);
+    return () => window.removeEventListener("focus", refresh);
+  }, [refresh]);
+
+  return workflows;
+};
+
+export const Palette = () => {
+  const { modules, error } = useInstalledModules();
+  const workflows = useWorkflows();
+  const [selected, setSelected] = useState<InstalledModule | null>(null);
+  const [input, setInput] = useState("");
+  const [running, setRunning] = useState(false);
+  const [result, setResult] = useState<RunResult | null>(null);
+
+  // Grouped by project. A workflow deployed to this machine is one of the
+  // installed modules and runs right here, the others open in a window.
+  const projects = useMemo(() => {
+    const groups = new Map<
+      string,
+      { modules: InstalledModule[]; workflows: Workflow[] }
+    >();
+    const group = (project: string) => {
+      if (!groups.has(project)) {
+        groups.set(project, { modules: [], workflows: [] });
+      }
+      return groups.get(project)!;
+    };
+    const installed = new Set(modules.map((module) => module.manifest.name));
+    for (const module of modules) {
+      const [project] = module.manifest.name.split("/");
+      group(project).modules.push(module);
+    }
+    for (const workflow of workflows) {
+      if (!installed.has(`${workflow.projectSlug}/${workflow.slug}`)) {
+        group(workflow.projectSlug).workflows.push(workflow);
+      }
+    }
+    return [...groups.entries()];
+  }, [modules, workflows]);
+
+  const openWorkflow = async (workflow: Workflow) => {
+    await commands.openWindow({
+      kind: "workflow",
+      projectSlug: workflow.projectSlug,
+      workflowSlug: workflow.slug,
+    });
+    await commands.hidePalette();
+  };
+
+  const reset = () => {
+    setSelected(null);
+    setInput("");
+    setResult(null);
+  };
+
+  useEffect(() => {
+    const onKeyDown = (ev: KeyboardEvent) => {
+      if (ev.key !== "Escape") return;
+      ev.preventDefault();
+      if (selected) {
+        reset();
+      } else {
+        commands.hidePalette();
+      }
+    };
+    document.addEventListener("keydown", onKeyDown);
+    return () => document.removeEventListener("keydown", onKeyDown);
+  }, [selected]);
+
+  const run = async () => {
+    if (!selected || running) return;
+    const { name, version } = selected.manifest;
+    setRunning(true);
+    setResult(null);
+    try {
+      const response = await commands.runModule({
+        spec: `${name}@${version}`,
+        input,
+      });
+      setResult(
+        response.status === "ok"
+          ? response.data
+          : {
+              ok: false,
+              status: 0,
+              body: response.error.message,
+              executionId: null,
+            },
+      );
+    } finally {
+      setRunning(false);
+    }
+  };
+
+  if (selected) {
+    return (
+      <div className="flex h-screen flex-col gap-2 overflow-hidden rounded-lg border bg-background p-3">
+        <div className="flex items-baseline justify-between text-sm">
+          <span className="font-medium">{selected.manifest.name}</span>
+          <span className="text-muted-foreground">
+            {selected.manifest.version}
+          </span>
+        </div>
+        <Textarea
+          autoFocus
+          className="min-h-24 font-mono text-sm"
+          placeholder="Input, as JSON or plain text. ⌘↵ to run, esc to go back."
+          value={input}
+          onChange={(ev) => setInput(ev.target.value)}
+          onKeyDown={(ev) => {
+            if (ev.key === "Enter" && (ev.metaKey || ev.ctrlKey)) {
+              ev.preventDefault();
+              run();
+            }
+          }}
+        />
+        {running && (
+          <p className="text-sm text-muted-foreground">Running…</p>
+        )}
+        {result && (
+          <div className="flex min-h-0 flex-1 flex-col gap-1">
+            <span
+              className={
+                result.ok ? "text-sm text-green-600" : "text-sm text-red-600"
+              }
+            >
+              {result.status ? `HTTP ${result.status}` : "Request failed"}
+            </span>
+            <pre className="flex-1 overflow-auto rounded bg-muted p-2 text-xs">
+              {formatBody(result.body)}
+            </pre>
+          </div>
+        )}
+      </div>
+    );
+  }
+
+  return (
+    <Command className="h-screen rounded-lg border">
+      <CommandInput
+        autoFocus
+        placeholder="Search installed modules and workflows…"
+      />
+      <CommandList className="max-h-none flex-1">
+        <CommandEmpty>
+          {error ?? "No module or workflow matches your search."}
+        </CommandEmpty>
+        {projects.map(([project, { modules, workflows }]) => (
+          <CommandGroup key={project} heading={project}>
+            {modules.map((module) => (
+              <CommandItem
+                key={module.manifest.name}
+                value={module.manifest.name}
+                onSelect={() => setSelected(module)}
+              >
+                <span>{module.manifest.name.split("/")[1]}</span>
+                <span className="ml-auto text-xs text-muted-foreground">
+                  {module.manifest.version}
+                </span>
+              </CommandItem>
+            ))}
+            {workflows.map((workflow) => (
+              <CommandItem
+                key={`${workflow.projectSlug}/${workflow.slug}`}
+                value={`${workflow.projectSlug}/${workflow.slug} ${workflow.name}`}
+                onSelect={() => openWorkflow(workflow)}
+              >
+                <span>{workflow.name}</span>
+                <span className="ml-auto text-xs text-muted-foreground">
+                  Open
+                </span>
+              </CommandItem>
+            ))}
+          </CommandGroup>
+        ))}
+      </CommandList>
+    </Command>
+  );
+};
@@ -18,6 +18,7 @@ import { Route as LayoutImport } from './routes/_layout'
 import { Route as LayoutIndexImport } from './routes/_layout.index'
 import { Route as LayoutPackageImport } from './routes/_layout.package'
 import { Route as LayoutExploreImport } from './routes/_layout.explore'
+import { Route as LayoutCrashReportsImport } from './routes/_layout.crash-reports'
 import { Route as LayoutProjectSlugIndexImport } from './routes/_layout.$projectSlug/index'
 import { Route as LayoutProjectSlugWorkflowSlugLayoutImport } from './routes/_layout.$projectSlug/$workflowSlug/_layout'
 import { Route as LayoutProjectSlugWorkflowSlugLayoutIndexImport } from './routes/_layout.$projectSlug/$workflowSlug/_layout.index'
@@ -58,6 +59,11 @@ const LayoutExploreRoute = LayoutExploreImport.update({
   getParentRoute: () => LayoutRoute,
 } as any)
 
+const LayoutCrashReportsRoute = LayoutCrashReportsImport.update({
+  path: '/crash-reports',
+  getParentRoute: () => LayoutRoute,
+} as any)
+
 const LayoutProjectSlugWorkflowSlugRoute =
   LayoutProjectSlugWorkflowSlugImport.update({
     path: '/$projectSlug/$workflowSlug',
@@ -117,6 +123,13 @@ declare module '@tanstack/react-router' {
       preLoaderRoute: typeof LoginImport
       parentRoute: typeof rootRoute
     }
+    '/_layout/crash-reports': {
+      id: '/_layout/crash-reports'
+      path: '/crash-reports'
+      fullPath: '/crash-reports'
+      preLoaderRoute: typeof LayoutCrashReportsImport
+      parentRoute: typeof LayoutImport
+    }
     '/_layout/explore': {
       id: '/_layout/explore'
       path: '/explore'
@@ -194,6 +207,7 @@ declare module '@tanstack/react-router' {
 
 export const routeTree = rootRoute.addChildren({
   LayoutRoute: LayoutRoute.addChildren({
+    LayoutCrashReportsRoute,
     LayoutExploreRoute,
     LayoutPackageRoute,
     LayoutIndexRoute,
@@ -227,6 +241,7 @@ export const routeTree = rootRoute.addChildren({
     "/_layout": {
       "filePath": "_layout.tsx",
       "children": [
+        "/_layout/crash-reports",
         "/_layout/explore",
         "/_layout/package",
         "/_layout/",
@@ -237,6 +252,10 @@ export const routeTree = rootRoute.addChildren({
     "/login": {
       "filePath": "login.tsx"
     },
+    "/_layout/crash-reports": {
+      "filePath": "_layout.crash-reports.tsx",
+      "parent": "/_layout"
+    },
     "/_layout/explore": {
       "filePath": "_layout.explore.tsx",
       "parent": "/_layout"
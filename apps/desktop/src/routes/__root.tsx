@@ -12,6 +12,8 @@ import { DashboardLayout } from "@craftgen/ui/layout/dashboard";
 import { api } from "@craftgen/ui/lib/api";
 import { cn } from "@craftgen/ui/lib/utils";
 
+import useNavigateEvent from "../hooks/use-navigate-event";
+
 interface MyRouterContext {
   auth: AuthSession;
   client: ReturnType<typeof api.useUtils>;
@@ -45,6 +47,8 @@ const queryClient = new QueryClient();
 
 export const Route = createRootRouteWithContext<MyRouterContext>()({
   component: () => {
+    useNavigateEvent();
+
     return (
       <>
         <DashboardLayout
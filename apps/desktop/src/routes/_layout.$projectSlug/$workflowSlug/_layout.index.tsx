@@ -1,4 +1,5 @@
 import { createFileRoute } from "@tanstack/react-router";
+import { z } from "zod";
 
 import { WorkflowInput } from "@craftgen/composer/input-form-view";
 import { LoadingDots } from "@craftgen/ui/components/loading-dots";
@@ -8,6 +9,8 @@ import { api } from "@craftgen/ui/lib/api";
 const ProjectPage = () => {
   const initial = Route.useLoaderData();
   const params = Route.useParams();
+  // Set when the window was opened on a specific execution, e.g. from the tray.
+  const search = Route.useSearch();
   const { data: module } = api.craft.module.meta.useQuery(
     {
       projectSlug: params.projectSlug,
@@ -23,7 +26,7 @@ const ProjectPage = () => {
       projectSlug: params.projectSlug,
       workflowSlug: params.workflowSlug,
       version: Number(module?.version?.version),
-      executionId: module?.execution?.id,
+      executionId: search.executionId ?? module?.execution?.id,
     },
     {
       initialData: initial.workflow,
@@ -41,8 +44,13 @@ const ProjectPage = () => {
 export const Route = createFileRoute(
   "/_layout/$projectSlug/$workflowSlug/_layout/",
 )({
+  validateSearch: z.object({
+    executionId: z.string().optional(),
+  }),
+  loaderDeps: ({ search: { executionId } }) => ({ executionId }),
   loader: async ({
     params: { projectSlug, workflowSlug },
+    deps: { executionId },
     context: { client },
   }) => {
     const module = await client.craft.module.meta.ensureData({
@@ -53,7 +61,7 @@ export const Route = createFileRoute(
       workflowSlug: workflowSlug,
       projectSlug: projectSlug,
       version: Number(module?.version?.version),
-      executionId: module?.execution?.id,
+      executionId: executionId ?? module?.execution?.id,
     });
     return { module, workflow };
   },
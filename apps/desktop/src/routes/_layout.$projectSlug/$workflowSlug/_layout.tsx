@@ -6,11 +6,14 @@ import {
   useChildMatches,
 } from "@tanstack/react-router";
 
+import { Button } from "@craftgen/ui/components/button";
 import { Tabs, TabsList, TabsTrigger } from "@craftgen/ui/components/tabs";
 import { WorkflowLayout } from "@craftgen/ui/layout/workflow";
 import { api } from "@craftgen/ui/lib/api";
 import { ModuleHeader } from "@craftgen/ui/views/module-header";
 
+import { commands } from "../../../bindings";
+
 const WorkflowPageLayout = () => {
   const params = Route.useParams();
   const data = Route.useLoaderData();
@@ -35,7 +38,7 @@ const WorkflowPageLayout = () => {
         workflow={workflow}
         moduleId={`${workflow.project.slug}/${workflow.slug}`}
       />
-      <Tabs className="mt-4" defaultValue={segment}>
+      <Tabs className="mt-4 flex items-center" defaultValue={segment}>
         <TabsList>
           <Link to={`/${workflow.project.slug}/${workflow.slug}`}>
             <TabsTrigger value="demo">Demo</TabsTrigger>
@@ -47,6 +50,20 @@ const WorkflowPageLayout = () => {
             <TabsTrigger value="versions">Versions</TabsTrigger>
           </Link>
         </TabsList>
+        <Button
+          className="ml-auto"
+          variant="ghost"
+          size="sm"
+          onClick={() =>
+            commands.openWindow({
+              kind: "workflow",
+              projectSlug: workflow.project.slug,
+              workflowSlug: workflow.slug,
+            })
+          }
+        >
+          Open in New Window
+        </Button>
       </Tabs>
       <Outlet />
     </WorkflowLayout>
@@ -0,0 +1,135 @@
+import { useState } from "react";
+import { useQuery, useQueryClient } from "@tanstack/react-query";
+import { createFileRoute } from "@tanstack/react-router";
+
+import { Button } from "@craftgen/ui/components/button";
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardFooter,
+  CardHeader,
+  CardTitle,
+} from "@craftgen/ui/components/card";
+import { Input } from "@craftgen/ui/components/input";
+import { toast } from "@craftgen/ui/components/use-toast";
+
+import { commands, CrashReport, Result } from "../bindings";
+
+const QUERY_KEY = ["crash-reports"];
+
+const unwrap = <T,>(result: Result<T, { message: string }>) => {
+  if (result.status === "error") throw new Error(result.error.message);
+  return result.data;
+};
+
+const CrashReportCard = ({ report }: { report: CrashReport }) => {
+  const queryClient = useQueryClient();
+  const [expanded, setExpanded] = useState(false);
+  const [redaction, setRedaction] = useState("");
+
+  const refresh = () => queryClient.invalidateQueries({ queryKey: QUERY_KEY });
+
+  const send = () =>
+    toast.promise(
+      commands.sendCrashReport(report.id).then(unwrap).finally(refresh),
+      {
+        loading: "Sending report...",
+        success: "Report sent, thank you",
+        error: (error) => `${error}`,
+      },
+    );
+
+  const remove = async () => {
+    try {
+      unwrap(await commands.deleteCrashReport(report.id));
+    } catch (error) {
+      toast.error(`${error}`);
+    }
+    refresh();
+  };
+
+  const redact = async () => {
+    try {
+      unwrap(await commands.redactCrashReport(report.id, redaction));
+      setRedaction("");
+    } catch (error) {
+      toast.error(`${error}`);
+    }
+    refresh();
+  };
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle className="text-base">{report.summary}</CardTitle>
+        <CardDescription>
+          {new Date(report.createdAt * 1000).toLocaleString()}
+          {report.attempts > 0 &&
+            ` · ${report.attempts} failed attempt${report.attempts > 1 ? "s" : ""}: ${report.lastError}`}
+        </CardDescription>
+      </CardHeader>
+      {expanded && (
+        <CardContent className="space-y-2">
+          <pre className="max-h-96 overflow-auto rounded bg-muted p-2 text-xs">
+            {JSON.stringify(report.event, null, 2)}
+          </pre>
+          <div className="flex gap-2">
+            <Input
+              placeholder="Text to redact, e.g. a file name or token"
+              value={redaction}
+              onChange={(e) => setRedaction(e.target.value)}
+            />
+            <Button
+              variant="outline"
+              disabled={!redaction.trim()}
+              onClick={redact}
+            >
+              Redact
+            </Button>
+          </div>
+        </CardContent>
+      )}
+      <CardFooter className="gap-2">
+        <Button variant="outline" onClick={() => setExpanded(!expanded)}>
+          {expanded ? "Hide details" : "Inspect"}
+        </Button>
+        <Button onClick={send}>Send</Button>
+        <Button variant="destructive" onClick={remove}>
+          Delete
+        </Button>
+      </CardFooter>
+    </Card>
+  );
+};
+
+function CrashReportsPage() {
+  const { data: reports = [], error } = useQuery({
+    queryKey: QUERY_KEY,
+    queryFn: async () => unwrap(await commands.listCrashReports()),
+  });
+
+  return (
+    <div className="mx-auto flex max-w-4xl flex-col space-y-4 p-4">
+      <div>
+        <h1 className="text-2xl font-semibold">Pending reports</h1>
+        <p className="text-sm text-muted-foreground">
+          Crash reports are kept here until they are sent. With "Share Usage
+          Data" on they are sent automatically when online, otherwise nothing
+          leaves this machine unless you send it.
+        </p>
+      </div>
+      {error && <p className="text-sm text-red-600">{`${error}`}</p>}
+      {!error && reports.length === 0 && (
+        <p className="text-sm text-muted-foreground">No pending reports.</p>
+      )}
+      {reports.map((report) => (
+        <CrashReportCard key={report.id} report={report} />
+      ))}
+    </div>
+  );
+}
+
+export const Route = createFileRoute("/_layout/crash-reports")({
+  component: CrashReportsPage,
+});
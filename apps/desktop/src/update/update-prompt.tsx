@@ -0,0 +1,85 @@
+import { useEffect, useState } from "react";
+
+import { Button } from "@craftgen/ui/components/button";
+import { ScrollArea } from "@craftgen/ui/components/scroll-area";
+
+import { commands, events, UpdateAction, UpdateOffer } from "../bindings";
+
+const useUpdateOffer = () => {
+  const [offer, setOffer] = useState<UpdateOffer | null>(null);
+
+  useEffect(() => {
+    commands.getUpdateOffer().then(setOffer);
+    // Sent when a later check replaces the release this window shows.
+    const unlisten = events.updateOffer.listen(({ payload }) => {
+      setOffer(payload);
+    });
+    return () => {
+      unlisten.then((unlisten) => unlisten());
+    };
+  }, []);
+
+  return offer;
+};
+
+export const UpdatePrompt = () => {
+  const offer = useUpdateOffer();
+  const [error, setError] = useState<string | null>(null);
+
+  const respond = async (action: UpdateAction) => {
+    const result = await commands.respondToUpdate(action);
+    if (result.status === "error") {
+      setError(result.error.message);
+    }
+  };
+
+  if (!offer) {
+    return (
+      <div className="flex h-screen items-center justify-center text-sm text-muted-foreground">
+        No update to show.
+      </div>
+    );
+  }
+
+  return (
+    <div className="flex h-screen flex-col gap-3 bg-background p-4">
+      <div>
+        <h1 className="text-lg font-semibold">
+          {offer.downgrade
+            ? `Switch to Craftgen ${offer.version}?`
+            : `Craftgen ${offer.version} is available`}
+        </h1>
+        <p className="text-sm text-muted-foreground">
+          You have {offer.currentVersion}. Latest {offer.channel} release
+          {offer.date ? `, published ${offer.date}` : ""}.
+        </p>
+        {offer.downgrade && (
+          <p className="mt-1 text-sm text-red-600">
+            This replaces your version with an older one. Workspaces saved by
+            the newer version may not open.
+          </p>
+        )}
+      </div>
+      <ScrollArea className="min-h-0 flex-1 rounded border bg-muted p-3">
+        {/* Rendered as plain text, the notes come from the update manifest. */}
+        <pre className="whitespace-pre-wrap font-sans text-sm">
+          {offer.notes ?? "This release has no release notes."}
+        </pre>
+      </ScrollArea>
+      {error && <p className="text-sm text-red-600">{error}</p>}
+      <div className="flex gap-2">
+        <Button variant="ghost" onClick={() => respond("skip")}>
+          Skip This Version
+        </Button>
+        <div className="ml-auto flex gap-2">
+          <Button variant="outline" onClick={() => respond("installOnQuit")}>
+            Install on Quit
+          </Button>
+          <Button autoFocus onClick={() => respond("installNow")}>
+            {offer.downgrade ? "Downgrade Now" : "Install Now"}
+          </Button>
+        </div>
+      </div>
+    </div>
+  );
+};
@@ -1,28 +1,14 @@
-import { ask } from "@tauri-apps/plugin-dialog";
-import { relaunch } from "@tauri-apps/plugin-process";
-import { check } from "@tauri-apps/plugin-updater";
+import { getCurrent } from "@tauri-apps/api/webviewWindow";
 
+import { commands } from "./bindings";
+
+/**
+ * Checks for a newer release once the app has loaded. The backend shows it
+ * in the update window, unless the user skipped that version.
+ */
 export async function checkForAppUpdates() {
   if (import.meta.env.DEV) return;
-  const update = await check();
-
-  if (update?.available) {
-    const yes = await ask(
-      `
-Update to ${update.version} is available!
-Release notes: ${update.body}
-        `,
-      {
-        title: "Update Now!",
-        kind: "info",
-        okLabel: "Update",
-        cancelLabel: "Cancel",
-      },
-    );
-
-    if (yes) {
-      await update.downloadAndInstall();
-      await relaunch();
-    }
-  }
+  // Workflow and execution windows load the same app.
+  if (getCurrent().label !== "main") return;
+  await commands.checkForUpdates(true);
 }
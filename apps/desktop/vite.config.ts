@@ -10,6 +10,17 @@ import { defineConfig } from "vite";
 export default defineConfig(async () => ({
   build: {
     sourcemap: true, // Source map generation must be turned on for Sentry to work
+    rollupOptions: {
+      input: {
+        main: path.resolve(__dirname, "index.html"),
+        // The quick-launch window, see `src-tauri/src/palette.rs`.
+        palette: path.resolve(__dirname, "palette.html"),
+        // Release notes and install options, see `src-tauri/src/updater/prompt.rs`.
+        update: path.resolve(__dirname, "update.html"),
+        // The log viewer, see `src-tauri/src/logs.rs`.
+        logs: path.resolve(__dirname, "logs.html"),
+      },
+    },
   },
   plugins: [
     preserveDirectives(),
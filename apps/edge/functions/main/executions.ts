@@ -0,0 +1,76 @@
+import { STATUS_CODE } from "https://deno.land/std@0.224.0/http/status.ts";
+import { Hono } from "npm:hono";
+
+export type ExecutionStatus = "running" | "succeeded" | "failed" | "cancelled";
+
+/** A single `/run` request, as reported to the desktop app. */
+export interface Execution {
+  id: string;
+  /** `<project>/<module>` */
+  module: string;
+  version?: string;
+  status: ExecutionStatus;
+  /** Milliseconds since the epoch. */
+  startedAt: number;
+  finishedAt?: number;
+}
+
+/** How many finished executions are kept for `/_internal/executions`. */
+const RECENT_LIMIT = 50;
+
+const running = new Map<
+  string,
+  { execution: Execution; controller: AbortController }
+>();
+const recent: Execution[] = [];
+
+export const startExecution = (module: string, version?: string) => {
+  const execution: Execution = {
+    id: crypto.randomUUID(),
+    module,
+    version,
+    status: "running",
+    startedAt: Date.now(),
+  };
+  const controller = new AbortController();
+  running.set(execution.id, { execution, controller });
+  return { execution, signal: controller.signal };
+};
+
+export const finishExecution = (
+  id: string,
+  status: Exclude<ExecutionStatus, "running">,
+) => {
+  const entry = running.get(id);
+  if (!entry) {
+    return;
+  }
+  running.delete(id);
+  // A cancelled execution stays cancelled even if the worker errors out.
+  if (entry.execution.status === "running") {
+    entry.execution.status = status;
+  }
+  entry.execution.finishedAt = Date.now();
+  recent.unshift(entry.execution);
+  recent.length = Math.min(recent.length, RECENT_LIMIT);
+};
+
+/** Running and recently finished executions, newest first. */
+export const executions = new Hono()
+  .get("/", (c) => {
+    return c.json({
+      running: [...running.values()]
+        .map(({ execution }) => execution)
+        .reverse(),
+      recent,
+    });
+  })
+  .delete("/:id", (c) => {
+    const entry = running.get(c.req.param("id"));
+    if (!entry) {
+      return c.json({ message: "Not running" }, STATUS_CODE.NotFound);
+    }
+    entry.execution.status = "cancelled";
+    entry.controller.abort();
+    return c.json({ execution: entry.execution });
+  });
@@ -13,6 +13,7 @@ import {
 import { buildDbClient } from "../database/lib/client-org.ts";
 import { appRouter } from "../ipc-api/mod.ts";
 import { trpcServer } from "../ipc-api/trpcServer.ts";
+import { executions } from "./executions.ts";
 import { packages } from "./package.ts";
 import { run } from "./run.ts";
 
@@ -32,6 +33,9 @@ app.get("/_internal/health", (c) => {
   return c.json({ message: "ok" }, STATUS_CODE.OK);
 });
 
+// Executions shown in the desktop tray
+app.route("/_internal/executions", executions);
+
 // Metric route
 app.get("/_internal/metric", async (c) => {
   const metric = await EdgeRuntime.getRuntimeMetrics();
@@ -0,0 +1,175 @@
+import { join } from "https://deno.land/std@0.224.0/path/mod.ts";
+import {
+  compare,
+  format,
+  maxSatisfying,
+  type Range,
+  type SemVer,
+  tryParse,
+  tryParseRange,
+} from "https://deno.land/std@0.224.0/semver/mod.ts";
+
+import type { ModulePermission } from "./worker.ts";
+
+/** `module.json` written by the desktop app when a module is installed. */
+export interface ModuleManifest {
+  name: string;
+  version: string;
+  entrypoint: string;
+  permissions: ModulePermission[];
+  dependencies: Record<string, string>;
+}
+
+/** `current.json`, the version used when a request doesn't pin one. */
+interface Pointer {
+  version: string;
+  previous?: string;
+}
+
+export interface ResolvedModule {
+  name: string;
+  version: string;
+  path: string;
+  manifest: ModuleManifest;
+  /** Every installed version, newest first. */
+  versions: string[];
+}
+
+const readJson = async <T>(path: string): Promise<T | undefined> => {
+  try {
+    return JSON.parse(await Deno.readTextFile(path));
+  } catch (e) {
+    if (e instanceof Deno.errors.NotFound) {
+      return undefined;
+    }
+    throw e;
+  }
+};
+
+/**
+ * Directories modules are looked up in: the ones installed into the workspace
+ * (`CRAFTGEN_MODULES_DIR`) first, then the ones bundled with the app
+ * (`SERVICE_BASE_DIR`).
+ */
+export const moduleRoots = (): string[] =>
+  [Deno.env.get("CRAFTGEN_MODULES_DIR"), Deno.env.get("SERVICE_BASE_DIR")]
+    .filter((dir): dir is string => !!dir);
+
+const exists = async (path: string) => {
+  try {
+    await Deno.stat(path);
+    return true;
+  } catch (e) {
+    if (e instanceof Deno.errors.NotFound) {
+      return false;
+    }
+    throw e;
+  }
+};
+
+/** The first of `moduleRoots()` that has `<project>/<module>`. */
+export const findModuleRoot = async (
+  projectSlug: string,
+  moduleName: string,
+): Promise<string | undefined> => {
+  for (const root of moduleRoots()) {
+    if (await exists(join(root, projectSlug, moduleName))) {
+      return root;
+    }
+  }
+  return undefined;
+};
+
+export const readManifest = (path: string) =>
+  readJson<ModuleManifest>(join(path, "module.json"));
+
+const installedVersions = async (moduleDir: string): Promise<SemVer[]> => {
+  const versions: SemVer[] = [];
+  try {
+    for await (const entry of Deno.readDir(moduleDir)) {
+      const version = entry.isDirectory ? tryParse(entry.name) : undefined;
+      if (version) {
+        versions.push(version);
+      }
+    }
+  } catch (e) {
+    if (!(e instanceof Deno.errors.NotFound)) {
+      throw e;
+    }
+  }
+  return versions.sort((a, b) => compare(b, a));
+};
+
+/** A comparator of a range, e.g. `>=1.2.0`, `^1.2`, `~1` or `1.x`. */
+const COMPARATOR =
+  /^(?:[~^]|[<>]=?|=)?(?:[*xX]|\d+(?:\.(?:\d+|[*xX])){0,2}(?:-[0-9A-Za-z.-]+)?)$/;
+
+/**
+ * Parses a version selector the way `parse_spec` does in the desktop app:
+ * `latest`, an exact version or a range of whitespace separated comparators,
+ * where a bare `1.2` means `1.2.x`. `||` and hyphen ranges aren't supported,
+ * so `undefined` is returned for them.
+ */
+export const parseSelector = (
+  selector: string,
+): Range | "latest" | undefined => {
+  if (selector === "latest") {
+    return "latest";
+  }
+  const comparators = selector.trim().split(/\s+/);
+  if (!comparators.every((comparator) => COMPARATOR.test(comparator))) {
+    return undefined;
+  }
+  return tryParseRange(comparators.join(" "));
+};
+
+/** The highest of `versions`, newest first, matching `selector`. */
+export const selectVersion = (
+  versions: SemVer[],
+  selector: Range | "latest",
+): string | undefined => {
+  const match =
+    selector === "latest" ? versions[0] : maxSatisfying(versions, selector);
+  return match ? format(match) : undefined;
+};
+
+/**
+ * Picks the installed version of `<project>/<module>` matching `selector`,
+ * mirroring `ModuleStore::resolve` in the desktop app: no selector means the
+ * current version, `latest` the newest one, anything else an exact version or
+ * semver range.
+ */
+export const resolveModule = async (
+  baseDir: string,
+  projectSlug: string,
+  moduleName: string,
+  selector?: string,
+): Promise<ResolvedModule | undefined> => {
+  const moduleDir = join(baseDir, projectSlug, moduleName);
+  const versions = await installedVersions(moduleDir);
+
+  let version: string | undefined;
+  if (!selector) {
+    const pointer = await readJson<Pointer>(join(moduleDir, "current.json"));
+    version = pointer?.version ?? (versions[0] && format(versions[0]));
+  } else {
+    const parsed = parseSelector(selector);
+    version = parsed && selectVersion(versions, parsed);
+  }
+  if (!version) {
+    return undefined;
+  }
+
+  const path = join(moduleDir, version);
+  const manifest = await readManifest(path);
+  if (!manifest) {
+    return undefined;
+  }
+  return {
+    name: `${projectSlug}/${moduleName}`,
+    version,
+    path,
+    manifest,
+    versions: versions.map((v) => format(v)),
+  };
+};
@@ -0,0 +1,33 @@
+import { assertEquals } from "https://deno.land/std@0.224.0/assert/mod.ts";
+import { compare, parse } from "https://deno.land/std@0.224.0/semver/mod.ts";
+
+import { parseSelector, selectVersion } from "./modules.ts";
+
+// Shared with the desktop app's `ModuleStore` tests, so both resolve
+// selectors the same way.
+const selectors: {
+  installed: string[];
+  cases: { selector: string; selects?: string | null; invalid?: boolean }[];
+} = JSON.parse(
+  await Deno.readTextFile(
+    new URL("./testdata/version_selectors.json", import.meta.url),
+  ),
+);
+
+const installed = selectors.installed.map((v) => parse(v)).sort((a, b) =>
+  compare(b, a)
+);
+
+for (const { selector, selects, invalid } of selectors.cases) {
+  Deno.test(`selects ${JSON.stringify(selector)}`, () => {
+    const parsed = parseSelector(selector);
+    if (invalid) {
+      assertEquals(parsed, undefined);
+      return;
+    }
+    assertEquals(
+      parsed && selectVersion(installed, parsed),
+      selects ?? undefined,
+    );
+  });
+}
@@ -1,64 +1,40 @@
 import { Hono } from "npm:hono";
 import { z } from "npm:zod";
 
-import { getDB } from "./surreal/db.ts";
+import { findModuleRoot, resolveModule } from "./modules.ts";
 import { zValidator } from "./zValidator.ts";
 
+// Modules are installed by the desktop app, which lays them out on disk, see
+// `ModuleStore`. This only reports what is installed.
 export const packages = new Hono()
-  .get("/", async (c) => {
-    const db = await getDB();
-    const packages = await db.select("package");
-
-    return c.json({ packages });
-  })
-  .post(
-    "/",
-    zValidator(
-      "json",
-      z.object({
-        projectSlug: z.string(),
-        moduleSlug: z.string(),
-        code: z.string(),
-      }),
-    ),
-    async (c) => {
-      const input = c.req.valid("json");
-      const db = await getDB();
-      const res = await db.create("package", {
-        id: {
-          project: input.projectSlug,
-          module: input.moduleSlug,
-        },
-        code: input.code,
-      });
-
-      return c.json(
-        {
-          package: res,
-        },
-        201,
-      );
-    },
-  )
   .get(
-    "/:projectSlug/:moduleName/:version{[0-9]+\\.[0-9]+\\.[0-9]+}?",
+    "/:projectSlug/:moduleName/:version?",
     zValidator(
       "param",
       z.object({
-        projectSlug: z.string().optional(),
-        moduleName: z.string().optional(),
-        version: z
-          .string()
-          .regex(/^\d+\.\d+\.\d+$/)
-          .optional(),
+        projectSlug: z.string(),
+        moduleName: z.string(),
+        // An exact version, a semver range or `latest`.
+        version: z.string().optional(),
       }),
     ),
-    (c) => {
+    async (c) => {
       const { projectSlug, moduleName, version } = c.req.valid("param");
+      const baseDir = await findModuleRoot(projectSlug, moduleName);
+      const resolved =
+        baseDir &&
+        (await resolveModule(baseDir, projectSlug, moduleName, version));
+      if (!resolved) {
+        return c.json(
+          { message: `Module ${projectSlug}/${moduleName} is not installed` },
+          404,
+        );
+      }
       return c.json({
         projectSlug,
         moduleName,
-        version: version || "latest",
+        version: resolved.version,
+        versions: resolved.versions,
       });
     },
   );
@@ -1,12 +1,50 @@
 import { STATUS_CODE } from "https://deno.land/std@0.224.0/http/status.ts";
+import { join, toFileUrl } from "https://deno.land/std@0.224.0/path/mod.ts";
 import { Hono } from "npm:hono";
 import { z } from "npm:zod";
 
+import { finishExecution, startExecution } from "./executions.ts";
+import { findModuleRoot, readManifest, resolveModule } from "./modules.ts";
 import { createWorker } from "./worker.ts";
 import { zValidator } from "./zValidator.ts";
 
+/**
+ * Passes `body` through and finishes the execution once it has been read to
+ * the end, failed or was cancelled by the client.
+ */
+const finishWithBody = (
+  body: ReadableStream<Uint8Array>,
+  id: string,
+  status: "succeeded" | "failed",
+) => {
+  const reader = body.getReader();
+  return new ReadableStream<Uint8Array>({
+    async pull(controller) {
+      try {
+        const { done, value } = await reader.read();
+        if (done) {
+          finishExecution(id, status);
+          controller.close();
+        } else {
+          controller.enqueue(value);
+        }
+      } catch (e) {
+        // Also the way a cancelled worker ends, which stays cancelled.
+        finishExecution(id, "failed");
+        controller.error(e);
+      }
+    },
+    cancel(reason) {
+      finishExecution(id, "cancelled");
+      return reader.cancel(reason);
+    },
+  });
+};
+
 export const run = new Hono().on(
   ["GET", "POST"],
+  // `moduleName` may pin a version: `scraper@1.2.0`, `scraper@^1` or
+  // `scraper@latest`. Without one the module's current version runs.
   "/:projectSlug/:moduleName/*",
   zValidator(
     "param",
@@ -17,35 +55,81 @@ export const run = new Hono().on(
   ),
   async (c) => {
     const module = c.req.param();
+    let executionId: string | undefined;
 
     try {
-      const serviceBaseDir = Deno.env.get("SERVICE_BASE_DIR")!;
-      const service_name = `${module.projectSlug}/${module.moduleName}`;
-      const servicePath = `${serviceBaseDir}/${service_name}`;
+      const [moduleName, selector] = module.moduleName.split("@", 2);
+      const serviceBaseDir =
+        (await findModuleRoot(module.projectSlug, moduleName)) ??
+        Deno.env.get("SERVICE_BASE_DIR")!;
+      const resolved = await resolveModule(
+        serviceBaseDir,
+        module.projectSlug,
+        moduleName,
+        selector,
+      );
+      if (!resolved && selector) {
+        c.status(STATUS_CODE.NotFound);
+        return c.json({
+          message: `No installed version of ${module.projectSlug}/${moduleName} matches ${selector}`,
+        });
+      }
+      // Modules that predate versioned installs live directly in the module
+      // directory, with or without a manifest.
+      const servicePath =
+        resolved?.path ?? join(serviceBaseDir, module.projectSlug, moduleName);
+      const manifest = resolved?.manifest ?? (await readManifest(servicePath));
       console.log("SERVICE PATH", {
         serviceBaseDir,
-        service_name,
+        service_name: `${module.projectSlug}/${moduleName}@${resolved?.version}`,
         servicePath,
       });
 
+      // Cancelled through `DELETE /_internal/executions/:id`.
+      const { execution, signal } = startExecution(
+        `${module.projectSlug}/${moduleName}`,
+        resolved?.version,
+      );
+      executionId = execution.id;
+
       const worker = await createWorker({
         servicePath,
+        entrypoint: manifest
+          ? toFileUrl(join(servicePath, manifest.entrypoint)).href
+          : undefined,
+        permissions: manifest?.permissions,
       });
-      const controller = new AbortController();
-      const signal = controller.signal;
 
       // Optional: abort the request after a timeout
       //setTimeout(() => controller.abort(), 2 * 60 * 1000);
 
       // const headers = new Headers(c.req.header());
-      return worker.fetch(c.req.raw, signal);
+      const response = await worker.fetch(c.req.raw, signal);
+      const status = response.ok ? "succeeded" : "failed";
+      // A streamed response keeps the execution running until its body ends.
+      const body = response.body
+        ? finishWithBody(response.body, execution.id, status)
+        : null;
+      if (!body) {
+        finishExecution(execution.id, status);
+      }
+      // Copied since responses from the worker have immutable headers.
+      const headers = new Headers(response.headers);
+      headers.set("x-craftgen-execution-id", execution.id);
+      return new Response(body, {
+        status: response.status,
+        statusText: response.statusText,
+        headers,
+      });
     } catch (e) {
       console.error("THE ERROR:", e);
       if (e instanceof Deno.errors.WorkerRequestCancelled) {
+        if (executionId) finishExecution(executionId, "cancelled");
         c.status(STATUS_CODE.RequestTimeout);
         return c.json({ message: "Cancelled" });
       }
 
+      if (executionId) finishExecution(executionId, "failed");
       c.status(STATUS_CODE.InternalServerError);
       return c.json({ message: e.toString() });
     }
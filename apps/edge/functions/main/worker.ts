@@ -1,6 +1,10 @@
+export type ModulePermission = "net" | "env";
+
 export const createWorker = async (params: {
   moduleCode?: string;
   servicePath?: string;
+  entrypoint?: string;
+  permissions?: ModulePermission[];
 }) => {
   const memoryLimitMb = 150;
   const workerTimeoutMs = 5 * 60 * 1000;
@@ -16,10 +20,13 @@ export const createWorker = async (params: {
 
   // const importMapPath = `data:${encodeURIComponent(JSON.stringify(importMap))}?${encodeURIComponent('/home/deno/functions/test')}`;
   const importMapPath = null;
-  const envVarsObj = Deno.env.toObject();
+  // Modules installed with a manifest only get what they asked for.
+  const permissions = params.permissions;
+  const envVarsObj =
+    !permissions || permissions.includes("env") ? Deno.env.toObject() : {};
   const envVars = Object.keys(envVarsObj).map((k) => [k, envVarsObj[k]]);
   const forceCreate = true;
-  const netAccessDisabled = false;
+  const netAccessDisabled = permissions ? !permissions.includes("net") : false;
 
   // load source from an eszip
   //const maybeEszip = await Deno.readFile('./bin.eszip');
@@ -70,7 +77,7 @@ export const createWorker = async (params: {
     cpuTimeHardLimitMs,
 
     // maybeEszip,
-    // maybeEntrypoint,
+    maybeEntrypoint: params.entrypoint,
     // maybeModuleCode: maybeModuleCode,
   });
 };
@@ -8,3 +8,101 @@ export const createKv = async (params: { orgId: OrgId }) => {
 
   return kv;
 };
+
+export type JobState = "pending" | "leased" | "succeeded" | "dead";
+
+/** Times are milliseconds since the unix epoch. */
+export type Job<T = unknown> = {
+  id: string;
+  queue: string;
+  payload: T;
+  state: JobState;
+  attempts: number;
+  maxAttempts: number;
+  runAt: number;
+  leasedUntil: number | null;
+  lastError: string | null;
+  createdAt: number;
+  updatedAt: number;
+};
+
+export type Lease<T = unknown> = { job: Job<T>; token: string };
+
+export type QueueStats = {
+  queue: string;
+  pending: number;
+  leased: number;
+  succeeded: number;
+  dead: number;
+  nextRunAt: number | null;
+};
+
+export class QueueError extends Error {
+  constructor(
+    /** `notFound`, `leaseLost`, `invalidInput`, `unauthorized` or `io`. */
+    readonly kind: string,
+    message: string,
+  ) {
+    super(message);
+  }
+}
+
+/**
+ * Client for the job queue the desktop app keeps in SQLite, so jobs survive
+ * runtime restarts. The app passes its address as `CRAFTGEN_QUEUE_URL` and
+ * `CRAFTGEN_QUEUE_TOKEN`.
+ */
+export const createQueue = (params?: { url?: string; token?: string }) => {
+  const url = params?.url ?? Deno.env.get("CRAFTGEN_QUEUE_URL");
+  const token = params?.token ?? Deno.env.get("CRAFTGEN_QUEUE_TOKEN");
+  if (!url || !token) {
+    throw new Error("CRAFTGEN_QUEUE_URL and CRAFTGEN_QUEUE_TOKEN are not set");
+  }
+
+  const request = async <T>(method: string, path: string, body?: unknown) => {
+    const response = await fetch(`${url}${path}`, {
+      method,
+      headers: {
+        authorization: `Bearer ${token}`,
+        "content-type": "application/json",
+      },
+      body: body === undefined ? undefined : JSON.stringify(body),
+    });
+    const json = await response.json();
+    if (!response.ok) throw new QueueError(json.kind, json.message);
+    return json as T;
+  };
+
+  return {
+    enqueue: <T>(
+      queue: string,
+      payload: T,
+      options?: { delayMs?: number; maxAttempts?: number },
+    ) => request<Job<T>>("POST", "/jobs", { queue, payload, ...options }),
+    get: <T>(id: string) => request<Job<T>>("GET", `/jobs/${id}`),
+    /** Due jobs, oldest first. Each lease counts as an attempt. */
+    lease: <T>(
+      queue: string,
+      options?: { limit?: number; leaseMs?: number },
+    ) =>
+      request<Lease<T>[]>(
+        "POST",
+        `/queues/${encodeURIComponent(queue)}/lease`,
+        options ?? {},
+      ),
+    ack: (lease: Lease) =>
+      request<Job>("POST", `/jobs/${lease.job.id}/ack`, { token: lease.token }),
+    /** Retries after `delayMs` or a backoff, or dead-letters the last attempt. */
+    nack: (lease: Lease, options?: { error?: string; delayMs?: number }) =>
+      request<Job>("POST", `/jobs/${lease.job.id}/nack`, {
+        token: lease.token,
+        ...options,
+      }),
+    extend: (lease: Lease, leaseMs?: number) =>
+      request<Job>("POST", `/jobs/${lease.job.id}/extend`, {
+        token: lease.token,
+        leaseMs,
+      }),
+    stats: () => request<QueueStats[]>("GET", "/queues"),
+  };
+};